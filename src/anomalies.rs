@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use crate::distribution::{quantile, sorted};
+use crate::models::{AnomalyKind, EntryAnomaly, FuelEntry};
+
+// Robust z-score above which a price or volume counts as an outlier
+const DEFAULT_ANOMALY_THRESHOLD: f64 = 3.5;
+
+// Fewer entries of a fuel type or vehicle say too little about what is usual
+const MIN_HISTORY_ENTRIES: usize = 5;
+
+// Scales the median absolute deviation to a standard deviation for normally
+// distributed values
+const MAD_SCALE: f64 = 1.4826;
+
+// Lower bound on the spread relative to the median, so a user who always pays
+// the same price is not flagged for every small change
+const MIN_RELATIVE_SPREAD: f64 = 0.1;
+
+// Pumps usually stop a little above the rated capacity
+const TANK_CAPACITY_TOLERANCE: f64 = 0.05;
+
+// Consumption outside this range between two odometer readings means one of
+// the readings or volumes is wrong rather than an unusual drive
+const MIN_PLAUSIBLE_LITERS_PER_100KM: f64 = 1.0;
+const MAX_PLAUSIBLE_LITERS_PER_100KM: f64 = 50.0;
+
+pub fn anomaly_threshold() -> f64 {
+    std::env::var("ANOMALY_THRESHOLD")
+        .ok()
+        .and_then(|threshold| threshold.parse::<f64>().ok())
+        .filter(|threshold| threshold.is_finite() && *threshold > 0.0)
+        .unwrap_or(DEFAULT_ANOMALY_THRESHOLD)
+}
+
+// Median and robust spread of a group of values
+struct Usual {
+    median: f64,
+    spread: f64,
+}
+
+impl Usual {
+    fn of(values: &[f64]) -> Option<Self> {
+        if values.len() < MIN_HISTORY_ENTRIES {
+            return None;
+        }
+        let values = sorted(values.iter().copied());
+        let median = quantile(&values, 0.5);
+        let deviations = sorted(values.iter().map(|value| (value - median).abs()));
+        let spread = (MAD_SCALE * quantile(&deviations, 0.5)).max(MIN_RELATIVE_SPREAD * median);
+        if spread > 0.0 {
+            Some(Usual { median, spread })
+        } else {
+            None
+        }
+    }
+
+    // Robust z-score; positive above the median
+    fn score(&self, value: f64) -> f64 {
+        (value - self.median) / self.spread
+    }
+
+    fn lower_limit(&self, threshold: f64) -> f64 {
+        (self.median - threshold * self.spread).max(0.0)
+    }
+
+    fn upper_limit(&self, threshold: f64) -> f64 {
+        self.median + threshold * self.spread
+    }
+}
+
+fn vehicle_key(entry: &FuelEntry) -> Option<String> {
+    entry.vehicle.as_ref().map(|vehicle| vehicle.to_lowercase())
+}
+
+// Anomalies of each of one user's live `entries` (sorted by date), in the same
+// order. Prices are compared with the user's other entries of the same fuel
+// type and volumes with those of the same vehicle. `tank_capacities` is keyed
+// by lowercase vehicle name.
+pub fn detect_anomalies(
+    entries: &[FuelEntry],
+    tank_capacities: &HashMap<String, f64>,
+    threshold: f64,
+) -> Vec<Vec<EntryAnomaly>> {
+    let mut prices: HashMap<Option<&str>, Vec<f64>> = HashMap::new();
+    let mut volumes: HashMap<Option<String>, Vec<f64>> = HashMap::new();
+    for entry in entries {
+        prices
+            .entry(entry.fuel_type.as_deref())
+            .or_default()
+            .push(entry.price_per_liter);
+        volumes
+            .entry(vehicle_key(entry))
+            .or_default()
+            .push(entry.liters);
+    }
+    let usual_prices: HashMap<Option<&str>, Usual> = prices
+        .into_iter()
+        .filter_map(|(fuel_type, values)| Usual::of(&values).map(|usual| (fuel_type, usual)))
+        .collect();
+    let usual_volumes: HashMap<Option<String>, Usual> = volumes
+        .into_iter()
+        .filter_map(|(vehicle, values)| Usual::of(&values).map(|usual| (vehicle, usual)))
+        .collect();
+
+    let mut anomalies = vec![Vec::new(); entries.len()];
+    // Index and reading of each vehicle's latest entry with an odometer reading so far
+    let mut previous_readings: HashMap<Option<String>, (usize, f64)> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let found = &mut anomalies[index];
+        let vehicle = vehicle_key(entry);
+
+        if let Some(usual) = usual_prices.get(&entry.fuel_type.as_deref()) {
+            if usual.score(entry.price_per_liter).abs() > threshold {
+                found.push(EntryAnomaly {
+                    kind: AnomalyKind::PriceOutlier,
+                    message: format!(
+                        "price per liter {:.3} is far from the usual {:.3} (expected {:.3} to {:.3})",
+                        entry.price_per_liter,
+                        usual.median,
+                        usual.lower_limit(threshold),
+                        usual.upper_limit(threshold)
+                    ),
+                });
+            }
+        }
+
+        // Partial fill-ups are normal, so only volumes above the usual count
+        if let Some(usual) = usual_volumes.get(&vehicle) {
+            if usual.score(entry.liters) > threshold {
+                found.push(EntryAnomaly {
+                    kind: AnomalyKind::VolumeOutlier,
+                    message: format!(
+                        "{:.2} liters is far above the usual {:.2} (expected at most {:.2})",
+                        entry.liters,
+                        usual.median,
+                        usual.upper_limit(threshold)
+                    ),
+                });
+            }
+        }
+
+        if let Some(capacity) = vehicle.as_ref().and_then(|v| tank_capacities.get(v)) {
+            if entry.liters > capacity * (1.0 + TANK_CAPACITY_TOLERANCE) {
+                found.push(EntryAnomaly {
+                    kind: AnomalyKind::ExceedsTankCapacity,
+                    message: format!(
+                        "{:.2} liters do not fit the {:.1} liter tank of {}",
+                        entry.liters,
+                        capacity,
+                        entry.vehicle.as_deref().unwrap_or_default()
+                    ),
+                });
+            }
+        }
+
+        // Every fill-up of the vehicle since its previous reading was burnt over
+        // that distance
+        if let Some(reading) = entry.odometer_reading {
+            if let Some(&(previous_index, previous)) = previous_readings.get(&vehicle) {
+                let distance = reading - previous;
+                if distance > 0.0 {
+                    let liters: f64 = entries[previous_index + 1..=index]
+                        .iter()
+                        .filter(|other| vehicle_key(other) == vehicle)
+                        .map(|other| other.liters)
+                        .sum();
+                    let per_100km = liters / distance * 100.0;
+                    if !(MIN_PLAUSIBLE_LITERS_PER_100KM..=MAX_PLAUSIBLE_LITERS_PER_100KM)
+                        .contains(&per_100km)
+                    {
+                        found.push(EntryAnomaly {
+                            kind: AnomalyKind::ImpossibleEconomy,
+                            message: format!(
+                                "{:.2} liters over {:.0} km since the previous odometer reading is {:.1} L/100 km",
+                                liters, distance, per_100km
+                            ),
+                        });
+                    }
+                }
+            }
+            previous_readings.insert(vehicle, (index, reading));
+        }
+    }
+
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(
+        day: u32,
+        vehicle: Option<&str>,
+        liters: f64,
+        price_per_liter: f64,
+        odometer_reading: Option<f64>,
+    ) -> FuelEntry {
+        serde_json::from_value(json!({
+            "id": format!("e{}", day),
+            "user_id": "u1",
+            "liters": liters,
+            "price_per_liter": price_per_liter,
+            "total_cost": liters * price_per_liter,
+            "date_time": format!("2026-03-{:02}T08:00:00Z", day),
+            "vehicle": vehicle,
+            "odometer_reading": odometer_reading,
+            "deleted_at": null,
+        }))
+        .unwrap()
+    }
+
+    fn kinds(anomalies: &[EntryAnomaly]) -> Vec<AnomalyKind> {
+        anomalies.iter().map(|anomaly| anomaly.kind).collect()
+    }
+
+    #[test]
+    fn flags_prices_far_from_the_usual() {
+        let mut entries: Vec<FuelEntry> = [1.78, 1.80, 1.82, 1.79, 1.81]
+            .iter()
+            .enumerate()
+            .map(|(day, price)| entry(day as u32 + 1, None, 40.0, *price, None))
+            .collect();
+        entries.push(entry(6, None, 40.0, 3.50, None));
+
+        let found = detect_anomalies(&entries, &HashMap::new(), DEFAULT_ANOMALY_THRESHOLD);
+        assert!(found[..5].iter().all(Vec::is_empty));
+        assert_eq!(kinds(&found[5]), [AnomalyKind::PriceOutlier]);
+    }
+
+    #[test]
+    fn short_histories_are_not_judged() {
+        let entries = [
+            entry(1, None, 40.0, 1.80, None),
+            entry(2, None, 40.0, 1.80, None),
+            entry(3, None, 40.0, 9.00, None),
+        ];
+
+        let found = detect_anomalies(&entries, &HashMap::new(), DEFAULT_ANOMALY_THRESHOLD);
+        assert!(found.iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn flags_volumes_above_the_tank_capacity() {
+        let entries = [
+            entry(1, Some("Golf"), 50.0, 1.80, None),
+            entry(2, Some("Golf"), 62.0, 1.80, None),
+            entry(3, Some("Polo"), 62.0, 1.80, None),
+        ];
+        let capacities = HashMap::from([("golf".to_string(), 55.0)]);
+
+        let found = detect_anomalies(&entries, &capacities, DEFAULT_ANOMALY_THRESHOLD);
+        assert!(found[0].is_empty());
+        assert_eq!(kinds(&found[1]), [AnomalyKind::ExceedsTankCapacity]);
+        assert!(found[2].is_empty());
+    }
+
+    #[test]
+    fn economy_is_checked_per_vehicle() {
+        // Two cars filled up alternately, each at a plausible 7 L/100 km
+        let entries = [
+            entry(1, Some("Golf"), 35.0, 1.80, Some(1_000.0)),
+            entry(2, Some("Van"), 30.0, 1.80, Some(50_000.0)),
+            entry(3, Some("golf"), 35.0, 1.80, Some(1_500.0)),
+            entry(4, Some("Van"), 30.0, 1.80, Some(50_400.0)),
+        ];
+
+        let found = detect_anomalies(&entries, &HashMap::new(), DEFAULT_ANOMALY_THRESHOLD);
+        assert!(found.iter().all(Vec::is_empty), "{:?}", found);
+    }
+
+    #[test]
+    fn flags_impossible_economy() {
+        let entries = [
+            entry(1, Some("Golf"), 35.0, 1.80, Some(1_000.0)),
+            entry(2, Some("Van"), 30.0, 1.80, None),
+            entry(3, Some("Golf"), 80.0, 1.80, Some(1_100.0)),
+        ];
+
+        let found = detect_anomalies(&entries, &HashMap::new(), DEFAULT_ANOMALY_THRESHOLD);
+        assert_eq!(kinds(&found[2]), [AnomalyKind::ImpossibleEconomy]);
+        assert!(found[2][0].message.starts_with("80.00 liters over 100 km"));
+    }
+}
@@ -1,4 +1,13 @@
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+const ADMIN_TOKEN_PREFIX: &str = "admin_token_";
+
+const DEFAULT_ADMIN_TOKEN_TTL_HOURS: i64 = 12;
 
 pub fn hash_password(password: &str) -> Result<String> {
     let hashed = bcrypt::hash(password, bcrypt::DEFAULT_COST)?;
@@ -8,4 +17,117 @@ pub fn hash_password(password: &str) -> Result<String> {
 pub fn verify_password(password: &str, hash: &str) -> Result<bool> {
     let is_valid = bcrypt::verify(password, hash)?;
     Ok(is_valid)
-}
\ No newline at end of file
+}
+
+pub fn admin_token_ttl_hours() -> i64 {
+    std::env::var("ADMIN_TOKEN_TTL_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse::<i64>().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(DEFAULT_ADMIN_TOKEN_TTL_HOURS)
+}
+
+// Key for signing admin tokens. Without `ADMIN_TOKEN_SECRET` a random key is
+// generated at startup, so tokens stop working when the server restarts.
+fn admin_token_secret() -> &'static [u8] {
+    static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+    SECRET.get_or_init(|| match std::env::var("ADMIN_TOKEN_SECRET") {
+        Ok(secret) if !secret.trim().is_empty() => secret.into_bytes(),
+        _ => format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()).into_bytes(),
+    })
+}
+
+pub fn admin_token_secret_configured() -> bool {
+    std::env::var("ADMIN_TOKEN_SECRET").is_ok_and(|secret| !secret.trim().is_empty())
+}
+
+fn admin_token_mac(expires_at: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(admin_token_secret())
+        .expect("HMAC accepts keys of any size");
+    mac.update(format!("admin:{}", expires_at).as_bytes());
+    mac
+}
+
+// "admin_token_<expiry in unix seconds>.<hex HMAC-SHA256 of the expiry>"
+pub fn issue_admin_token(now: DateTime<Utc>) -> (String, DateTime<Utc>) {
+    let expires_at = now + Duration::hours(admin_token_ttl_hours());
+    let signature = admin_token_mac(expires_at.timestamp())
+        .finalize()
+        .into_bytes();
+    let token = format!(
+        "{}{}.{:x}",
+        ADMIN_TOKEN_PREFIX,
+        expires_at.timestamp(),
+        signature
+    );
+    (token, expires_at)
+}
+
+// True for a token issued by `issue_admin_token` that has not expired yet
+pub fn verify_admin_token(token: &str, now: DateTime<Utc>) -> bool {
+    let Some((expires_at, signature)) = token
+        .strip_prefix(ADMIN_TOKEN_PREFIX)
+        .and_then(|rest| rest.split_once('.'))
+    else {
+        return false;
+    };
+    let Ok(expires_at) = expires_at.parse::<i64>() else {
+        return false;
+    };
+    let Some(signature) = decode_hex(signature) else {
+        return false;
+    };
+
+    expires_at > now.timestamp() && admin_token_mac(expires_at).verify_slice(&signature).is_ok()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issued_admin_tokens_verify_until_they_expire() {
+        let now = Utc::now();
+        let (token, expires_at) = issue_admin_token(now);
+
+        assert!(verify_admin_token(&token, now));
+        assert!(verify_admin_token(
+            &token,
+            expires_at - Duration::seconds(1)
+        ));
+        assert!(!verify_admin_token(&token, expires_at));
+    }
+
+    #[test]
+    fn rejects_forged_admin_tokens() {
+        let now = Utc::now();
+        let (token, _) = issue_admin_token(now);
+
+        assert!(!verify_admin_token("admin_token_x", now));
+        assert!(!verify_admin_token("admin_token_99999999999.00", now));
+
+        // Moving the expiry invalidates the signature
+        let (expiry, signature) = token
+            .strip_prefix(ADMIN_TOKEN_PREFIX)
+            .unwrap()
+            .split_once('.')
+            .unwrap();
+        let extended = format!(
+            "{}{}.{}",
+            ADMIN_TOKEN_PREFIX,
+            expiry.parse::<i64>().unwrap() + 3600,
+            signature
+        );
+        assert!(!verify_admin_token(&extended, now));
+    }
+}
@@ -0,0 +1,180 @@
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::models::{Budget, BudgetPeriod, BudgetProgress};
+
+// Percentages of a budget that raise an alert when spending reaches them
+pub const BUDGET_ALERT_THRESHOLDS: [i64; 2] = [80, 100];
+
+// The pace is measured over at least a day, so a fill-up in the first hours of
+// a period does not project an absurd total
+const MIN_PROJECTION_DAYS: i64 = 1;
+
+// UTC instant of local midnight at the start of `date`. Where midnight is
+// skipped by a DST change, the day starts at the first local time after it.
+fn local_midnight(time_zone: Tz, date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    time_zone
+        .from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| {
+            time_zone
+                .from_local_datetime(&(midnight + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|start| start.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+}
+
+// Start, end (exclusive) and label of the calendar month or year of
+// `time_zone` that contains `now`
+pub fn current_period(
+    period: BudgetPeriod,
+    time_zone: Tz,
+    now: DateTime<Utc>,
+) -> (DateTime<Utc>, DateTime<Utc>, String) {
+    let today = now.with_timezone(&time_zone).date_naive();
+    let (start, end, label) = match period {
+        BudgetPeriod::Monthly => {
+            let start = today.with_day(1).unwrap_or(today);
+            let end = start.checked_add_months(Months::new(1));
+            (start, end, start.format("%Y-%m").to_string())
+        }
+        BudgetPeriod::Yearly => {
+            let start = NaiveDate::from_ymd_opt(today.year(), 1, 1).unwrap_or(today);
+            let end = NaiveDate::from_ymd_opt(today.year() + 1, 1, 1);
+            (start, end, today.year().to_string())
+        }
+    };
+    let end = end.unwrap_or(start);
+    (
+        local_midnight(time_zone, start),
+        local_midnight(time_zone, end),
+        label,
+    )
+}
+
+// Progress of `budget` given what was `spent` so far in the period from
+// `start` to `end`
+pub fn budget_progress(
+    budget: Budget,
+    spent: f64,
+    (start, end, period_label): (DateTime<Utc>, DateTime<Utc>, String),
+    now: DateTime<Utc>,
+) -> BudgetProgress {
+    let length = (end - start).num_seconds().max(1) as f64;
+    let elapsed = (now - start)
+        .max(Duration::days(MIN_PROJECTION_DAYS))
+        .num_seconds()
+        .min(length as i64) as f64;
+    let projected_spend = spent * length / elapsed;
+
+    let percent_of_budget = |value: f64| {
+        if budget.amount > 0.0 {
+            value / budget.amount * 100.0
+        } else {
+            0.0
+        }
+    };
+    let status = if spent >= budget.amount {
+        "over_budget"
+    } else if projected_spend > budget.amount {
+        "at_risk"
+    } else {
+        "on_track"
+    };
+
+    BudgetProgress {
+        period_label,
+        period_start: start,
+        period_end: end,
+        spent,
+        remaining: (budget.amount - spent).max(0.0),
+        percent_used: percent_of_budget(spent),
+        projected_spend,
+        projected_percent: percent_of_budget(projected_spend),
+        status: status.to_string(),
+        budget,
+    }
+}
+
+// Thresholds the spending has reached, lowest first
+pub fn crossed_thresholds(progress: &BudgetProgress) -> impl Iterator<Item = i64> + '_ {
+    BUDGET_ALERT_THRESHOLDS
+        .into_iter()
+        .filter(|threshold| progress.percent_used >= *threshold as f64)
+}
+
+pub fn alert_message(progress: &BudgetProgress, threshold: i64) -> String {
+    let scope = match &progress.budget.vehicle {
+        Some(vehicle) => format!("{} budget for {}", progress.budget.period, vehicle),
+        None => format!("{} budget", progress.budget.period),
+    };
+    if threshold >= 100 {
+        format!(
+            "You have reached your {} for {}: {:.2} spent of {:.2}",
+            scope, progress.period_label, progress.spent, progress.budget.amount
+        )
+    } else {
+        format!(
+            "You have used {}% of your {} for {}: {:.2} spent of {:.2}",
+            threshold, scope, progress.period_label, progress.spent, progress.budget.amount
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn months_span_a_dst_change() {
+        // Berlin moves from UTC+1 to UTC+2 on 29 March 2026
+        let (start, end, label) = current_period(
+            BudgetPeriod::Monthly,
+            chrono_tz::Europe::Berlin,
+            utc("2026-03-15T12:00:00Z"),
+        );
+
+        assert_eq!(label, "2026-03");
+        assert_eq!(start, utc("2026-02-28T23:00:00Z"));
+        assert_eq!(end, utc("2026-03-31T22:00:00Z"));
+    }
+
+    #[test]
+    fn periods_follow_the_local_date() {
+        // Already 1 April, 00:30 in Berlin
+        let now = utc("2026-03-31T22:30:00Z");
+
+        let (start, _, label) =
+            current_period(BudgetPeriod::Monthly, chrono_tz::Europe::Berlin, now);
+        assert_eq!(label, "2026-04");
+        assert_eq!(start, utc("2026-03-31T22:00:00Z"));
+
+        let (_, _, label) = current_period(BudgetPeriod::Monthly, Tz::UTC, now);
+        assert_eq!(label, "2026-03");
+
+        let (start, end, label) = current_period(
+            BudgetPeriod::Yearly,
+            chrono_tz::America::New_York,
+            utc("2027-01-01T03:00:00Z"),
+        );
+        assert_eq!(label, "2026");
+        assert_eq!(start, utc("2026-01-01T05:00:00Z"));
+        assert_eq!(end, utc("2027-01-01T05:00:00Z"));
+    }
+
+    #[test]
+    fn days_without_a_midnight_start_at_the_first_local_time() {
+        // Santiago skips from 00:00 to 01:00 on 6 September 2026
+        let date = NaiveDate::from_ymd_opt(2026, 9, 6).unwrap();
+        assert_eq!(
+            local_midnight(chrono_tz::America::Santiago, date),
+            utc("2026-09-06T04:00:00Z")
+        );
+    }
+}
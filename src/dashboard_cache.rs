@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+
+use crate::models::{DashboardFilter, DashboardSection};
+
+const DEFAULT_DASHBOARD_CACHE_TTL_SECS: u64 = 300;
+
+// Upper bound on distinct filter/section combinations kept in memory
+const MAX_CACHED_DASHBOARDS: usize = 64;
+
+// 0 turns the cache off
+pub fn dashboard_cache_ttl_secs() -> u64 {
+    std::env::var("DASHBOARD_CACHE_TTL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_DASHBOARD_CACHE_TTL_SECS)
+}
+
+struct CachedDashboard {
+    data_version: String,
+    stored_at: Instant,
+    stats: Value,
+}
+
+// Computed dashboard responses keyed by filter and sections. An entry is served
+// until the TTL runs out or the data version changes, so any write to entries
+// or users makes the next request recompute instead of waiting for the TTL.
+#[derive(Clone)]
+pub struct DashboardCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, CachedDashboard>>>,
+}
+
+impl DashboardCache {
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            ttl: Duration::from_secs(ttl_secs),
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero()
+    }
+
+    pub fn key(filter: &DashboardFilter, sections: &[DashboardSection]) -> String {
+        // Same sections in a different order share an entry
+        let sections: Vec<&str> = DashboardSection::ALL
+            .iter()
+            .filter(|section| sections.contains(section))
+            .map(|section| section.name())
+            .collect();
+        format!("{:?}|{}", filter, sections.join(","))
+    }
+
+    pub fn get(&self, key: &str, data_version: &str) -> Option<Value> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|cached| {
+                cached.data_version == data_version && cached.stored_at.elapsed() < self.ttl
+            })
+            .map(|cached| cached.stats.clone())
+    }
+
+    pub fn insert(&self, key: String, data_version: String, stats: Value) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, cached| {
+            cached.data_version == data_version && cached.stored_at.elapsed() < self.ttl
+        });
+        if entries.len() >= MAX_CACHED_DASHBOARDS && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            CachedDashboard {
+                data_version,
+                stored_at: Instant::now(),
+                stats,
+            },
+        );
+    }
+}
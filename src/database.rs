@@ -1,19 +1,84 @@
 use anyhow::Result;
-use chrono::{Datelike, Utc};
-use sqlx::SqlitePool;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::fmt;
 use std::fs;
 use uuid::Uuid;
 
+use crate::anomalies::{anomaly_threshold, detect_anomalies};
+use crate::budgets::{alert_message, budget_progress, crossed_thresholds, current_period};
+use crate::distribution::{
+    cost_histogram, cost_histogram_edges, mean_and_std_dev, sorted, summarize,
+};
+use crate::duplicates::{same_fuel_data, DuplicatePolicy};
+use crate::forecast::{
+    backtest, month_from_index, month_index, MonthlyModel, PREDICTION_INTERVAL_LEVEL,
+};
 use crate::models::{
-    ActivityPatterns, BudgetAnalysis, ConsumptionForecast, ConsumptionPatterns, CostAnalytics,
-    CostDistribution, CostPerUserStats, CostRange, CreateFuelEntryRequest, DashboardStats,
-    EngagementMetrics, FeatureUsageStats, FillUpPatterns, FuelEfficiencyStats, FuelEntry,
-    FuelEntryDB, FuelEntryData, MonthlyStats, OdometerAnalytics, PredictiveAnalytics,
-    PriceComparisons, PriceForecast, PriceTrends, PriceVolatility, RegionalPrice,
-    RetentionAnalysis, RevenueProjections, ServiceConfig, ServiceStatus, UpdateFuelEntryRequest,
-    User, UserActivity, UserBehaviorStats, UserEfficiency, UserEntryCount, UserGrowthForecast,
-    UserRegistrationStats, UserSpending, WeeklyPattern,
+    ActivityPatterns, Actor, AnomalyStatus, Budget, BudgetAlert, BudgetAnalysis, BudgetPeriod,
+    BudgetProgress, BulkEntryResult, BulkEntryStatus, BulkMode, BulkUpdateFuelEntriesRequest,
+    BulkUpdateResult, BulkUpdateStatus, ConsumptionForecast, ConsumptionPatterns, CostAnalytics,
+    CostDistribution, CostPerUserStats, CreateBudgetRequest, CreateFuelEntryRequest,
+    CreateNotificationChannelRequest, CreateWebhookRequest, CreatedWebhook, DailyPattern,
+    DailyPriceTrend, DashboardFilter, DashboardSection, DashboardStats, DashboardSummary,
+    DuplicateCheck, EconomyTrendPoint, EfficiencyTrend, EngagementMetrics, FeatureUsageStats,
+    FillGap, FillUpPatterns, FuelEfficiencyStats, FuelEntry, FuelEntryData, FuelEntryFilter,
+    FuelEntryListQuery, FuelEntryPage, FuelEntryRevision, FuelEntrySort, IdempotencyRecord,
+    MonthlyStats, NotificationChannelConfig, NotificationChannelKind, OdometerAnalytics,
+    PeriodVolatility, PredictiveAnalytics, PriceComparisons, PriceForecast, PriceTrends,
+    PriceVolatility, RegionalPrice, RetentionAnalysis, RevenueProjections, SeasonalPattern,
+    ServiceConfig, ServiceStatus, SetVehicleRequest, SignupCohort, SortOrder, SyncChange,
+    SyncChanges, SyncOperation, SyncResult, SyncStatus, UpdateBudgetRequest,
+    UpdateFuelEntryRequest, UpdateUserSettingsRequest, User, UserActivity, UserBehaviorStats,
+    UserCostCategory, UserEfficiency, UserEntryCount, UserGrowthForecast, UserRegistrationStats,
+    UserSettings, UserSpending, UserStats, Vehicle, Webhook, WebhookDelivery,
+    WebhookDeliveryAttempt, WebhookDeliveryLog, WebhookDeliveryStatus, WebhookEvent, WeeklyPattern,
+    YearComparison,
 };
+use crate::pricing::{resolve_amounts, resolve_updated_amounts};
+use crate::validation::{
+    normalize_fuel_type, normalize_tags, normalize_vehicle, validate_budget,
+    validate_budget_amount, validate_fuel_entry, validate_notification_target,
+    validate_odometer_order, validate_user_settings, validate_vehicle, validate_webhook,
+    ValidationError, DEFAULT_HEMISPHERE, DEFAULT_TIME_ZONE,
+};
+use crate::webhooks::{new_webhook_secret, retry_delay, WEBHOOK_MAX_ATTEMPTS};
+
+const FUEL_ENTRY_COLUMNS: &str = "id, user_id, liters, price_per_liter, total_cost, date_time, \
+     odometer_reading, vehicle, fuel_type, tags, deleted_at, version, anomalies, anomaly_status";
+
+// Returned when a write names an expected version (from `If-Match`) that is no
+// longer the stored one, i.e. someone else changed the entry in the meantime.
+#[derive(Debug)]
+pub struct VersionConflict {
+    pub current_version: i64,
+}
+
+impl fmt::Display for VersionConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the fuel entry was modified concurrently (current version {})",
+            self.current_version
+        )
+    }
+}
+
+impl std::error::Error for VersionConflict {}
+
+fn check_version(entry: &FuelEntry, expected_version: Option<i64>) -> Result<()> {
+    match expected_version {
+        Some(expected) if expected != entry.version => Err(VersionConflict {
+            current_version: entry.version,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
 
 pub async fn create_database_pool() -> Result<SqlitePool> {
     // Create database file if it doesn't exist
@@ -26,199 +91,512 @@ pub async fn create_database_pool() -> Result<SqlitePool> {
     Ok(pool)
 }
 
-pub async fn create_tables(pool: &SqlitePool) -> Result<()> {
-    sqlx::query(
+pub async fn create_user(pool: &SqlitePool, email: &str, password_hash: &str) -> Result<User> {
+    let id = Uuid::new_v4().to_string();
+    let created_at = Utc::now();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("INSERT INTO users (id, email, password_hash, created_at) VALUES (?, ?, ?, ?)")
+        .bind(&id)
+        .bind(email)
+        .bind(password_hash)
+        .bind(created_at)
+        .execute(&mut *tx)
+        .await?;
+
+    let user = User {
+        id,
+        email: email.to_string(),
+        password_hash: password_hash.to_string(),
+        created_at,
+        deleted_at: None,
+        time_zone: DEFAULT_TIME_ZONE.to_string(),
+        hemisphere: DEFAULT_HEMISPHERE.to_string(),
+    };
+    enqueue_webhook_event(
+        &mut tx,
+        WebhookEvent::UserCreated,
+        &user.id,
+        user_webhook_data(&user),
+    )
+    .await?;
+    bump_data_version(&mut tx).await?;
+    tx.commit().await?;
+
+    Ok(user)
+}
+
+pub async fn get_user_settings(pool: &SqlitePool, user_id: &str) -> Result<Option<UserSettings>> {
+    let settings: Option<(String, String)> = sqlx::query_as(
+        "SELECT time_zone, hemisphere FROM users WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(settings.map(|(time_zone, hemisphere)| UserSettings {
+        user_id: user_id.to_string(),
+        time_zone,
+        hemisphere,
+    }))
+}
+
+// Falls back to UTC for unknown users, like the analytics do
+async fn get_user_time_zone(conn: &mut SqliteConnection, user_id: &str) -> Result<Tz> {
+    let time_zone: Option<String> = sqlx::query_scalar("SELECT time_zone FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    Ok(time_zone
+        .and_then(|time_zone| time_zone.parse().ok())
+        .unwrap_or(Tz::UTC))
+}
+
+// Returns the settings after the update, or None for an unknown user.
+// Invalid values fail with a `ValidationError`.
+pub async fn update_user_settings(
+    pool: &SqlitePool,
+    user_id: &str,
+    request: &UpdateUserSettingsRequest,
+) -> Result<Option<UserSettings>> {
+    validate_user_settings(request).into_result()?;
+
+    let mut tx = pool.begin().await?;
+    let updated = sqlx::query(
         r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id TEXT PRIMARY KEY,
-            email TEXT UNIQUE NOT NULL,
-            password_hash TEXT NOT NULL,
-            created_at TEXT NOT NULL
-        )
+        UPDATE users
+        SET time_zone = COALESCE(?, time_zone), hemisphere = COALESCE(?, hemisphere)
+        WHERE id = ? AND deleted_at IS NULL
         "#,
     )
-    .execute(pool)
+    .bind(request.time_zone.as_deref().map(str::trim))
+    .bind(
+        request
+            .hemisphere
+            .as_deref()
+            .map(|hemisphere| hemisphere.trim().to_lowercase()),
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
     .await?;
+    // Analytics are bucketed by local time and season
+    if updated.rows_affected() > 0 {
+        bump_data_version(&mut tx).await?;
+    }
+    tx.commit().await?;
+
+    get_user_settings(pool, user_id).await
+}
 
+pub async fn get_user_by_email(pool: &SqlitePool, email: &str) -> Result<Option<User>> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = ?")
+        .bind(email)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(user)
+}
+
+async fn insert_fuel_entry(conn: &mut SqliteConnection, entry: &FuelEntry) -> Result<()> {
     sqlx::query(
         r#"
-        CREATE TABLE IF NOT EXISTS fuel_entries (
-            id TEXT PRIMARY KEY,
-            user_id TEXT NOT NULL,
-            data TEXT NOT NULL,
-            FOREIGN KEY (user_id) REFERENCES users (id)
-        )
+        INSERT INTO fuel_entries
+            (id, user_id, liters, price_per_liter, total_cost, date_time, odometer_reading,
+             vehicle, fuel_type, tags)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
     )
-    .execute(pool)
+    .bind(&entry.id)
+    .bind(&entry.user_id)
+    .bind(entry.liters)
+    .bind(entry.price_per_liter)
+    .bind(entry.total_cost)
+    .bind(entry.date_time)
+    .bind(entry.odometer_reading)
+    .bind(&entry.vehicle)
+    .bind(&entry.fuel_type)
+    .bind(&entry.tags)
+    .execute(conn)
     .await?;
 
-    // Create service_config table
-    sqlx::query(
+    Ok(())
+}
+
+// Closest entries of the same user and vehicle before and after `entry` that
+// carry an odometer reading. Entries without a vehicle only count each other.
+async fn get_odometer_neighbours(
+    conn: &mut SqliteConnection,
+    entry: &FuelEntry,
+) -> Result<(Option<FuelEntry>, Option<FuelEntry>)> {
+    let previous = sqlx::query_as::<_, FuelEntry>(&format!(
         r#"
-        CREATE TABLE IF NOT EXISTS service_config (
-            service_name TEXT PRIMARY KEY,
-            enabled BOOLEAN NOT NULL DEFAULT 1,
-            updated_at TEXT NOT NULL
-        )
+        SELECT {} FROM fuel_entries
+        WHERE user_id = ? AND id != ? AND deleted_at IS NULL
+            AND vehicle IS ? COLLATE NOCASE
+            AND odometer_reading IS NOT NULL AND date_time < ?
+        ORDER BY date_time DESC
+        LIMIT 1
         "#,
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(&entry.user_id)
+    .bind(&entry.id)
+    .bind(&entry.vehicle)
+    .bind(entry.date_time)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let next = sqlx::query_as::<_, FuelEntry>(&format!(
+        r#"
+        SELECT {} FROM fuel_entries
+        WHERE user_id = ? AND id != ? AND deleted_at IS NULL
+            AND vehicle IS ? COLLATE NOCASE
+            AND odometer_reading IS NOT NULL AND date_time > ?
+        ORDER BY date_time ASC
+        LIMIT 1
+        "#,
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(&entry.user_id)
+    .bind(&entry.id)
+    .bind(&entry.vehicle)
+    .bind(entry.date_time)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok((previous, next))
+}
+
+async fn validate_entry(conn: &mut SqliteConnection, entry: &FuelEntry) -> Result<ValidationError> {
+    let mut errors = validate_fuel_entry(entry);
+    let (previous, next) = get_odometer_neighbours(conn, entry).await?;
+    errors.merge(validate_odometer_order(
+        entry,
+        previous.as_ref(),
+        next.as_ref(),
+    ));
+    Ok(errors)
+}
+
+// Appends a revision to the entry's audit trail. `old` is None for creations.
+async fn record_revision(
+    conn: &mut SqliteConnection,
+    action: &str,
+    old: Option<&FuelEntry>,
+    new: &FuelEntry,
+    actor: &Actor,
+) -> Result<()> {
+    let revision: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(revision), 0) + 1 FROM fuel_entry_revisions WHERE entry_id = ?",
     )
-    .execute(pool)
+    .bind(&new.id)
+    .fetch_one(&mut *conn)
     .await?;
 
-    // Initialize default service configurations
-    let now = Utc::now();
     sqlx::query(
-        "INSERT OR IGNORE INTO service_config (service_name, enabled, updated_at) VALUES (?, ?, ?)",
+        r#"
+        INSERT INTO fuel_entry_revisions
+            (id, entry_id, user_id, revision, action, old_values, new_values, actor, source, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
     )
-    .bind("signin")
-    .bind(true)
-    .bind(now)
-    .execute(pool)
+    .bind(Uuid::new_v4().to_string())
+    .bind(&new.id)
+    .bind(&new.user_id)
+    .bind(revision)
+    .bind(action)
+    .bind(old.map(sqlx::types::Json))
+    .bind(sqlx::types::Json(new))
+    .bind(&actor.id)
+    .bind(actor.source.as_str())
+    .bind(Utc::now())
+    .execute(&mut *conn)
     .await?;
 
-    sqlx::query(
-        "INSERT OR IGNORE INTO service_config (service_name, enabled, updated_at) VALUES (?, ?, ?)",
+    // Every recorded change also moves the entry to the head of the sync feed
+    let change_seq: i64 = sqlx::query_scalar(
+        "UPDATE sync_state SET last_seq = last_seq + 1 WHERE id = 1 RETURNING last_seq",
     )
-    .bind("fuel_entry")
-    .bind(true)
-    .bind(now)
-    .execute(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
+    sqlx::query("UPDATE fuel_entries SET change_seq = ? WHERE id = ?")
+        .bind(change_seq)
+        .bind(&new.id)
+        .execute(&mut *conn)
+        .await?;
+
+    // ...and goes out to webhooks; a revert is an update like any other
+    let event = match action {
+        "created" => Some(WebhookEvent::FuelEntryCreated),
+        "updated" | "reverted" => Some(WebhookEvent::FuelEntryUpdated),
+        "deleted" => Some(WebhookEvent::FuelEntryDeleted),
+        "restored" => Some(WebhookEvent::FuelEntryRestored),
+        _ => None,
+    };
+    if let Some(event) = event {
+        enqueue_webhook_event(
+            conn,
+            event,
+            &new.user_id,
+            json!({ "entry": new, "previous": old }),
+        )
+        .await?;
+    }
+
     Ok(())
 }
 
-pub async fn create_user(pool: &SqlitePool, email: &str, password_hash: &str) -> Result<User> {
-    let id = Uuid::new_v4().to_string();
-    let created_at = Utc::now();
+// Builds a new entry from request data, deriving the missing amount. A
+// client-generated id is kept as given but must be a UUID.
+fn new_fuel_entry(user_id: &str, data: &FuelEntryData) -> Result<FuelEntry, ValidationError> {
+    let mut errors = ValidationError::default();
 
-    sqlx::query("INSERT INTO users (id, email, password_hash, created_at) VALUES (?, ?, ?, ?)")
-        .bind(&id)
-        .bind(email)
-        .bind(password_hash)
-        .bind(created_at)
-        .execute(pool)
-        .await?;
+    let id = match data.id.as_deref() {
+        Some(id) if Uuid::parse_str(id).is_ok() => id.to_string(),
+        Some(_) => {
+            errors.add("id", "must be a UUID");
+            String::new()
+        }
+        None => Uuid::new_v4().to_string(),
+    };
+
+    let amounts = resolve_amounts(
+        data.liters,
+        data.price_per_liter,
+        data.total_cost,
+        data.currency.as_deref(),
+    );
+    let (liters, price_per_liter, total_cost) = match amounts {
+        Ok(amounts) if errors.is_empty() => amounts,
+        Ok(_) => return Err(errors),
+        Err(amount_errors) => {
+            errors.merge(amount_errors);
+            return Err(errors);
+        }
+    };
 
-    Ok(User {
+    Ok(FuelEntry {
         id,
-        email: email.to_string(),
-        password_hash: password_hash.to_string(),
-        created_at,
+        user_id: user_id.to_string(),
+        liters,
+        price_per_liter,
+        total_cost,
+        date_time: data.date_time,
+        odometer_reading: data.odometer_reading,
+        vehicle: normalize_vehicle(data.vehicle.as_deref()),
+        fuel_type: normalize_fuel_type(data.fuel_type.as_deref()),
+        tags: sqlx::types::Json(normalize_tags(&data.tags)),
+        deleted_at: None,
+        version: 1,
+        anomalies: sqlx::types::Json(Vec::new()),
+        anomaly_status: None,
     })
 }
 
-pub async fn get_user_by_email(pool: &SqlitePool, email: &str) -> Result<Option<User>> {
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = ?")
-        .bind(email)
-        .fetch_optional(pool)
-        .await?;
-
-    Ok(user)
+// Applies a partial update on top of `entry`; see `resolve_updated_amounts`
+fn updated_fuel_entry(
+    entry: &FuelEntry,
+    request: &UpdateFuelEntryRequest,
+) -> Result<FuelEntry, ValidationError> {
+    let (liters, price_per_liter, total_cost) = resolve_updated_amounts(
+        (entry.liters, entry.price_per_liter, entry.total_cost),
+        request.liters,
+        request.price_per_liter,
+        request.total_cost,
+        request.currency.as_deref(),
+    )?;
+
+    Ok(FuelEntry {
+        liters,
+        price_per_liter,
+        total_cost,
+        date_time: request.date_time.unwrap_or(entry.date_time),
+        odometer_reading: request.odometer_reading.or(entry.odometer_reading),
+        vehicle: match &request.vehicle {
+            Some(vehicle) => normalize_vehicle(Some(vehicle)),
+            None => entry.vehicle.clone(),
+        },
+        fuel_type: match &request.fuel_type {
+            Some(fuel_type) => normalize_fuel_type(Some(fuel_type)),
+            None => entry.fuel_type.clone(),
+        },
+        tags: match &request.tags {
+            Some(tags) => sqlx::types::Json(normalize_tags(tags)),
+            None => entry.tags.clone(),
+        },
+        ..entry.clone()
+    })
 }
 
 pub async fn create_fuel_entry(
     pool: &SqlitePool,
     user_id: &str,
     request: &CreateFuelEntryRequest,
+    actor: &Actor,
 ) -> Result<FuelEntry> {
-    let id = Uuid::new_v4().to_string();
-
-    let fuel_entry = FuelEntry {
-        id: id.clone(),
-        user_id: user_id.to_string(),
-        liters: request.liters,
-        price_per_liter: request.price_per_liter,
-        total_cost: request.total_cost,
-        date_time: request.date_time,
-        odometer_reading: request.odometer_reading,
-    };
-
-    let data = serde_json::to_string(&fuel_entry)?;
+    let fuel_entry = new_fuel_entry(
+        user_id,
+        &FuelEntryData {
+            id: request.id.clone(),
+            liters: request.liters,
+            price_per_liter: request.price_per_liter,
+            total_cost: request.total_cost,
+            date_time: request.date_time,
+            odometer_reading: request.odometer_reading,
+            vehicle: request.vehicle.clone(),
+            fuel_type: request.fuel_type.clone(),
+            tags: request.tags.clone(),
+            currency: request.currency.clone(),
+        },
+    )?;
 
-    sqlx::query("INSERT INTO fuel_entries (id, user_id, data) VALUES (?, ?, ?)")
-        .bind(&id)
-        .bind(user_id)
-        .bind(&data)
-        .execute(pool)
-        .await?;
+    let mut tx = pool.begin().await?;
+    if get_fuel_entry_any_user(&mut tx, &fuel_entry.id)
+        .await?
+        .is_some()
+    {
+        let mut errors = ValidationError::default();
+        errors.add("id", "is already in use");
+        return Err(errors.into());
+    }
+    validate_entry(&mut tx, &fuel_entry).await?.into_result()?;
+    insert_fuel_entry(&mut tx, &fuel_entry).await?;
+    record_revision(&mut tx, "created", None, &fuel_entry, actor).await?;
+    let refreshed = refresh_anomalies(&mut tx, user_id).await?;
+    tx.commit().await?;
 
-    Ok(fuel_entry)
+    Ok(with_anomalies(fuel_entry, &refreshed))
 }
 
+// Creates a batch of entries, skipping duplicates of stored entries and of
+// earlier inputs. Every input gets a result. In atomic mode nothing is committed
+// if any input is invalid; the returned errors then list every problem.
 pub async fn create_fuel_entries(
     pool: &SqlitePool,
     user_id: &str,
     entries_data: &[FuelEntryData],
-) -> Result<Vec<FuelEntry>> {
-    let mut created_entries = Vec::new();
+    mode: BulkMode,
+    duplicate_check: &DuplicateCheck,
+    actor: &Actor,
+) -> Result<(Vec<FuelEntry>, Vec<BulkEntryResult>, ValidationError)> {
+    let mut created_entries: Vec<(usize, FuelEntry)> = Vec::new();
+    let mut results = Vec::new();
+    let mut errors = ValidationError::default();
 
     // Use a transaction to ensure all entries are created or none
     let mut tx = pool.begin().await?;
 
     // Get existing entries for this user to check for duplicates
     let existing_entries = get_fuel_entries_by_user_tx(&mut tx, user_id).await?;
+    let time_zone = get_user_time_zone(&mut tx, user_id).await?;
+    let duplicate_policy = DuplicatePolicy::from_request(duplicate_check, time_zone);
+
+    for (index, entry_data) in entries_data.iter().enumerate() {
+        let mut result = BulkEntryResult::new(index, BulkEntryStatus::Invalid);
+
+        let fuel_entry = match new_fuel_entry(user_id, entry_data) {
+            Ok(entry) => entry,
+            Err(entry_errors) => {
+                result.errors = entry_errors.errors.clone();
+                errors.extend_prefixed(&format!("entries[{}]", index), entry_errors);
+                results.push(result);
+                continue;
+            }
+        };
 
-    for entry_data in entries_data {
-        // Check if this entry already exists (same data, ignoring ID)
-        let is_duplicate = existing_entries.iter().any(|existing| {
-            existing.liters == entry_data.liters
-                && existing.price_per_liter == entry_data.price_per_liter
-                && existing.total_cost == entry_data.total_cost
-                && existing.date_time == entry_data.date_time
-                && existing.odometer_reading == entry_data.odometer_reading
-        });
-
-        if is_duplicate {
-            continue; // Skip duplicate entry
+        // An entry uploaded before under the same client id is skipped
+        if entry_data.id.is_some() {
+            match get_fuel_entry_any_user(&mut tx, &fuel_entry.id).await? {
+                Some(existing) if existing.user_id == user_id => {
+                    result.status = BulkEntryStatus::Duplicate;
+                    result.matched_entry_id = Some(existing.id);
+                    results.push(result);
+                    continue;
+                }
+                Some(_) => {
+                    let mut entry_errors = ValidationError::default();
+                    entry_errors.add("id", "is already in use");
+                    result.errors = entry_errors.errors.clone();
+                    errors.extend_prefixed(&format!("entries[{}]", index), entry_errors);
+                    results.push(result);
+                    continue;
+                }
+                None => {}
+            }
         }
 
-        let id = Uuid::new_v4().to_string();
+        // Skip entries already stored, then repeats within this batch
+        if let Some(existing) = existing_entries
+            .iter()
+            .find(|existing| duplicate_policy.is_duplicate(&fuel_entry, existing))
+        {
+            result.status = BulkEntryStatus::Duplicate;
+            result.matched_entry_id = Some(existing.id.clone());
+            results.push(result);
+            continue;
+        }
+        if let Some((created_index, created)) = created_entries
+            .iter()
+            .find(|(_, created)| duplicate_policy.is_duplicate(&fuel_entry, created))
+        {
+            result.status = BulkEntryStatus::Duplicate;
+            result.matched_entry_id = Some(created.id.clone());
+            result.matched_index = Some(*created_index);
+            results.push(result);
+            continue;
+        }
 
-        let fuel_entry = FuelEntry {
-            id: id.clone(),
-            user_id: user_id.to_string(),
-            liters: entry_data.liters,
-            price_per_liter: entry_data.price_per_liter,
-            total_cost: entry_data.total_cost,
-            date_time: entry_data.date_time,
-            odometer_reading: entry_data.odometer_reading,
-        };
+        // Entries inserted earlier in the batch count as neighbours for the odometer check
+        let entry_errors = validate_entry(&mut tx, &fuel_entry).await?;
+        if !entry_errors.is_empty() {
+            result.errors = entry_errors.errors.clone();
+            errors.extend_prefixed(&format!("entries[{}]", index), entry_errors);
+            results.push(result);
+            continue;
+        }
 
-        let data = serde_json::to_string(&fuel_entry)?;
+        insert_fuel_entry(&mut tx, &fuel_entry).await?;
+        record_revision(&mut tx, "created", None, &fuel_entry, actor).await?;
 
-        sqlx::query("INSERT INTO fuel_entries (id, user_id, data) VALUES (?, ?, ?)")
-            .bind(&id)
-            .bind(user_id)
-            .bind(&data)
-            .execute(&mut *tx)
-            .await?;
+        result.status = BulkEntryStatus::Created;
+        result.id = Some(fuel_entry.id.clone());
+        results.push(result);
+        created_entries.push((index, fuel_entry));
+    }
 
-        created_entries.push(fuel_entry);
+    if mode == BulkMode::Atomic && !errors.is_empty() {
+        // Dropping the transaction rolls back every insert of this batch
+        for result in results
+            .iter_mut()
+            .filter(|r| r.status == BulkEntryStatus::Created)
+        {
+            result.status = BulkEntryStatus::RolledBack;
+        }
+        return Ok((Vec::new(), results, errors));
     }
 
+    let refreshed = refresh_anomalies(&mut tx, user_id).await?;
+
     // Commit the transaction
     tx.commit().await?;
 
-    Ok(created_entries)
+    let created_entries = created_entries
+        .into_iter()
+        .map(|(_, entry)| with_anomalies(entry, &refreshed))
+        .collect();
+    Ok((created_entries, results, errors))
 }
 
 pub async fn get_fuel_entries_by_user(pool: &SqlitePool, user_id: &str) -> Result<Vec<FuelEntry>> {
-    let entries_db =
-        sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries WHERE user_id = ?")
-            .bind(user_id)
-            .fetch_all(pool)
-            .await?;
-
-    let mut entries = Vec::new();
-    for entry_db in entries_db {
-        let fuel_entry: FuelEntry = serde_json::from_str(&entry_db.data)?;
-        entries.push(fuel_entry);
-    }
-
-    // Sort by date_time descending
-    entries.sort_by(|a, b| b.date_time.cmp(&a.date_time));
+    let entries = sqlx::query_as::<_, FuelEntry>(&format!(
+        "SELECT {} FROM fuel_entries WHERE user_id = ? AND deleted_at IS NULL ORDER BY date_time DESC",
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
 
     Ok(entries)
 }
@@ -227,17 +605,13 @@ async fn get_fuel_entries_by_user_tx<'a>(
     tx: &mut sqlx::Transaction<'a, sqlx::Sqlite>,
     user_id: &str,
 ) -> Result<Vec<FuelEntry>> {
-    let entries_db =
-        sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries WHERE user_id = ?")
-            .bind(user_id)
-            .fetch_all(&mut **tx)
-            .await?;
-
-    let mut entries = Vec::new();
-    for entry_db in entries_db {
-        let fuel_entry: FuelEntry = serde_json::from_str(&entry_db.data)?;
-        entries.push(fuel_entry);
-    }
+    let entries = sqlx::query_as::<_, FuelEntry>(&format!(
+        "SELECT {} FROM fuel_entries WHERE user_id = ? AND deleted_at IS NULL",
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(&mut **tx)
+    .await?;
 
     Ok(entries)
 }
@@ -247,166 +621,2358 @@ pub async fn get_fuel_entry_by_id(
     id: &str,
     user_id: &str,
 ) -> Result<Option<FuelEntry>> {
-    let entry_db =
-        sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries WHERE id = ? AND user_id = ?")
-            .bind(id)
-            .bind(user_id)
-            .fetch_optional(pool)
-            .await?;
+    let entry = sqlx::query_as::<_, FuelEntry>(&format!(
+        "SELECT {} FROM fuel_entries WHERE id = ? AND user_id = ? AND deleted_at IS NULL",
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
 
-    if let Some(entry_db) = entry_db {
-        let fuel_entry: FuelEntry = serde_json::from_str(&entry_db.data)?;
-        Ok(Some(fuel_entry))
-    } else {
-        Ok(None)
-    }
+    Ok(entry)
 }
 
-pub async fn update_fuel_entry(
-    pool: &SqlitePool,
+async fn get_fuel_entry_tx(
+    conn: &mut SqliteConnection,
     id: &str,
     user_id: &str,
-    request: &UpdateFuelEntryRequest,
 ) -> Result<Option<FuelEntry>> {
-    let existing_entry = get_fuel_entry_by_id(pool, id, user_id).await?;
-
-    if let Some(entry) = existing_entry {
-        let updated_entry = FuelEntry {
-            id: entry.id.clone(),
-            user_id: entry.user_id,
-            liters: request.liters.unwrap_or(entry.liters),
-            price_per_liter: request.price_per_liter.unwrap_or(entry.price_per_liter),
-            total_cost: request.total_cost.unwrap_or(entry.total_cost),
-            date_time: request.date_time.unwrap_or(entry.date_time),
-            odometer_reading: request.odometer_reading.or(entry.odometer_reading),
-        };
+    let entry = sqlx::query_as::<_, FuelEntry>(&format!(
+        "SELECT {} FROM fuel_entries WHERE id = ? AND user_id = ?",
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(conn)
+    .await?;
 
-        let data = serde_json::to_string(&updated_entry)?;
+    Ok(entry)
+}
 
-        sqlx::query("UPDATE fuel_entries SET data = ? WHERE id = ? AND user_id = ?")
-            .bind(&data)
-            .bind(id)
-            .bind(user_id)
-            .execute(pool)
-            .await?;
+// Looks an entry up by id alone, to detect client-generated ids that are already taken
+async fn get_fuel_entry_any_user(
+    conn: &mut SqliteConnection,
+    id: &str,
+) -> Result<Option<FuelEntry>> {
+    let entry = sqlx::query_as::<_, FuelEntry>(&format!(
+        "SELECT {} FROM fuel_entries WHERE id = ?",
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(conn)
+    .await?;
 
-        Ok(Some(updated_entry))
-    } else {
-        Ok(None)
-    }
+    Ok(entry)
 }
 
-pub async fn delete_fuel_entry(pool: &SqlitePool, id: &str, user_id: &str) -> Result<bool> {
-    // First check if the entry exists and belongs to the user
-    let existing_entry = get_fuel_entry_by_id(pool, id, user_id).await?;
-
-    if existing_entry.is_some() {
-        let result = sqlx::query("DELETE FROM fuel_entries WHERE id = ? AND user_id = ?")
-            .bind(id)
-            .bind(user_id)
-            .execute(pool)
-            .await?;
+// Writes the data fields of `entry` over the stored row and bumps its version
+async fn save_fuel_entry(conn: &mut SqliteConnection, entry: &mut FuelEntry) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE fuel_entries
+        SET liters = ?, price_per_liter = ?, total_cost = ?, date_time = ?, odometer_reading = ?,
+            vehicle = ?, fuel_type = ?, tags = ?, version = version + 1
+        WHERE id = ? AND user_id = ?
+        "#,
+    )
+    .bind(entry.liters)
+    .bind(entry.price_per_liter)
+    .bind(entry.total_cost)
+    .bind(entry.date_time)
+    .bind(entry.odometer_reading)
+    .bind(&entry.vehicle)
+    .bind(&entry.fuel_type)
+    .bind(&entry.tags)
+    .bind(&entry.id)
+    .bind(&entry.user_id)
+    .execute(conn)
+    .await?;
 
-        Ok(result.rows_affected() > 0)
-    } else {
-        Ok(false)
-    }
+    entry.version += 1;
+    Ok(())
 }
 
-pub async fn delete_fuel_entries(
+// `expected_version` comes from the client's `If-Match`; None skips the check
+pub async fn update_fuel_entry(
     pool: &SqlitePool,
+    id: &str,
     user_id: &str,
-    entry_ids: &[String],
-) -> Result<(usize, Vec<String>)> {
-    if entry_ids.is_empty() {
-        return Ok((0, Vec::new()));
-    }
-
-    let mut deleted_ids = Vec::new();
+    request: &UpdateFuelEntryRequest,
+    expected_version: Option<i64>,
+    actor: &Actor,
+) -> Result<Option<FuelEntry>> {
     let mut tx = pool.begin().await?;
+    let existing_entry = get_fuel_entry_tx(&mut tx, id, user_id).await?;
 
-    for entry_id in entry_ids {
-        // Check if the entry exists and belongs to the user
-        let existing_entry = sqlx::query_as::<_, FuelEntryDB>(
-            "SELECT * FROM fuel_entries WHERE id = ? AND user_id = ?",
-        )
-        .bind(entry_id)
-        .bind(user_id)
-        .fetch_optional(&mut *tx)
-        .await?;
+    match existing_entry {
+        Some(entry) if entry.deleted_at.is_none() => {
+            check_version(&entry, expected_version)?;
 
-        if existing_entry.is_some() {
-            // Delete the entry
-            let result = sqlx::query("DELETE FROM fuel_entries WHERE id = ? AND user_id = ?")
-                .bind(entry_id)
-                .bind(user_id)
-                .execute(&mut *tx)
-                .await?;
+            let mut updated_entry = updated_fuel_entry(&entry, request)?;
 
-            if result.rows_affected() > 0 {
-                deleted_ids.push(entry_id.clone());
-            }
-        }
-    }
+            validate_entry(&mut tx, &updated_entry)
+                .await?
+                .into_result()?;
 
-    // Commit the transaction
-    tx.commit().await?;
+            save_fuel_entry(&mut tx, &mut updated_entry).await?;
+            record_revision(&mut tx, "updated", Some(&entry), &updated_entry, actor).await?;
+            let refreshed = refresh_anomalies(&mut tx, user_id).await?;
+            tx.commit().await?;
 
-    Ok((deleted_ids.len(), deleted_ids))
+            Ok(Some(with_anomalies(updated_entry, &refreshed)))
+        }
+        _ => Ok(None),
+    }
 }
 
-pub async fn get_dashboard_stats(pool: &SqlitePool) -> Result<DashboardStats> {
-    let total_users: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
-        .fetch_one(pool)
-        .await?;
-
-    let total_fuel_entries: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM fuel_entries")
-        .fetch_one(pool)
-        .await?;
-
-    let entries_db = sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries")
-        .fetch_all(pool)
-        .await?;
-
-    let mut all_entries = Vec::new();
-    for entry_db in entries_db {
-        let fuel_entry: FuelEntry = serde_json::from_str(&entry_db.data)?;
-        all_entries.push(fuel_entry);
+// Appends the conditions of `filter` to a query whose WHERE clause is already open
+fn push_entry_filter(builder: &mut QueryBuilder<'_, Sqlite>, filter: &FuelEntryFilter) {
+    if let Some(from) = filter.from {
+        builder.push(" AND date_time >= ").push_bind(from);
     }
-
-    let total_fuel_cost: f64 = all_entries.iter().map(|e| e.total_cost).sum();
-    let total_liters: f64 = all_entries.iter().map(|e| e.liters).sum();
-    let average_price_per_liter = if total_liters > 0.0 {
-        total_fuel_cost / total_liters
+    if let Some(to) = filter.to {
+        builder.push(" AND date_time <= ").push_bind(to);
+    }
+    if let Some(min_price) = filter.min_price_per_liter {
+        builder
+            .push(" AND price_per_liter >= ")
+            .push_bind(min_price);
+    }
+    if let Some(max_price) = filter.max_price_per_liter {
+        builder
+            .push(" AND price_per_liter <= ")
+            .push_bind(max_price);
+    }
+    if let Some(min_cost) = filter.min_total_cost {
+        builder.push(" AND total_cost >= ").push_bind(min_cost);
+    }
+    if let Some(max_cost) = filter.max_total_cost {
+        builder.push(" AND total_cost <= ").push_bind(max_cost);
+    }
+    if let Some(min_liters) = filter.min_liters {
+        builder.push(" AND liters >= ").push_bind(min_liters);
+    }
+    if let Some(max_liters) = filter.max_liters {
+        builder.push(" AND liters <= ").push_bind(max_liters);
+    }
+    if let Some(vehicle) = &filter.vehicle {
+        builder
+            .push(" AND vehicle = ")
+            .push_bind(vehicle.trim().to_string())
+            .push(" COLLATE NOCASE");
+    }
+    if let Some(fuel_type) = normalize_fuel_type(filter.fuel_type.as_deref()) {
+        builder.push(" AND fuel_type = ").push_bind(fuel_type);
+    }
+    if let Some(tag) = &filter.tag {
+        builder
+            .push(
+                " AND EXISTS (SELECT 1 FROM json_each(fuel_entries.tags) WHERE json_each.value = ",
+            )
+            .push_bind(tag.trim().to_string())
+            .push(")");
+    }
+    match filter.flagged {
+        Some(true) => {
+            builder.push(" AND anomaly_status IN ('pending', 'confirmed')");
+        }
+        Some(false) => {
+            builder.push(" AND (anomaly_status IS NULL OR anomaly_status = 'dismissed')");
+        }
+        None => {}
+    }
+}
+
+// Position after the last entry of a page: its sort value and id (the tie-breaker).
+// Handed to clients base64-encoded as an opaque `next_cursor`.
+#[derive(Serialize, Deserialize)]
+struct EntryCursor {
+    sort: FuelEntrySort,
+    value: CursorValue,
+    id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum CursorValue {
+    Date(DateTime<Utc>),
+    Number(f64),
+}
+
+fn sort_column(sort: FuelEntrySort) -> &'static str {
+    match sort {
+        FuelEntrySort::DateTime => "date_time",
+        FuelEntrySort::TotalCost => "total_cost",
+        FuelEntrySort::Liters => "liters",
+        FuelEntrySort::PricePerLiter => "price_per_liter",
+    }
+}
+
+fn encode_cursor(sort: FuelEntrySort, entry: &FuelEntry) -> String {
+    let value = match sort {
+        FuelEntrySort::DateTime => CursorValue::Date(entry.date_time),
+        FuelEntrySort::TotalCost => CursorValue::Number(entry.total_cost),
+        FuelEntrySort::Liters => CursorValue::Number(entry.liters),
+        FuelEntrySort::PricePerLiter => CursorValue::Number(entry.price_per_liter),
+    };
+    let cursor = EntryCursor {
+        sort,
+        value,
+        id: entry.id.clone(),
+    };
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+}
+
+fn decode_cursor(cursor: &str, sort: FuelEntrySort) -> Result<EntryCursor, ValidationError> {
+    let mut errors = ValidationError::default();
+    let decoded = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<EntryCursor>(&bytes).ok());
+
+    match decoded {
+        Some(cursor) if cursor.sort == sort => Ok(cursor),
+        Some(_) => {
+            errors.add("cursor", "was issued for a different sort field");
+            Err(errors)
+        }
+        None => {
+            errors.add("cursor", "is not a valid cursor");
+            Err(errors)
+        }
+    }
+}
+
+// One page of a user's live entries, filtered and sorted in SQL. Pages are
+// keyed on (sort value, id) so inserts and deletes between requests do not
+// shift them. Without a limit every matching entry is returned.
+pub async fn list_fuel_entries(
+    pool: &SqlitePool,
+    user_id: &str,
+    query: &FuelEntryListQuery,
+    limit: Option<i64>,
+) -> Result<FuelEntryPage> {
+    let filter = query.filter();
+    let column = sort_column(query.sort);
+    let (comparison, direction) = match query.order {
+        SortOrder::Asc => (">", "ASC"),
+        SortOrder::Desc => ("<", "DESC"),
+    };
+    let cursor = match query.cursor.as_deref().filter(|cursor| !cursor.is_empty()) {
+        Some(cursor) => Some(decode_cursor(cursor, query.sort)?),
+        None => None,
+    };
+
+    let mut count_builder = QueryBuilder::<Sqlite>::new(
+        "SELECT COUNT(*) FROM fuel_entries WHERE deleted_at IS NULL AND user_id = ",
+    );
+    count_builder.push_bind(user_id);
+    push_entry_filter(&mut count_builder, &filter);
+    let total_count: i64 = count_builder.build_query_scalar().fetch_one(pool).await?;
+
+    let mut builder = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM fuel_entries WHERE deleted_at IS NULL AND user_id = ",
+        FUEL_ENTRY_COLUMNS
+    ));
+    builder.push_bind(user_id);
+    push_entry_filter(&mut builder, &filter);
+
+    if let Some(cursor) = cursor {
+        builder.push(format!(" AND ({} {} ", column, comparison));
+        push_cursor_value(&mut builder, &cursor.value);
+        builder.push(format!(" OR ({} = ", column));
+        push_cursor_value(&mut builder, &cursor.value);
+        builder
+            .push(format!(" AND id {} ", comparison))
+            .push_bind(cursor.id)
+            .push("))");
+    }
+
+    builder.push(format!(
+        " ORDER BY {} {}, id {}",
+        column, direction, direction
+    ));
+    // One extra row tells whether another page follows
+    if let Some(limit) = limit {
+        builder.push(" LIMIT ").push_bind(limit + 1);
+    }
+
+    let mut entries: Vec<FuelEntry> = builder.build_query_as().fetch_all(pool).await?;
+
+    let next_cursor = match limit {
+        Some(limit) if entries.len() as i64 > limit => {
+            entries.truncate(limit as usize);
+            entries.last().map(|entry| encode_cursor(query.sort, entry))
+        }
+        _ => None,
+    };
+
+    Ok(FuelEntryPage {
+        entries,
+        total_count,
+        next_cursor,
+    })
+}
+
+fn push_cursor_value(builder: &mut QueryBuilder<'_, Sqlite>, value: &CursorValue) {
+    match value {
+        CursorValue::Date(date_time) => builder.push_bind(*date_time),
+        CursorValue::Number(number) => builder.push_bind(*number),
+    };
+}
+
+// Applies many patches in one transaction, either one per listed entry or one
+// patch to every entry matching a filter. Every target gets a result; in atomic
+// mode nothing is committed if any target failed, and the returned errors say why.
+pub async fn update_fuel_entries(
+    pool: &SqlitePool,
+    request: &BulkUpdateFuelEntriesRequest,
+    actor: &Actor,
+) -> Result<(Vec<FuelEntry>, Vec<BulkUpdateResult>, ValidationError)> {
+    let user_id = request.user_id.as_str();
+    let mut tx = pool.begin().await?;
+
+    let targets: Vec<(String, Option<i64>, &UpdateFuelEntryRequest)> =
+        match (&request.updates, &request.filter, &request.patch) {
+            (Some(updates), _, _) => updates
+                .iter()
+                .map(|u| (u.id.clone(), u.base_version, &u.patch))
+                .collect(),
+            (None, Some(filter), Some(patch)) => {
+                let mut builder = QueryBuilder::<Sqlite>::new(
+                    "SELECT id, version FROM fuel_entries WHERE deleted_at IS NULL AND user_id = ",
+                );
+                builder.push_bind(user_id);
+                push_entry_filter(&mut builder, filter);
+                builder.push(" ORDER BY date_time ASC");
+
+                // Matches are read in this transaction, so their current versions
+                // are the ones the patch is based on
+                let matches: Vec<(String, i64)> =
+                    builder.build_query_as().fetch_all(&mut *tx).await?;
+                matches
+                    .into_iter()
+                    .map(|(id, version)| (id, Some(version), patch))
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+    let mut updated_entries = Vec::new();
+    let mut results = Vec::new();
+    let mut errors = ValidationError::default();
+
+    for (index, (id, base_version, patch)) in targets.into_iter().enumerate() {
+        let prefix = format!("updates[{}]", index);
+        let mut result = BulkUpdateResult {
+            index,
+            id: id.clone(),
+            status: BulkUpdateStatus::Invalid,
+            version: None,
+            errors: Vec::new(),
+        };
+
+        // Without the version the patch was based on, it could silently
+        // overwrite a change the client has not seen
+        let Some(base_version) = base_version else {
+            let mut version_errors = ValidationError::default();
+            version_errors.add("base_version", "is required");
+            result.errors = version_errors.errors.clone();
+            errors.extend_prefixed(&prefix, version_errors);
+            results.push(result);
+            continue;
+        };
+
+        let entry = match get_fuel_entry_tx(&mut tx, &id, user_id).await? {
+            Some(entry) if entry.deleted_at.is_none() => entry,
+            _ => {
+                result.status = BulkUpdateStatus::NotFound;
+                errors.add(&format!("{}.id", prefix), "fuel entry not found");
+                results.push(result);
+                continue;
+            }
+        };
+
+        if base_version != entry.version {
+            result.status = BulkUpdateStatus::Conflict;
+            result.version = Some(entry.version);
+            errors.add(
+                &format!("{}.base_version", prefix),
+                format!("does not match the current version {}", entry.version),
+            );
+            results.push(result);
+            continue;
+        }
+
+        let mut updated_entry = match updated_fuel_entry(&entry, patch) {
+            Ok(updated_entry) => updated_entry,
+            Err(entry_errors) => {
+                result.errors = entry_errors.errors.clone();
+                errors.extend_prefixed(&prefix, entry_errors);
+                results.push(result);
+                continue;
+            }
+        };
+
+        let entry_errors = validate_entry(&mut tx, &updated_entry).await?;
+        if !entry_errors.is_empty() {
+            result.errors = entry_errors.errors.clone();
+            errors.extend_prefixed(&prefix, entry_errors);
+            results.push(result);
+            continue;
+        }
+
+        save_fuel_entry(&mut tx, &mut updated_entry).await?;
+        record_revision(&mut tx, "updated", Some(&entry), &updated_entry, actor).await?;
+
+        result.status = BulkUpdateStatus::Updated;
+        result.version = Some(updated_entry.version);
+        results.push(result);
+        updated_entries.push(updated_entry);
+    }
+
+    if request.mode == BulkMode::Atomic && !errors.is_empty() {
+        // Dropping the transaction rolls back every update of this batch
+        for result in results
+            .iter_mut()
+            .filter(|r| r.status == BulkUpdateStatus::Updated)
+        {
+            result.status = BulkUpdateStatus::RolledBack;
+            result.version = None;
+        }
+        return Ok((Vec::new(), results, errors));
+    }
+
+    let refreshed = refresh_anomalies(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    let updated_entries = updated_entries
+        .into_iter()
+        .map(|entry| with_anomalies(entry, &refreshed))
+        .collect();
+    Ok((updated_entries, results, errors))
+}
+
+// Sets the data fields back to the state recorded by `revision`. The entry must
+// be live; reverting is itself recorded as a new revision.
+pub async fn revert_fuel_entry(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+    revision: i64,
+    actor: &Actor,
+) -> Result<Option<FuelEntry>> {
+    let mut tx = pool.begin().await?;
+
+    let Some(entry) = get_fuel_entry_tx(&mut tx, id, user_id).await? else {
+        return Ok(None);
+    };
+    if entry.deleted_at.is_some() {
+        return Ok(None);
+    }
+
+    let snapshot: Option<sqlx::types::Json<FuelEntry>> = sqlx::query_scalar(
+        "SELECT new_values FROM fuel_entry_revisions WHERE entry_id = ? AND user_id = ? AND revision = ?",
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(revision)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(sqlx::types::Json(snapshot)) = snapshot else {
+        return Ok(None);
+    };
+
+    let mut reverted_entry = FuelEntry {
+        liters: snapshot.liters,
+        price_per_liter: snapshot.price_per_liter,
+        total_cost: snapshot.total_cost,
+        date_time: snapshot.date_time,
+        odometer_reading: snapshot.odometer_reading,
+        vehicle: snapshot.vehicle,
+        fuel_type: snapshot.fuel_type,
+        tags: snapshot.tags,
+        ..entry.clone()
+    };
+
+    validate_entry(&mut tx, &reverted_entry)
+        .await?
+        .into_result()?;
+
+    save_fuel_entry(&mut tx, &mut reverted_entry).await?;
+    record_revision(&mut tx, "reverted", Some(&entry), &reverted_entry, actor).await?;
+    let refreshed = refresh_anomalies(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    Ok(Some(with_anomalies(reverted_entry, &refreshed)))
+}
+
+pub async fn get_fuel_entry_history(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+) -> Result<Vec<FuelEntryRevision>> {
+    let revisions = sqlx::query_as::<_, FuelEntryRevision>(
+        r#"
+        SELECT id, entry_id, user_id, revision, action, old_values, new_values, actor, source, created_at
+        FROM fuel_entry_revisions
+        WHERE entry_id = ? AND user_id = ?
+        ORDER BY revision ASC
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(revisions)
+}
+
+// Sets or clears the tombstone of one entry and records the change. Returns
+// false when the entry does not exist or is already in the requested state.
+async fn set_fuel_entry_deleted(
+    conn: &mut SqliteConnection,
+    id: &str,
+    user_id: &str,
+    deleted_at: Option<DateTime<Utc>>,
+    actor: &Actor,
+) -> Result<bool> {
+    let Some(entry) = get_fuel_entry_tx(conn, id, user_id).await? else {
+        return Ok(false);
+    };
+    if entry.deleted_at.is_some() == deleted_at.is_some() {
+        return Ok(false);
+    }
+
+    sqlx::query(
+        "UPDATE fuel_entries SET deleted_at = ?, version = version + 1 WHERE id = ? AND user_id = ?",
+    )
+    .bind(deleted_at)
+    .bind(id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    let action = if deleted_at.is_some() {
+        "deleted"
     } else {
-        0.0
+        "restored"
     };
+    let updated_entry = FuelEntry {
+        deleted_at,
+        version: entry.version + 1,
+        ..entry.clone()
+    };
+    record_revision(conn, action, Some(&entry), &updated_entry, actor).await?;
+
+    Ok(true)
+}
+
+// Deletion only sets a tombstone; the row is removed by the trash purge job
+// once the retention period has passed and can be restored until then.
+pub async fn delete_fuel_entry(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+    expected_version: Option<i64>,
+    actor: &Actor,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    if let Some(entry) = get_fuel_entry_tx(&mut tx, id, user_id).await? {
+        if entry.deleted_at.is_none() {
+            check_version(&entry, expected_version)?;
+        }
+    }
+    let deleted = set_fuel_entry_deleted(&mut tx, id, user_id, Some(Utc::now()), actor).await?;
+    if deleted {
+        refresh_anomalies(&mut tx, user_id).await?;
+    }
+    tx.commit().await?;
+
+    Ok(deleted)
+}
+
+pub async fn delete_fuel_entries(
+    pool: &SqlitePool,
+    user_id: &str,
+    entry_ids: &[String],
+    actor: &Actor,
+) -> Result<(usize, Vec<String>)> {
+    if entry_ids.is_empty() {
+        return Ok((0, Vec::new()));
+    }
+
+    let mut deleted_ids = Vec::new();
+    let deleted_at = Utc::now();
+    let mut tx = pool.begin().await?;
+
+    for entry_id in entry_ids {
+        // Only live entries that belong to the user are affected
+        if set_fuel_entry_deleted(&mut tx, entry_id, user_id, Some(deleted_at), actor).await? {
+            deleted_ids.push(entry_id.clone());
+        }
+    }
+    if !deleted_ids.is_empty() {
+        refresh_anomalies(&mut tx, user_id).await?;
+    }
+
+    // Commit the transaction
+    tx.commit().await?;
+
+    Ok((deleted_ids.len(), deleted_ids))
+}
+
+pub async fn get_deleted_fuel_entries(pool: &SqlitePool, user_id: &str) -> Result<Vec<FuelEntry>> {
+    let entries = sqlx::query_as::<_, FuelEntry>(&format!(
+        "SELECT {} FROM fuel_entries WHERE user_id = ? AND deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+pub async fn restore_fuel_entry(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+    actor: &Actor,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let restored = set_fuel_entry_deleted(&mut tx, id, user_id, None, actor).await?;
+    if restored {
+        refresh_anomalies(&mut tx, user_id).await?;
+    }
+    tx.commit().await?;
+
+    Ok(restored)
+}
+
+pub async fn restore_fuel_entries(
+    pool: &SqlitePool,
+    user_id: &str,
+    entry_ids: &[String],
+    actor: &Actor,
+) -> Result<(usize, Vec<String>)> {
+    let mut restored_ids = Vec::new();
+    let mut tx = pool.begin().await?;
+
+    for entry_id in entry_ids {
+        if set_fuel_entry_deleted(&mut tx, entry_id, user_id, None, actor).await? {
+            restored_ids.push(entry_id.clone());
+        }
+    }
+    if !restored_ids.is_empty() {
+        refresh_anomalies(&mut tx, user_id).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok((restored_ids.len(), restored_ids))
+}
+
+// Re-runs the anomaly detector over every live entry of `user_id`, since a
+// write can change what is usual for the user and the economy of the next
+// fill-up. Only changed flags are stored: an entry found with different kinds
+// of anomaly goes back to pending review, one without any is cleared. Flags are
+// review state rather than user data: they leave the entry's version and the
+// sync feed alone and only invalidate the dashboard. Since every entry write
+// ends here and flags decide which entries count towards budgets, the budget
+// thresholds are re-checked last. Returns the live entries with their current
+// flags, oldest first.
+async fn refresh_anomalies(conn: &mut SqliteConnection, user_id: &str) -> Result<Vec<FuelEntry>> {
+    let mut entries = sqlx::query_as::<_, FuelEntry>(&format!(
+        "SELECT {} FROM fuel_entries WHERE user_id = ? AND deleted_at IS NULL ORDER BY date_time ASC, id ASC",
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let tank_capacities: std::collections::HashMap<String, f64> =
+        sqlx::query_as::<_, (String, f64)>(
+            "SELECT name, tank_capacity_liters FROM vehicles WHERE user_id = ?",
+        )
+        .bind(user_id)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|(name, capacity)| (name.to_lowercase(), capacity))
+        .collect();
+
+    let detected = detect_anomalies(&entries, &tank_capacities, anomaly_threshold());
+    let mut changed = false;
+    for (entry, anomalies) in entries.iter_mut().zip(detected) {
+        if entry.anomalies.0 == anomalies {
+            continue;
+        }
+        changed = true;
+        let same_kinds = entry
+            .anomalies
+            .iter()
+            .map(|anomaly| anomaly.kind)
+            .eq(anomalies.iter().map(|anomaly| anomaly.kind));
+        let status = if anomalies.is_empty() {
+            None
+        } else if same_kinds {
+            // Only the figures in the messages moved
+            entry.anomaly_status.clone()
+        } else {
+            Some(AnomalyStatus::Pending.as_str().to_string())
+        };
+
+        sqlx::query("UPDATE fuel_entries SET anomalies = ?, anomaly_status = ? WHERE id = ?")
+            .bind(sqlx::types::Json(&anomalies))
+            .bind(&status)
+            .bind(&entry.id)
+            .execute(&mut *conn)
+            .await?;
+
+        entry.anomalies = sqlx::types::Json(anomalies);
+        entry.anomaly_status = status;
+    }
+    if changed {
+        bump_data_version(&mut *conn).await?;
+    }
+    raise_budget_alerts(&mut *conn, user_id).await?;
+
+    Ok(entries)
+}
+
+// `entry` with the flags it has among `refreshed` (from `refresh_anomalies`)
+fn with_anomalies(entry: FuelEntry, refreshed: &[FuelEntry]) -> FuelEntry {
+    match refreshed.iter().find(|r| r.id == entry.id) {
+        Some(r) => FuelEntry {
+            anomalies: r.anomalies.clone(),
+            anomaly_status: r.anomaly_status.clone(),
+            ..entry
+        },
+        None => entry,
+    }
+}
+
+// Re-checks the entries of every user, e.g. after `ANOMALY_THRESHOLD` changed
+// or for entries stored before detection existed. Each user is committed on
+// its own, so the scan does not hold the write lock for the whole database.
+// Returns the number of live entries with unresolved anomalies afterwards.
+pub async fn rescan_anomalies(pool: &SqlitePool) -> Result<i64> {
+    let user_ids: Vec<String> =
+        sqlx::query_scalar("SELECT DISTINCT user_id FROM fuel_entries WHERE deleted_at IS NULL")
+            .fetch_all(pool)
+            .await?;
+    for user_id in &user_ids {
+        let mut tx = pool.begin().await?;
+        refresh_anomalies(&mut tx, user_id).await?;
+        tx.commit().await?;
+    }
+
+    let flagged: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM fuel_entries WHERE deleted_at IS NULL AND anomaly_status IN ('pending', 'confirmed')",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(flagged)
+}
+
+// Live flagged entries in the given review state, newest first
+pub async fn get_anomaly_queue(
+    pool: &SqlitePool,
+    status: AnomalyStatus,
+    user_id: Option<&str>,
+    limit: i64,
+) -> Result<Vec<FuelEntry>> {
+    let mut builder = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM fuel_entries WHERE deleted_at IS NULL AND anomaly_status = ",
+        FUEL_ENTRY_COLUMNS
+    ));
+    builder.push_bind(status.as_str());
+    if let Some(user_id) = user_id {
+        builder
+            .push(" AND user_id = ")
+            .push_bind(user_id.to_string());
+    }
+    builder
+        .push(" ORDER BY date_time DESC, id DESC LIMIT ")
+        .push_bind(limit);
+
+    let entries = builder.build_query_as().fetch_all(pool).await?;
+    Ok(entries)
+}
+
+// Sets the review state of a live flagged entry. Like flag changes it leaves
+// the entry's version alone. Returns None when there is no such entry.
+pub async fn review_anomaly(
+    pool: &SqlitePool,
+    id: &str,
+    status: AnomalyStatus,
+) -> Result<Option<FuelEntry>> {
+    let mut tx = pool.begin().await?;
+
+    let updated = sqlx::query(
+        "UPDATE fuel_entries SET anomaly_status = ? WHERE id = ? AND deleted_at IS NULL AND anomaly_status IS NOT NULL",
+    )
+    .bind(status.as_str())
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Ok(None);
+    }
+    bump_data_version(&mut tx).await?;
+
+    let entry = get_fuel_entry_any_user(&mut tx, id).await?;
+    // A dismissed entry counts towards the budgets again
+    if let Some(entry) = &entry {
+        raise_budget_alerts(&mut tx, &entry.user_id).await?;
+    }
+    tx.commit().await?;
+
+    Ok(entry)
+}
+
+pub async fn get_vehicles(pool: &SqlitePool, user_id: &str) -> Result<Vec<Vehicle>> {
+    let vehicles = sqlx::query_as::<_, Vehicle>(
+        "SELECT name, tank_capacity_liters, updated_at FROM vehicles WHERE user_id = ? ORDER BY name",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(vehicles)
+}
+
+// Creates or replaces the tank capacity of one vehicle and re-checks the
+// user's entries against it. Invalid values fail with a `ValidationError`.
+pub async fn set_vehicle(
+    pool: &SqlitePool,
+    user_id: &str,
+    name: &str,
+    request: &SetVehicleRequest,
+) -> Result<Vehicle> {
+    validate_vehicle(name, request).into_result()?;
+    let name = normalize_vehicle(Some(name)).unwrap_or_default();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+        INSERT INTO vehicles (user_id, name, tank_capacity_liters, updated_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (user_id, name) DO UPDATE
+        SET tank_capacity_liters = excluded.tank_capacity_liters, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(&name)
+    .bind(request.tank_capacity_liters)
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await?;
+
+    let vehicle = sqlx::query_as::<_, Vehicle>(
+        "SELECT name, tank_capacity_liters, updated_at FROM vehicles WHERE user_id = ? AND name = ?",
+    )
+    .bind(user_id)
+    .bind(&name)
+    .fetch_one(&mut *tx)
+    .await?;
+    refresh_anomalies(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    Ok(vehicle)
+}
+
+// Returns false when the user has no vehicle of that name
+pub async fn delete_vehicle(pool: &SqlitePool, user_id: &str, name: &str) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let deleted = sqlx::query("DELETE FROM vehicles WHERE user_id = ? AND name = ?")
+        .bind(user_id)
+        .bind(name.trim())
+        .execute(&mut *tx)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Ok(false);
+    }
+    refresh_anomalies(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+const BUDGET_COLUMNS: &str = "id, user_id, period, vehicle, amount, created_at, updated_at";
+
+const BUDGET_ALERT_COLUMNS: &str =
+    "id, budget_id, user_id, period_label, threshold, spent, amount, \
+     message, created_at, read_at, delivered_at";
+
+// At most this many alerts are handed to the delivery job per run
+const ALERT_DELIVERY_BATCH_SIZE: i64 = 100;
+
+// Progress of `budget` in its period containing `now`, with spending taken
+// from the user's live entries (of the budget's vehicle, if it has one).
+// Entries with pending or confirmed anomalies are likely mistyped and do not
+// count until an admin dismisses their flags.
+async fn get_budget_progress(
+    conn: &mut SqliteConnection,
+    budget: Budget,
+    time_zone: Tz,
+    now: DateTime<Utc>,
+) -> Result<BudgetProgress> {
+    let period = BudgetPeriod::from_name(&budget.period).unwrap_or(BudgetPeriod::Monthly);
+    let (start, end, label) = current_period(period, time_zone, now);
+
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT TOTAL(total_cost) FROM fuel_entries WHERE deleted_at IS NULL \
+         AND (anomaly_status IS NULL OR anomaly_status = 'dismissed') AND user_id = ",
+    );
+    builder
+        .push_bind(budget.user_id.clone())
+        .push(" AND date_time >= ")
+        .push_bind(start)
+        .push(" AND date_time < ")
+        .push_bind(end);
+    if let Some(vehicle) = &budget.vehicle {
+        builder
+            .push(" AND vehicle = ")
+            .push_bind(vehicle.clone())
+            .push(" COLLATE NOCASE");
+    }
+    let spent: f64 = builder.build_query_scalar().fetch_one(&mut *conn).await?;
+
+    Ok(budget_progress(budget, spent, (start, end, label), now))
+}
+
+async fn get_budgets_progress(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> Result<Vec<BudgetProgress>> {
+    let budgets = sqlx::query_as::<_, Budget>(&format!(
+        "SELECT {} FROM budgets WHERE user_id = ? ORDER BY created_at ASC, id ASC",
+        BUDGET_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let time_zone = get_user_time_zone(conn, user_id).await?;
+    let now = Utc::now();
+    let mut progress = Vec::with_capacity(budgets.len());
+    for budget in budgets {
+        progress.push(get_budget_progress(conn, budget, time_zone, now).await?);
+    }
+
+    Ok(progress)
+}
+
+// Records an alert for every threshold the user's budgets have reached in the
+// current period and queues it for the user's `budget.alert` webhooks. Each
+// threshold alerts once per period; alerts already raised are left alone, also
+// when spending drops again.
+async fn raise_budget_alerts(conn: &mut SqliteConnection, user_id: &str) -> Result<()> {
+    let now = Utc::now();
+    for progress in get_budgets_progress(conn, user_id).await? {
+        for threshold in crossed_thresholds(&progress) {
+            let alert = sqlx::query_as::<_, BudgetAlert>(&format!(
+                r#"
+                INSERT OR IGNORE INTO budget_alerts
+                    (id, budget_id, user_id, period_label, threshold, spent, amount, message, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                RETURNING {}
+                "#,
+                BUDGET_ALERT_COLUMNS
+            ))
+            .bind(Uuid::new_v4().to_string())
+            .bind(&progress.budget.id)
+            .bind(user_id)
+            .bind(&progress.period_label)
+            .bind(threshold)
+            .bind(progress.spent)
+            .bind(progress.budget.amount)
+            .bind(alert_message(&progress, threshold))
+            .bind(now)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            if let Some(alert) = alert {
+                enqueue_webhook_event(
+                    conn,
+                    WebhookEvent::BudgetAlert,
+                    user_id,
+                    json!({ "alert": alert }),
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn get_budgets(pool: &SqlitePool, user_id: &str) -> Result<Vec<BudgetProgress>> {
+    let mut conn = pool.acquire().await?;
+    get_budgets_progress(&mut conn, user_id).await
+}
+
+// Budgets are unique per user, period and vehicle (or no vehicle). Invalid or
+// duplicate budgets fail with a `ValidationError`.
+pub async fn create_budget(
+    pool: &SqlitePool,
+    user_id: &str,
+    request: &CreateBudgetRequest,
+) -> Result<BudgetProgress> {
+    validate_budget(request).into_result()?;
+    let vehicle = normalize_vehicle(request.vehicle.as_deref());
+
+    let mut tx = pool.begin().await?;
+    let existing: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM budgets WHERE user_id = ? AND period = ? AND vehicle IS ?",
+    )
+    .bind(user_id)
+    .bind(request.period.as_str())
+    .bind(&vehicle)
+    .fetch_one(&mut *tx)
+    .await?;
+    if existing > 0 {
+        let mut errors = ValidationError::default();
+        let scope = match &vehicle {
+            Some(vehicle) => format!("for vehicle '{}'", vehicle),
+            None => "for all vehicles".to_string(),
+        };
+        errors.add(
+            "period",
+            format!(
+                "a {} budget {} already exists",
+                request.period.as_str(),
+                scope
+            ),
+        );
+        return Err(errors.into());
+    }
+
+    let now = Utc::now();
+    let budget = Budget {
+        id: Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        period: request.period.as_str().to_string(),
+        vehicle,
+        amount: request.amount,
+        created_at: now,
+        updated_at: now,
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO budgets (id, user_id, period, vehicle, amount, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&budget.id)
+    .bind(&budget.user_id)
+    .bind(&budget.period)
+    .bind(&budget.vehicle)
+    .bind(budget.amount)
+    .bind(budget.created_at)
+    .bind(budget.updated_at)
+    .execute(&mut *tx)
+    .await?;
+
+    raise_budget_alerts(&mut tx, user_id).await?;
+    let time_zone = get_user_time_zone(&mut tx, user_id).await?;
+    let progress = get_budget_progress(&mut tx, budget, time_zone, now).await?;
+    tx.commit().await?;
+
+    Ok(progress)
+}
+
+// Changes the amount of a budget. Returns None when the user has no such budget.
+pub async fn update_budget(
+    pool: &SqlitePool,
+    user_id: &str,
+    id: &str,
+    request: &UpdateBudgetRequest,
+) -> Result<Option<BudgetProgress>> {
+    validate_budget_amount(request.amount).into_result()?;
+
+    let mut tx = pool.begin().await?;
+    let now = Utc::now();
+    let budget = sqlx::query_as::<_, Budget>(&format!(
+        "UPDATE budgets SET amount = ?, updated_at = ? WHERE id = ? AND user_id = ? RETURNING {}",
+        BUDGET_COLUMNS
+    ))
+    .bind(request.amount)
+    .bind(now)
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(budget) = budget else {
+        return Ok(None);
+    };
+
+    raise_budget_alerts(&mut tx, user_id).await?;
+    let time_zone = get_user_time_zone(&mut tx, user_id).await?;
+    let progress = get_budget_progress(&mut tx, budget, time_zone, now).await?;
+    tx.commit().await?;
+
+    Ok(Some(progress))
+}
+
+// Deletes a budget along with its alerts. Returns false when the user has no
+// such budget.
+pub async fn delete_budget(pool: &SqlitePool, user_id: &str, id: &str) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let deleted = sqlx::query("DELETE FROM budgets WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Ok(false);
+    }
+    sqlx::query("DELETE FROM budget_alerts WHERE budget_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+// The user's budget alerts, newest first
+pub async fn get_notifications(
+    pool: &SqlitePool,
+    user_id: &str,
+    unread_only: bool,
+) -> Result<Vec<BudgetAlert>> {
+    let mut builder = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM budget_alerts WHERE user_id = ",
+        BUDGET_ALERT_COLUMNS
+    ));
+    builder.push_bind(user_id.to_string());
+    if unread_only {
+        builder.push(" AND read_at IS NULL");
+    }
+    builder.push(" ORDER BY created_at DESC, id DESC");
+
+    let alerts = builder.build_query_as().fetch_all(pool).await?;
+    Ok(alerts)
+}
+
+// Marks the given notifications, or all of them when `ids` is None, as read.
+// Returns how many were unread before.
+pub async fn mark_notifications_read(
+    pool: &SqlitePool,
+    user_id: &str,
+    ids: Option<&[String]>,
+) -> Result<u64> {
+    let mut builder = QueryBuilder::<Sqlite>::new("UPDATE budget_alerts SET read_at = ");
+    builder
+        .push_bind(Utc::now())
+        .push(" WHERE read_at IS NULL AND user_id = ")
+        .push_bind(user_id.to_string());
+    if let Some(ids) = ids {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        builder.push(" AND id IN (");
+        let mut separated = builder.separated(", ");
+        for id in ids {
+            separated.push_bind(id.clone());
+        }
+        separated.push_unseparated(")");
+    }
+
+    let updated = builder.build().execute(pool).await?;
+    Ok(updated.rows_affected())
+}
+
+pub async fn get_notification_channels(
+    pool: &SqlitePool,
+    user_id: &str,
+) -> Result<Vec<NotificationChannelConfig>> {
+    let channels = sqlx::query_as::<_, NotificationChannelConfig>(
+        "SELECT id, user_id, kind, target, created_at FROM notification_channels WHERE user_id = ? ORDER BY created_at ASC, id ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(channels)
+}
+
+// Email channels without a target go to the account's address. Invalid targets
+// fail with a `ValidationError`.
+pub async fn create_notification_channel(
+    pool: &SqlitePool,
+    user_id: &str,
+    request: &CreateNotificationChannelRequest,
+) -> Result<NotificationChannelConfig> {
+    let target = match request.target.as_deref().map(str::trim) {
+        Some(target) if !target.is_empty() => target.to_string(),
+        _ if request.kind == NotificationChannelKind::Email => {
+            sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+                .bind(user_id)
+                .fetch_one(pool)
+                .await?
+        }
+        _ => {
+            let mut errors = ValidationError::default();
+            errors.add("target", "is required");
+            return Err(errors.into());
+        }
+    };
+    validate_notification_target(request.kind, &target).into_result()?;
+
+    let channel = NotificationChannelConfig {
+        id: Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        kind: request.kind.as_str().to_string(),
+        target,
+        created_at: Utc::now(),
+    };
+    sqlx::query(
+        "INSERT INTO notification_channels (id, user_id, kind, target, created_at) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&channel.id)
+    .bind(&channel.user_id)
+    .bind(&channel.kind)
+    .bind(&channel.target)
+    .bind(channel.created_at)
+    .execute(pool)
+    .await?;
+
+    Ok(channel)
+}
+
+// Returns false when the user has no such channel
+pub async fn delete_notification_channel(
+    pool: &SqlitePool,
+    user_id: &str,
+    id: &str,
+) -> Result<bool> {
+    let deleted = sqlx::query("DELETE FROM notification_channels WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+// Alerts of active users not yet handed to their channels, oldest first
+pub async fn get_undelivered_alerts(pool: &SqlitePool) -> Result<Vec<BudgetAlert>> {
+    let alerts = sqlx::query_as::<_, BudgetAlert>(&format!(
+        r#"
+        SELECT {} FROM budget_alerts
+        WHERE delivered_at IS NULL
+            AND user_id IN (SELECT id FROM users WHERE deleted_at IS NULL)
+        ORDER BY created_at ASC, id ASC
+        LIMIT ?
+        "#,
+        BUDGET_ALERT_COLUMNS
+    ))
+    .bind(ALERT_DELIVERY_BATCH_SIZE)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(alerts)
+}
+
+pub async fn mark_alert_delivered(pool: &SqlitePool, id: &str) -> Result<()> {
+    sqlx::query("UPDATE budget_alerts SET delivered_at = ? WHERE id = ?")
+        .bind(Utc::now())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+const WEBHOOK_COLUMNS: &str = "id, user_id, url, secret, events, created_at";
+
+const WEBHOOK_DELIVERY_COLUMNS: &str = "id, webhook_id, event, payload, status, attempts, \
+     next_attempt_at, last_status_code, last_error, created_at, delivered_at";
+
+// At most this many due deliveries are attempted per run of the delivery job
+const WEBHOOK_DELIVERY_BATCH_SIZE: i64 = 50;
+
+// Queues `event` about `user_id` for the user's webhooks and every admin-level
+// webhook subscribed to it, in the caller's transaction so nothing is sent for
+// changes that roll back. All subscribers get the same payload and event id.
+async fn enqueue_webhook_event(
+    conn: &mut SqliteConnection,
+    event: WebhookEvent,
+    user_id: &str,
+    data: Value,
+) -> Result<()> {
+    let webhooks = sqlx::query_as::<_, Webhook>(&format!(
+        "SELECT {} FROM webhooks WHERE user_id = ? OR user_id IS NULL",
+        WEBHOOK_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let subscribed: Vec<&Webhook> = webhooks
+        .iter()
+        .filter(|webhook| webhook.events.contains(&event))
+        .collect();
+    if subscribed.is_empty() {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let payload = json!({
+        "id": Uuid::new_v4().to_string(),
+        "event": event.as_str(),
+        "created_at": now,
+        "user_id": user_id,
+        "data": data
+    })
+    .to_string();
+
+    for webhook in subscribed {
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (id, webhook_id, event, payload, status, next_attempt_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&webhook.id)
+        .bind(event.as_str())
+        .bind(&payload)
+        .bind(WebhookDeliveryStatus::Pending.as_str())
+        .bind(now)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// Webhook payload data for a user; the password hash is never sent
+fn user_webhook_data(user: &User) -> Value {
+    json!({
+        "user": {
+            "id": user.id,
+            "email": user.email,
+            "created_at": user.created_at,
+            "deleted_at": user.deleted_at
+        }
+    })
+}
+
+// Webhooks of `owner`, or the admin-level ones when `owner` is None
+pub async fn get_webhooks(pool: &SqlitePool, owner: Option<&str>) -> Result<Vec<Webhook>> {
+    let webhooks = sqlx::query_as::<_, Webhook>(&format!(
+        "SELECT {} FROM webhooks WHERE user_id IS ? ORDER BY created_at ASC, id ASC",
+        WEBHOOK_COLUMNS
+    ))
+    .bind(owner)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(webhooks)
+}
+
+async fn get_webhook(pool: &SqlitePool, owner: Option<&str>, id: &str) -> Result<Option<Webhook>> {
+    let webhook = sqlx::query_as::<_, Webhook>(&format!(
+        "SELECT {} FROM webhooks WHERE id = ? AND user_id IS ?",
+        WEBHOOK_COLUMNS
+    ))
+    .bind(id)
+    .bind(owner)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(webhook)
+}
+
+// Subscribes a URL for `owner` (admin-level when None) with a new signing
+// secret. Invalid requests fail with a `ValidationError`.
+pub async fn create_webhook(
+    pool: &SqlitePool,
+    owner: Option<&str>,
+    request: &CreateWebhookRequest,
+) -> Result<CreatedWebhook> {
+    validate_webhook(request).into_result()?;
+
+    let mut events = Vec::new();
+    for event in &request.events {
+        if !events.contains(event) {
+            events.push(*event);
+        }
+    }
+    let webhook = Webhook {
+        id: Uuid::new_v4().to_string(),
+        user_id: owner.map(str::to_string),
+        url: request.url.trim().to_string(),
+        secret: new_webhook_secret(),
+        events: sqlx::types::Json(events),
+        created_at: Utc::now(),
+    };
+    sqlx::query(
+        "INSERT INTO webhooks (id, user_id, url, secret, events, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&webhook.id)
+    .bind(&webhook.user_id)
+    .bind(&webhook.url)
+    .bind(&webhook.secret)
+    .bind(&webhook.events)
+    .bind(webhook.created_at)
+    .execute(pool)
+    .await?;
+
+    Ok(CreatedWebhook {
+        secret: webhook.secret.clone(),
+        webhook,
+    })
+}
+
+// Deletes a webhook along with its delivery log. Returns false when `owner`
+// has no such webhook.
+pub async fn delete_webhook(pool: &SqlitePool, owner: Option<&str>, id: &str) -> Result<bool> {
+    if get_webhook(pool, owner, id).await?.is_none() {
+        return Ok(false);
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "DELETE FROM webhook_delivery_attempts WHERE delivery_id IN (SELECT id FROM webhook_deliveries WHERE webhook_id = ?)",
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM webhook_deliveries WHERE webhook_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM webhooks WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+async fn with_delivery_attempts(
+    pool: &SqlitePool,
+    delivery: WebhookDelivery,
+) -> Result<WebhookDeliveryLog> {
+    let attempts_log = sqlx::query_as::<_, WebhookDeliveryAttempt>(
+        r#"
+        SELECT attempted_at, status_code, error, duration_ms
+        FROM webhook_delivery_attempts
+        WHERE delivery_id = ?
+        ORDER BY attempted_at ASC, id ASC
+        "#,
+    )
+    .bind(&delivery.id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(WebhookDeliveryLog {
+        payload: serde_json::from_str(&delivery.payload).unwrap_or(Value::Null),
+        delivery,
+        attempts_log,
+    })
+}
+
+// Delivery log of one webhook, newest first. None when `owner` has no such
+// webhook.
+pub async fn get_webhook_deliveries(
+    pool: &SqlitePool,
+    owner: Option<&str>,
+    webhook_id: &str,
+    status: Option<WebhookDeliveryStatus>,
+    limit: i64,
+) -> Result<Option<Vec<WebhookDeliveryLog>>> {
+    if get_webhook(pool, owner, webhook_id).await?.is_none() {
+        return Ok(None);
+    }
+
+    let mut builder = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM webhook_deliveries WHERE webhook_id = ",
+        WEBHOOK_DELIVERY_COLUMNS
+    ));
+    builder.push_bind(webhook_id.to_string());
+    if let Some(status) = status {
+        builder.push(" AND status = ").push_bind(status.as_str());
+    }
+    builder
+        .push(" ORDER BY created_at DESC, id DESC LIMIT ")
+        .push_bind(limit);
+    let deliveries: Vec<WebhookDelivery> = builder.build_query_as().fetch_all(pool).await?;
+
+    let mut log = Vec::with_capacity(deliveries.len());
+    for delivery in deliveries {
+        log.push(with_delivery_attempts(pool, delivery).await?);
+    }
+
+    Ok(Some(log))
+}
+
+// Queues a delivery to be sent again right away with a fresh set of retries,
+// whatever its state. The payload is unchanged, so receivers can recognise the
+// event by its id. None when `owner` has no such webhook or delivery.
+pub async fn redeliver_webhook(
+    pool: &SqlitePool,
+    owner: Option<&str>,
+    webhook_id: &str,
+    delivery_id: &str,
+) -> Result<Option<WebhookDeliveryLog>> {
+    if get_webhook(pool, owner, webhook_id).await?.is_none() {
+        return Ok(None);
+    }
+
+    let delivery = sqlx::query_as::<_, WebhookDelivery>(&format!(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = ?, attempts = 0, next_attempt_at = ?, delivered_at = NULL
+        WHERE id = ? AND webhook_id = ?
+        RETURNING {}
+        "#,
+        WEBHOOK_DELIVERY_COLUMNS
+    ))
+    .bind(WebhookDeliveryStatus::Pending.as_str())
+    .bind(Utc::now())
+    .bind(delivery_id)
+    .bind(webhook_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match delivery {
+        Some(delivery) => Ok(Some(with_delivery_attempts(pool, delivery).await?)),
+        None => Ok(None),
+    }
+}
+
+// Pending deliveries whose next attempt is due at `now`, oldest first, with
+// the webhook each goes to
+pub async fn get_due_webhook_deliveries(
+    pool: &SqlitePool,
+    now: DateTime<Utc>,
+) -> Result<Vec<(WebhookDelivery, Webhook)>> {
+    let deliveries = sqlx::query_as::<_, WebhookDelivery>(&format!(
+        r#"
+        SELECT {} FROM webhook_deliveries
+        WHERE status = ? AND next_attempt_at <= ?
+        ORDER BY next_attempt_at ASC, id ASC
+        LIMIT ?
+        "#,
+        WEBHOOK_DELIVERY_COLUMNS
+    ))
+    .bind(WebhookDeliveryStatus::Pending.as_str())
+    .bind(now)
+    .bind(WEBHOOK_DELIVERY_BATCH_SIZE)
+    .fetch_all(pool)
+    .await?;
+
+    let mut due = Vec::with_capacity(deliveries.len());
+    let mut webhooks: std::collections::HashMap<String, Option<Webhook>> =
+        std::collections::HashMap::new();
+    for delivery in deliveries {
+        if !webhooks.contains_key(&delivery.webhook_id) {
+            let webhook = sqlx::query_as::<_, Webhook>(&format!(
+                "SELECT {} FROM webhooks WHERE id = ?",
+                WEBHOOK_COLUMNS
+            ))
+            .bind(&delivery.webhook_id)
+            .fetch_optional(pool)
+            .await?;
+            webhooks.insert(delivery.webhook_id.clone(), webhook);
+        }
+        if let Some(Some(webhook)) = webhooks.get(&delivery.webhook_id) {
+            due.push((delivery, webhook.clone()));
+        }
+    }
+
+    Ok(due)
+}
+
+// Logs one attempt at `delivery` and moves it on: delivered when there is no
+// `error`, otherwise retried after a growing delay until the attempts run out
+pub async fn record_webhook_attempt(
+    pool: &SqlitePool,
+    delivery: &WebhookDelivery,
+    status_code: Option<i64>,
+    error: Option<&str>,
+    duration_ms: i64,
+) -> Result<()> {
+    let now = Utc::now();
+    let attempts = delivery.attempts + 1;
+    let (status, next_attempt_at, delivered_at) = match error {
+        None => (WebhookDeliveryStatus::Delivered, None, Some(now)),
+        Some(_) if attempts >= WEBHOOK_MAX_ATTEMPTS => (WebhookDeliveryStatus::Failed, None, None),
+        Some(_) => (
+            WebhookDeliveryStatus::Pending,
+            Some(now + retry_delay(attempts)),
+            None,
+        ),
+    };
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+        INSERT INTO webhook_delivery_attempts (id, delivery_id, attempted_at, status_code, error, duration_ms)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&delivery.id)
+    .bind(now)
+    .bind(status_code)
+    .bind(error)
+    .bind(duration_ms)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = ?, attempts = ?, next_attempt_at = ?, last_status_code = ?, last_error = ?,
+            delivered_at = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(status.as_str())
+    .bind(attempts)
+    .bind(next_attempt_at)
+    .bind(status_code)
+    .bind(error)
+    .bind(delivered_at)
+    .bind(&delivery.id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Changes to the user's entries (including tombstones) after the `since` cursor,
+// oldest first
+pub async fn get_sync_changes(
+    pool: &SqlitePool,
+    user_id: &str,
+    since: i64,
+    limit: i64,
+) -> Result<SyncChanges> {
+    let purged_seq: i64 = sqlx::query_scalar("SELECT purged_seq FROM sync_state WHERE id = 1")
+        .fetch_one(pool)
+        .await?;
+
+    // Tombstones the client has not seen yet may already be purged
+    if since > 0 && since < purged_seq {
+        return Ok(SyncChanges {
+            changes: Vec::new(),
+            cursor: 0,
+            has_more: false,
+            reset: true,
+        });
+    }
+
+    // One extra row tells whether another page follows
+    let mut changes = sqlx::query_as::<_, SyncChange>(&format!(
+        r#"
+        SELECT {}, change_seq FROM fuel_entries
+        WHERE user_id = ? AND change_seq > ?
+        ORDER BY change_seq ASC
+        LIMIT ?
+        "#,
+        FUEL_ENTRY_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(since)
+    .bind(limit + 1)
+    .fetch_all(pool)
+    .await?;
+
+    let has_more = changes.len() as i64 > limit;
+    changes.truncate(limit as usize);
+    let cursor = changes.last().map(|c| c.change_seq).unwrap_or(since);
+
+    Ok(SyncChanges {
+        changes,
+        cursor,
+        has_more,
+        reset: false,
+    })
+}
+
+fn sync_result(
+    index: usize,
+    id: &str,
+    status: SyncStatus,
+    reason: Option<&str>,
+    entry: Option<FuelEntry>,
+) -> SyncResult {
+    SyncResult {
+        index,
+        id: id.to_string(),
+        status,
+        reason: reason.map(str::to_string),
+        entry,
+    }
+}
+
+// Applies a batch of offline changes in one transaction. Conflicts never abort
+// the batch: the server copy wins and is returned so the client can reconcile.
+// Any validation error rolls the whole batch back.
+pub async fn apply_sync_operations(
+    pool: &SqlitePool,
+    user_id: &str,
+    operations: &[SyncOperation],
+    actor: &Actor,
+) -> Result<Vec<SyncResult>> {
+    let mut results = Vec::new();
+    let mut errors = ValidationError::default();
+    let mut tx = pool.begin().await?;
+
+    for (index, operation) in operations.iter().enumerate() {
+        let prefix = format!("operations[{}]", index);
+
+        match operation {
+            SyncOperation::Create { entry: data } => {
+                let Some(id) = data.id.as_deref() else {
+                    errors.add(&format!("{}.id", prefix), "is required for sync creates");
+                    continue;
+                };
+                let fuel_entry = match new_fuel_entry(user_id, data) {
+                    Ok(entry) => entry,
+                    Err(entry_errors) => {
+                        errors.extend_prefixed(&prefix, entry_errors);
+                        continue;
+                    }
+                };
+
+                // A create that was already uploaded is a no-op; anything else
+                // under the same id keeps the server copy
+                match get_fuel_entry_any_user(&mut tx, id).await? {
+                    Some(existing) if existing.user_id != user_id => {
+                        results.push(sync_result(
+                            index,
+                            id,
+                            SyncStatus::Conflict,
+                            Some("id_taken"),
+                            None,
+                        ));
+                        continue;
+                    }
+                    Some(existing) if existing.deleted_at.is_some() => {
+                        results.push(sync_result(
+                            index,
+                            id,
+                            SyncStatus::Conflict,
+                            Some("deleted"),
+                            Some(existing),
+                        ));
+                        continue;
+                    }
+                    Some(existing) if same_fuel_data(&existing, &fuel_entry) => {
+                        results.push(sync_result(
+                            index,
+                            id,
+                            SyncStatus::Unchanged,
+                            None,
+                            Some(existing),
+                        ));
+                        continue;
+                    }
+                    Some(existing) => {
+                        results.push(sync_result(
+                            index,
+                            id,
+                            SyncStatus::Conflict,
+                            Some("exists"),
+                            Some(existing),
+                        ));
+                        continue;
+                    }
+                    None => {}
+                }
+
+                let entry_errors = validate_entry(&mut tx, &fuel_entry).await?;
+                if !entry_errors.is_empty() {
+                    errors.extend_prefixed(&prefix, entry_errors);
+                    continue;
+                }
+
+                insert_fuel_entry(&mut tx, &fuel_entry).await?;
+                record_revision(&mut tx, "created", None, &fuel_entry, actor).await?;
+                results.push(sync_result(
+                    index,
+                    id,
+                    SyncStatus::Applied,
+                    None,
+                    Some(fuel_entry),
+                ));
+            }
+            SyncOperation::Update {
+                id,
+                base_version,
+                changes,
+            } => {
+                let Some(base_version) = *base_version else {
+                    errors.add(&format!("{}.base_version", prefix), "is required");
+                    continue;
+                };
+                let entry = match get_fuel_entry_tx(&mut tx, id, user_id).await? {
+                    None => {
+                        results.push(sync_result(
+                            index,
+                            id,
+                            SyncStatus::Conflict,
+                            Some("not_found"),
+                            None,
+                        ));
+                        continue;
+                    }
+                    Some(entry) if entry.deleted_at.is_some() => {
+                        results.push(sync_result(
+                            index,
+                            id,
+                            SyncStatus::Conflict,
+                            Some("deleted"),
+                            Some(entry),
+                        ));
+                        continue;
+                    }
+                    Some(entry) if base_version != entry.version => {
+                        results.push(sync_result(
+                            index,
+                            id,
+                            SyncStatus::Conflict,
+                            Some("version_mismatch"),
+                            Some(entry),
+                        ));
+                        continue;
+                    }
+                    Some(entry) => entry,
+                };
+
+                let mut updated_entry = match updated_fuel_entry(&entry, changes) {
+                    Ok(updated_entry) => updated_entry,
+                    Err(entry_errors) => {
+                        errors.extend_prefixed(&prefix, entry_errors);
+                        continue;
+                    }
+                };
+
+                let entry_errors = validate_entry(&mut tx, &updated_entry).await?;
+                if !entry_errors.is_empty() {
+                    errors.extend_prefixed(&prefix, entry_errors);
+                    continue;
+                }
+
+                save_fuel_entry(&mut tx, &mut updated_entry).await?;
+                record_revision(&mut tx, "updated", Some(&entry), &updated_entry, actor).await?;
+                results.push(sync_result(
+                    index,
+                    id,
+                    SyncStatus::Applied,
+                    None,
+                    Some(updated_entry),
+                ));
+            }
+            SyncOperation::Delete { id, base_version } => {
+                let Some(base_version) = *base_version else {
+                    errors.add(&format!("{}.base_version", prefix), "is required");
+                    continue;
+                };
+                match get_fuel_entry_tx(&mut tx, id, user_id).await? {
+                    None => results.push(sync_result(
+                        index,
+                        id,
+                        SyncStatus::Conflict,
+                        Some("not_found"),
+                        None,
+                    )),
+                    Some(entry) if entry.deleted_at.is_some() => results.push(sync_result(
+                        index,
+                        id,
+                        SyncStatus::Unchanged,
+                        None,
+                        Some(entry),
+                    )),
+                    Some(entry) if base_version != entry.version => results.push(sync_result(
+                        index,
+                        id,
+                        SyncStatus::Conflict,
+                        Some("version_mismatch"),
+                        Some(entry),
+                    )),
+                    Some(_) => {
+                        set_fuel_entry_deleted(&mut tx, id, user_id, Some(Utc::now()), actor)
+                            .await?;
+                        let deleted_entry = get_fuel_entry_tx(&mut tx, id, user_id).await?;
+                        results.push(sync_result(
+                            index,
+                            id,
+                            SyncStatus::Applied,
+                            None,
+                            deleted_entry,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    // Nothing is stored unless every operation is valid
+    errors.into_result()?;
+    let refreshed = refresh_anomalies(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    for result in &mut results {
+        result.entry = result
+            .entry
+            .take()
+            .map(|entry| with_anomalies(entry, &refreshed));
+    }
+    Ok(results)
+}
+
+// Permanently removes entries and users whose tombstone is older than `cutoff`.
+// Returns the number of purged entries and users.
+pub async fn purge_deleted(pool: &SqlitePool, cutoff: DateTime<Utc>) -> Result<(u64, u64)> {
+    const PURGEABLE_ENTRIES: &str = r#"
+        (deleted_at IS NOT NULL AND deleted_at < ?)
+        OR user_id IN (SELECT id FROM users WHERE deleted_at IS NOT NULL AND deleted_at < ?)
+    "#;
+    const PURGEABLE_USERS: &str =
+        "SELECT id FROM users WHERE deleted_at IS NOT NULL AND deleted_at < ?";
+    const PURGEABLE_WEBHOOK_DELIVERIES: &str = r#"
+        (status != 'pending' AND created_at < ?)
+        OR webhook_id IN (SELECT id FROM webhooks WHERE user_id IN (SELECT id FROM users WHERE deleted_at IS NOT NULL AND deleted_at < ?))
+    "#;
+
+    let mut tx = pool.begin().await?;
+
+    // Sync clients behind the last purged change can no longer see its tombstone
+    sqlx::query(&format!(
+        r#"
+        UPDATE sync_state
+        SET purged_seq = MAX(purged_seq, (SELECT COALESCE(MAX(change_seq), 0) FROM fuel_entries WHERE {}))
+        WHERE id = 1
+        "#,
+        PURGEABLE_ENTRIES
+    ))
+    .bind(cutoff)
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    let entries = sqlx::query(&format!(
+        "DELETE FROM fuel_entries WHERE {}",
+        PURGEABLE_ENTRIES
+    ))
+    .bind(cutoff)
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    // The webhook delivery log is kept as long as the trash, apart from
+    // pending deliveries, which stay until they are sent or given up on.
+    for statement in [
+        "DELETE FROM webhook_delivery_attempts WHERE delivery_id IN (SELECT id FROM webhook_deliveries WHERE {})",
+        "DELETE FROM webhook_deliveries WHERE {}",
+    ] {
+        sqlx::query(&statement.replace("{}", PURGEABLE_WEBHOOK_DELIVERIES))
+            .bind(cutoff)
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // Rows referencing a purged user go first, as foreign keys are enforced
+    for table in [
+        "vehicles",
+        "budget_alerts",
+        "budgets",
+        "notification_channels",
+        "webhooks",
+    ] {
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE user_id IN ({})",
+            table, PURGEABLE_USERS
+        ))
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let users = sqlx::query(&format!(
+        "DELETE FROM users WHERE id IN ({})",
+        PURGEABLE_USERS
+    ))
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    // History of purged entries goes with them
+    sqlx::query(
+        "DELETE FROM fuel_entry_revisions WHERE entry_id NOT IN (SELECT id FROM fuel_entries)",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((entries.rows_affected(), users.rows_affected()))
+}
+
+// Claims `key` for a new request and returns None, or returns the record left by
+// an earlier request with the same key. Records created before `expired_before`
+// no longer count and are replaced.
+pub async fn claim_idempotency_key(
+    pool: &SqlitePool,
+    key: &str,
+    request_hash: &str,
+    expired_before: DateTime<Utc>,
+) -> Result<Option<IdempotencyRecord>> {
+    sqlx::query("DELETE FROM idempotency_keys WHERE key = ? AND created_at < ?")
+        .bind(key)
+        .bind(expired_before)
+        .execute(pool)
+        .await?;
+
+    // A single statement, so two concurrent requests cannot both claim the key
+    let claimed = sqlx::query(
+        "INSERT OR IGNORE INTO idempotency_keys (key, request_hash, created_at) VALUES (?, ?, ?)",
+    )
+    .bind(key)
+    .bind(request_hash)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    if claimed.rows_affected() > 0 {
+        return Ok(None);
+    }
+
+    let record = sqlx::query_as::<_, IdempotencyRecord>(
+        r#"
+        SELECT request_hash, status_code, response_headers, response_body
+        FROM idempotency_keys
+        WHERE key = ?
+        "#,
+    )
+    .bind(key)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record)
+}
+
+pub async fn store_idempotent_response(
+    pool: &SqlitePool,
+    key: &str,
+    status_code: u16,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE idempotency_keys SET status_code = ?, response_headers = ?, response_body = ? WHERE key = ?",
+    )
+    .bind(status_code as i64)
+    .bind(sqlx::types::Json(headers))
+    .bind(body)
+    .bind(key)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Gives the key up again, e.g. after a server error the client should be able to retry
+pub async fn release_idempotency_key(pool: &SqlitePool, key: &str) -> Result<()> {
+    sqlx::query("DELETE FROM idempotency_keys WHERE key = ?")
+        .bind(key)
+        .execute(pool)
+        .await?;
 
-    let users_with_most_entries = get_users_with_most_entries(pool).await?;
+    Ok(())
+}
+
+pub async fn purge_idempotency_keys(pool: &SqlitePool, cutoff: DateTime<Utc>) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM idempotency_keys WHERE created_at < ?")
+        .bind(cutoff)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Appends the dashboard scope to a query over fuel_entries whose WHERE clause is already open
+fn push_dashboard_filter(builder: &mut QueryBuilder<'_, Sqlite>, filter: &DashboardFilter) {
+    push_entry_filter(builder, &filter.entries);
+    push_user_ids(builder, "user_id", &filter.user_ids);
+}
+
+fn push_user_ids(builder: &mut QueryBuilder<'_, Sqlite>, column: &str, user_ids: &[String]) {
+    if user_ids.is_empty() {
+        return;
+    }
+    builder.push(format!(" AND {} IN (", column));
+    let mut separated = builder.separated(", ");
+    for user_id in user_ids {
+        separated.push_bind(user_id.clone());
+    }
+    separated.push_unseparated(")");
+}
+
+// Names of the seasons indexed by `UserLocale::season`
+const SEASONS: [&str; 4] = ["Spring", "Summer", "Autumn", "Winter"];
+
+// Most recent days with entries in the daily price trend
+const DAILY_PRICE_TREND_DAYS: usize = 90;
+
+// Months in each rolling price volatility window
+const VOLATILITY_WINDOW_MONTHS: i32 = 3;
+
+// Most recent windows listed in `monthly_volatility`
+const MAX_VOLATILITY_PERIODS: usize = 24;
+
+// Time zone and hemisphere of a user, for bucketing entries by local hour, day
+// and season
+#[derive(Clone, Copy)]
+struct UserLocale {
+    time_zone: Tz,
+    southern: bool,
+}
+
+impl Default for UserLocale {
+    fn default() -> Self {
+        UserLocale {
+            time_zone: Tz::UTC,
+            southern: false,
+        }
+    }
+}
+
+impl UserLocale {
+    fn of(user: &User) -> Self {
+        UserLocale {
+            // Stored names are validated on update; anything else falls back to UTC
+            time_zone: user.time_zone.parse().unwrap_or(Tz::UTC),
+            southern: user.hemisphere == "southern",
+        }
+    }
+
+    fn local(&self, date_time: DateTime<Utc>) -> DateTime<Tz> {
+        date_time.with_timezone(&self.time_zone)
+    }
+
+    // Index into `SEASONS` of a local month, using meteorological seasons
+    // (winter is December to February in the north, June to August in the south)
+    fn season(&self, month: u32) -> usize {
+        let northern = match month {
+            3..=5 => 0,
+            6..=8 => 1,
+            9..=11 => 2,
+            _ => 3,
+        };
+        if self.southern {
+            (northern + 2) % 4
+        } else {
+            northern
+        }
+    }
+}
+
+// Locales of the users in scope by user id
+async fn get_user_locales(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<std::collections::HashMap<String, UserLocale>> {
+    Ok(get_dashboard_users(pool, filter)
+        .await?
+        .iter()
+        .map(|user| (user.id.clone(), UserLocale::of(user)))
+        .collect())
+}
+
+// Live users the dashboard covers
+async fn get_dashboard_users(pool: &SqlitePool, filter: &DashboardFilter) -> Result<Vec<User>> {
+    let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM users WHERE deleted_at IS NULL");
+    push_user_ids(&mut builder, "id", &filter.user_ids);
+
+    let users = builder.build_query_as().fetch_all(pool).await?;
+    Ok(users)
+}
+
+// Live entries within the dashboard scope, newest first
+async fn get_dashboard_entries(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<Vec<FuelEntry>> {
+    let mut builder = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM fuel_entries WHERE deleted_at IS NULL",
+        FUEL_ENTRY_COLUMNS
+    ));
+    push_dashboard_filter(&mut builder, filter);
+    builder.push(" ORDER BY date_time DESC");
+
+    let entries = builder.build_query_as().fetch_all(pool).await?;
+    Ok(entries)
+}
+
+// Entry totals of one user within the dashboard scope
+#[derive(sqlx::FromRow)]
+struct UserTotals {
+    user_id: String,
+    email: String,
+    entry_count: i32,
+    total_cost: f64,
+    total_liters: f64,
+    first_entry: Option<DateTime<Utc>>,
+    last_entry: Option<DateTime<Utc>>,
+    time_zone: String,
+}
+
+// Totals for every covered user, including users without matching entries, in
+// one grouped query rather than one query per user
+async fn get_user_totals(pool: &SqlitePool, filter: &DashboardFilter) -> Result<Vec<UserTotals>> {
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT
+            users.id AS user_id,
+            users.email AS email,
+            COUNT(fuel_entries.id) AS entry_count,
+            COALESCE(SUM(fuel_entries.total_cost), 0.0) AS total_cost,
+            COALESCE(SUM(fuel_entries.liters), 0.0) AS total_liters,
+            MIN(fuel_entries.date_time) AS first_entry,
+            MAX(fuel_entries.date_time) AS last_entry,
+            users.time_zone AS time_zone
+        FROM users
+        LEFT JOIN fuel_entries
+            ON fuel_entries.user_id = users.id AND fuel_entries.deleted_at IS NULL
+        "#,
+    );
+    // The entry conditions go into the join so users without matches keep a row
+    push_entry_filter(&mut builder, &filter.entries);
+    builder.push(" WHERE users.deleted_at IS NULL");
+    push_user_ids(&mut builder, "users.id", &filter.user_ids);
+    builder.push(" GROUP BY users.id ORDER BY users.rowid");
+
+    let totals = builder.build_query_as().fetch_all(pool).await?;
+    Ok(totals)
+}
+
+// Top 10 entries within the dashboard scope by `order_by`
+async fn get_dashboard_top_entries(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+    order_by: &str,
+) -> Result<Vec<FuelEntry>> {
+    let mut builder = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM fuel_entries WHERE deleted_at IS NULL",
+        FUEL_ENTRY_COLUMNS
+    ));
+    push_dashboard_filter(&mut builder, filter);
+    builder.push(format!(" ORDER BY {} DESC LIMIT 10", order_by));
+
+    let entries = builder.build_query_as().fetch_all(pool).await?;
+    Ok(entries)
+}
+
+// Records a change the dashboard reads that is not an entry write
+async fn bump_data_version(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query("UPDATE sync_state SET data_version = data_version + 1 WHERE id = 1")
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+// Changes whenever anything the dashboard reads changes: every entry write
+// advances the sync sequence and everything else bumps `data_version`
+pub async fn get_dashboard_data_version(pool: &SqlitePool) -> Result<String> {
+    let (last_seq, data_version): (i64, i64) =
+        sqlx::query_as("SELECT last_seq, data_version FROM sync_state WHERE id = 1")
+            .fetch_one(pool)
+            .await?;
 
-    let mut most_expensive_entries = all_entries.clone();
-    most_expensive_entries.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
-    most_expensive_entries.truncate(10);
+    Ok(format!("{}:{}", last_seq, data_version))
+}
 
-    let mut recent_entries = all_entries.clone();
-    recent_entries.sort_by(|a, b| b.date_time.cmp(&a.date_time));
-    recent_entries.truncate(10);
+// Computes the requested sections only, over the entries and users in `filter`
+pub async fn get_dashboard_stats(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+    sections: &[DashboardSection],
+) -> Result<DashboardStats> {
+    let wants = |section: DashboardSection| sections.contains(&section);
 
-    let monthly_stats = get_monthly_stats(pool).await?;
-    let user_registration_stats = get_user_registration_stats(pool).await?;
-    let fuel_efficiency_stats = get_fuel_efficiency_stats(pool).await?;
-    let consumption_patterns = get_consumption_patterns(pool).await?;
-    let cost_analytics = get_cost_analytics(pool).await?;
-    let user_behavior_stats = get_user_behavior_stats(pool).await?;
-    let predictive_analytics = get_predictive_analytics(pool).await?;
-    let price_trends = get_price_trends(pool).await?;
+    let summary = if wants(DashboardSection::Summary) {
+        Some(get_dashboard_summary(pool, filter).await?)
+    } else {
+        None
+    };
+    let users_with_most_entries = if wants(DashboardSection::UsersWithMostEntries) {
+        Some(get_users_with_most_entries(pool, filter).await?)
+    } else {
+        None
+    };
+    let most_expensive_entries = if wants(DashboardSection::MostExpensiveEntries) {
+        Some(get_dashboard_top_entries(pool, filter, "total_cost").await?)
+    } else {
+        None
+    };
+    let recent_entries = if wants(DashboardSection::RecentEntries) {
+        Some(get_dashboard_top_entries(pool, filter, "date_time").await?)
+    } else {
+        None
+    };
+    let monthly_stats = if wants(DashboardSection::MonthlyStats) {
+        Some(get_monthly_stats(pool, filter).await?)
+    } else {
+        None
+    };
+    let user_registration_stats = if wants(DashboardSection::UserRegistrationStats) {
+        Some(get_user_registration_stats(pool, filter).await?)
+    } else {
+        None
+    };
+    let fuel_efficiency_stats = if wants(DashboardSection::FuelEfficiencyStats) {
+        Some(get_fuel_efficiency_stats(pool, filter).await?)
+    } else {
+        None
+    };
+    let consumption_patterns = if wants(DashboardSection::ConsumptionPatterns) {
+        Some(get_consumption_patterns(pool, filter).await?)
+    } else {
+        None
+    };
+    let cost_analytics = if wants(DashboardSection::CostAnalytics) {
+        Some(get_cost_analytics(pool, filter).await?)
+    } else {
+        None
+    };
+    let user_behavior_stats = if wants(DashboardSection::UserBehaviorStats) {
+        Some(get_user_behavior_stats(pool, filter).await?)
+    } else {
+        None
+    };
+    let predictive_analytics = if wants(DashboardSection::PredictiveAnalytics) {
+        Some(get_predictive_analytics(pool, filter).await?)
+    } else {
+        None
+    };
+    let price_trends = if wants(DashboardSection::PriceTrends) {
+        Some(get_price_trends(pool, filter).await?)
+    } else {
+        None
+    };
 
     Ok(DashboardStats {
-        total_users,
-        total_fuel_entries,
-        total_fuel_cost,
-        total_liters,
-        average_price_per_liter,
+        generated_at: Utc::now(),
+        summary,
         users_with_most_entries,
         most_expensive_entries,
         recent_entries,
@@ -421,185 +2987,300 @@ pub async fn get_dashboard_stats(pool: &SqlitePool) -> Result<DashboardStats> {
     })
 }
 
-pub async fn get_users_with_most_entries(pool: &SqlitePool) -> Result<Vec<UserEntryCount>> {
-    let users = sqlx::query_as::<_, User>("SELECT * FROM users")
-        .fetch_all(pool)
-        .await?;
-
-    let mut user_counts = Vec::new();
+async fn get_dashboard_summary(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<DashboardSummary> {
+    let mut builder =
+        QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM users WHERE deleted_at IS NULL");
+    push_user_ids(&mut builder, "id", &filter.user_ids);
+    let total_users: i32 = builder.build_query_scalar().fetch_one(pool).await?;
+
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT COUNT(*), COALESCE(SUM(total_cost), 0.0), COALESCE(SUM(liters), 0.0) \
+         FROM fuel_entries WHERE deleted_at IS NULL",
+    );
+    push_dashboard_filter(&mut builder, filter);
+    let (total_fuel_entries, total_fuel_cost, total_liters): (i32, f64, f64) =
+        builder.build_query_as().fetch_one(pool).await?;
 
-    for user in users {
-        let entries = get_fuel_entries_by_user(pool, &user.id).await?;
-        let entry_count = entries.len() as i32;
-        let total_cost: f64 = entries.iter().map(|e| e.total_cost).sum();
-        let total_liters: f64 = entries.iter().map(|e| e.liters).sum();
+    let average_price_per_liter = if total_liters > 0.0 {
+        total_fuel_cost / total_liters
+    } else {
+        0.0
+    };
 
-        user_counts.push(UserEntryCount {
-            user_id: user.id,
-            email: user.email,
-            entry_count,
-            total_cost,
-            total_liters,
-        });
-    }
+    Ok(DashboardSummary {
+        total_users,
+        total_fuel_entries,
+        total_fuel_cost,
+        total_liters,
+        average_price_per_liter,
+    })
+}
 
-    user_counts.sort_by(|a, b| b.entry_count.cmp(&a.entry_count));
+pub async fn get_users_with_most_entries(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<Vec<UserEntryCount>> {
+    let mut user_counts: Vec<UserEntryCount> = get_user_totals(pool, filter)
+        .await?
+        .into_iter()
+        .map(|totals| UserEntryCount {
+            user_id: totals.user_id,
+            email: totals.email,
+            entry_count: totals.entry_count,
+            total_cost: totals.total_cost,
+            total_liters: totals.total_liters,
+        })
+        .collect();
+
+    user_counts.sort_by_key(|u| std::cmp::Reverse(u.entry_count));
     user_counts.truncate(10);
 
     Ok(user_counts)
 }
 
-pub async fn get_monthly_stats(pool: &SqlitePool) -> Result<Vec<MonthlyStats>> {
-    let entries_db = sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries")
-        .fetch_all(pool)
-        .await?;
-
-    let mut all_entries = Vec::new();
-    for entry_db in entries_db {
-        let fuel_entry: FuelEntry = serde_json::from_str(&entry_db.data)?;
-        all_entries.push(fuel_entry);
-    }
-
-    use std::collections::HashMap;
-    let mut monthly_data: HashMap<String, (i32, f64, f64)> = HashMap::new();
+// Entry totals of one calendar month
+struct MonthTotals {
+    year: i32,
+    month: u32,
+    month_index: i32,
+    entries: i32,
+    cost: f64,
+    liters: f64,
+}
 
-    for entry in all_entries {
-        let month_key = format!("{}-{:02}", entry.date_time.year(), entry.date_time.month());
-        let (count, cost, liters) = monthly_data.get(&month_key).unwrap_or(&(0, 0.0, 0.0));
-        monthly_data.insert(
-            month_key,
-            (count + 1, cost + entry.total_cost, liters + entry.liters),
-        );
+// Totals per month within the dashboard scope, newest first. Entries count
+// towards the month in the time zone of the user who logged them.
+async fn get_monthly_totals(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<Vec<MonthTotals>> {
+    let locales = get_user_locales(pool, filter).await?;
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT user_id, date_time, total_cost, liters FROM fuel_entries WHERE deleted_at IS NULL",
+    );
+    push_dashboard_filter(&mut builder, filter);
+    let rows: Vec<(String, DateTime<Utc>, f64, f64)> =
+        builder.build_query_as().fetch_all(pool).await?;
+
+    let mut months: std::collections::BTreeMap<i32, (i32, f64, f64)> =
+        std::collections::BTreeMap::new();
+    for (user_id, date_time, cost, liters) in rows {
+        let locale = locales.get(&user_id).copied().unwrap_or_default();
+        let local = locale.local(date_time);
+        let month = months
+            .entry(month_index(local.year(), local.month()))
+            .or_default();
+        month.0 += 1;
+        month.1 += cost;
+        month.2 += liters;
     }
 
-    let mut stats = Vec::new();
-    for (month_key, (count, cost, liters)) in monthly_data {
-        let parts: Vec<&str> = month_key.split('-').collect();
-        let year = parts[0].parse::<i32>().unwrap_or(0);
-        let month_num = parts[1].parse::<u32>().unwrap_or(1);
-        let month_name = match month_num {
-            1 => "January",
-            2 => "February",
-            3 => "March",
-            4 => "April",
-            5 => "May",
-            6 => "June",
-            7 => "July",
-            8 => "August",
-            9 => "September",
-            10 => "October",
-            11 => "November",
-            12 => "December",
-            _ => "Unknown",
-        };
-
-        stats.push(MonthlyStats {
-            month: month_name.to_string(),
-            year,
-            total_entries: count,
-            total_cost: cost,
-            total_liters: liters,
-            average_price: if liters > 0.0 { cost / liters } else { 0.0 },
-        });
-    }
+    Ok(months
+        .into_iter()
+        .rev()
+        .map(|(x, (entries, cost, liters))| {
+            let (year, month) = month_from_index(x);
+            MonthTotals {
+                year,
+                month,
+                month_index: x,
+                entries,
+                cost,
+                liters,
+            }
+        })
+        .collect())
+}
 
-    stats.sort_by(|a, b| {
-        let a_date = (a.year, a.month.as_str());
-        let b_date = (b.year, b.month.as_str());
-        b_date.cmp(&a_date)
-    });
+pub async fn get_monthly_stats(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<Vec<MonthlyStats>> {
+    let stats = get_monthly_totals(pool, filter)
+        .await?
+        .into_iter()
+        .map(|totals| MonthlyStats {
+            month: month_name(totals.month).to_string(),
+            year: totals.year,
+            total_entries: totals.entries,
+            total_cost: totals.cost,
+            total_liters: totals.liters,
+            average_price: if totals.liters > 0.0 {
+                totals.cost / totals.liters
+            } else {
+                0.0
+            },
+        })
+        .collect();
 
     Ok(stats)
 }
 
-pub async fn get_user_registration_stats(pool: &SqlitePool) -> Result<Vec<UserRegistrationStats>> {
-    let users = sqlx::query_as::<_, User>("SELECT * FROM users")
-        .fetch_all(pool)
-        .await?;
-
-    use std::collections::HashMap;
-    let mut monthly_registrations: HashMap<String, i32> = HashMap::new();
-
-    for user in users {
-        let month_key = format!("{}-{:02}", user.created_at.year(), user.created_at.month());
-        let count = monthly_registrations.get(&month_key).unwrap_or(&0);
-        monthly_registrations.insert(month_key, count + 1);
-    }
-
-    let mut stats = Vec::new();
-    for (month_key, count) in monthly_registrations {
-        let parts: Vec<&str> = month_key.split('-').collect();
-        let year = parts[0].parse::<i32>().unwrap_or(0);
-        let month_num = parts[1].parse::<u32>().unwrap_or(1);
-        let month_name = match month_num {
-            1 => "January",
-            2 => "February",
-            3 => "March",
-            4 => "April",
-            5 => "May",
-            6 => "June",
-            7 => "July",
-            8 => "August",
-            9 => "September",
-            10 => "October",
-            11 => "November",
-            12 => "December",
-            _ => "Unknown",
-        };
-
-        stats.push(UserRegistrationStats {
-            month: month_name.to_string(),
-            year,
-            new_users: count,
-        });
+fn month_name(month_num: u32) -> &'static str {
+    match month_num {
+        1 => "January",
+        2 => "February",
+        3 => "March",
+        4 => "April",
+        5 => "May",
+        6 => "June",
+        7 => "July",
+        8 => "August",
+        9 => "September",
+        10 => "October",
+        11 => "November",
+        12 => "December",
+        _ => "Unknown",
     }
+}
 
-    stats.sort_by(|a, b| {
-        let a_date = (a.year, a.month.as_str());
-        let b_date = (b.year, b.month.as_str());
-        b_date.cmp(&a_date)
-    });
+pub async fn get_user_registration_stats(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<Vec<UserRegistrationStats>> {
+    // Newest month first
+    let stats = get_monthly_registrations(pool, filter)
+        .await?
+        .into_iter()
+        .rev()
+        .map(|(x, count)| {
+            let (year, month) = month_from_index(x);
+            UserRegistrationStats {
+                month: month_name(month).to_string(),
+                year,
+                new_users: count as i32,
+            }
+        })
+        .collect();
 
     Ok(stats)
 }
 
 pub async fn get_all_users(pool: &SqlitePool) -> Result<Vec<User>> {
-    let users = sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY created_at DESC")
-        .fetch_all(pool)
-        .await?;
+    let users = sqlx::query_as::<_, User>(
+        "SELECT * FROM users WHERE deleted_at IS NULL ORDER BY created_at DESC",
+    )
+    .fetch_all(pool)
+    .await?;
     Ok(users)
 }
 
-pub async fn delete_user_by_id(pool: &SqlitePool, user_id: &str) -> Result<bool> {
+// Soft-deletes the user together with their live entries. Both share one
+// timestamp so restoring the user brings back exactly those entries.
+pub async fn delete_user_by_id(pool: &SqlitePool, user_id: &str, actor: &Actor) -> Result<bool> {
+    let deleted_at = Utc::now();
     let mut tx = pool.begin().await?;
 
-    sqlx::query("DELETE FROM fuel_entries WHERE user_id = ?")
+    let result = sqlx::query("UPDATE users SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+        .bind(deleted_at)
         .bind(user_id)
         .execute(&mut *tx)
         .await?;
 
-    let result = sqlx::query("DELETE FROM users WHERE id = ?")
+    if result.rows_affected() > 0 {
+        let entry_ids: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM fuel_entries WHERE user_id = ? AND deleted_at IS NULL",
+        )
         .bind(user_id)
-        .execute(&mut *tx)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for entry_id in &entry_ids {
+            set_fuel_entry_deleted(&mut tx, entry_id, user_id, Some(deleted_at), actor).await?;
+        }
+
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        enqueue_webhook_event(
+            &mut tx,
+            WebhookEvent::UserDeleted,
+            user_id,
+            user_webhook_data(&user),
+        )
         .await?;
+        bump_data_version(&mut tx).await?;
+    }
 
     tx.commit().await?;
 
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn get_fuel_efficiency_stats(pool: &SqlitePool) -> Result<FuelEfficiencyStats> {
-    let users = get_all_users(pool).await?;
+pub async fn restore_user_by_id(pool: &SqlitePool, user_id: &str, actor: &Actor) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let deleted_at: Option<Option<String>> =
+        sqlx::query_scalar("SELECT deleted_at FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    let Some(Some(deleted_at)) = deleted_at else {
+        return Ok(false);
+    };
+
+    let entry_ids: Vec<String> =
+        sqlx::query_scalar("SELECT id FROM fuel_entries WHERE user_id = ? AND deleted_at = ?")
+            .bind(user_id)
+            .bind(&deleted_at)
+            .fetch_all(&mut *tx)
+            .await?;
+
+    for entry_id in &entry_ids {
+        set_fuel_entry_deleted(&mut tx, entry_id, user_id, None, actor).await?;
+    }
+
+    let user =
+        sqlx::query_as::<_, User>("UPDATE users SET deleted_at = NULL WHERE id = ? RETURNING *")
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+    enqueue_webhook_event(
+        &mut tx,
+        WebhookEvent::UserRestored,
+        user_id,
+        user_webhook_data(&user),
+    )
+    .await?;
+    bump_data_version(&mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+pub async fn get_fuel_efficiency_stats(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<FuelEfficiencyStats> {
+    let users = get_dashboard_users(pool, filter).await?;
     let mut user_efficiencies = Vec::new();
+    // Distance and liters of odometer segments per local month of the fill-up
+    let mut monthly_segments: std::collections::BTreeMap<i32, (f64, f64)> =
+        std::collections::BTreeMap::new();
     let mut total_odometer_users = 0;
     let mut total_distance = 0.0;
     let mut total_entries_with_odometer = 0;
 
+    // One read of all entries in scope, grouped per user
+    let mut entries_by_user: std::collections::HashMap<String, Vec<FuelEntry>> =
+        std::collections::HashMap::new();
+    for entry in get_dashboard_entries(pool, filter).await? {
+        entries_by_user
+            .entry(entry.user_id.clone())
+            .or_default()
+            .push(entry);
+    }
+
     for user in users {
-        let entries = get_fuel_entries_by_user(pool, &user.id).await?;
-        if entries.is_empty() {
+        let Some(entries) = entries_by_user.remove(&user.id) else {
             continue;
-        }
+        };
 
         let total_liters: f64 = entries.iter().map(|e| e.liters).sum();
         let avg_liters = total_liters / entries.len() as f64;
@@ -612,24 +3293,23 @@ pub async fn get_fuel_efficiency_stats(pool: &SqlitePool) -> Result<FuelEfficien
         };
 
         // Calculate distance if odometer readings are available
-        let mut distance_covered = 0.0;
-        let mut has_odometer_data = false;
-
         let mut sorted_entries = entries.clone();
-        sorted_entries.sort_by(|a, b| a.date_time.cmp(&b.date_time));
-
-        for window in sorted_entries.windows(2) {
-            if let (Some(prev_odo), Some(curr_odo)) =
-                (window[0].odometer_reading, window[1].odometer_reading)
-            {
-                if curr_odo > prev_odo {
-                    distance_covered += curr_odo - prev_odo;
-                    has_odometer_data = true;
-                }
-            }
+        sorted_entries.sort_by_key(|e| e.date_time);
+
+        let segments = odometer_segments(&sorted_entries);
+        let distance_covered: f64 = segments.iter().map(|(_, distance)| distance).sum();
+
+        let locale = UserLocale::of(&user);
+        for (entry, distance) in &segments {
+            let local = locale.local(entry.date_time);
+            let month = monthly_segments
+                .entry(month_index(local.year(), local.month()))
+                .or_default();
+            month.0 += distance;
+            month.1 += entry.liters;
         }
 
-        if has_odometer_data {
+        if !segments.is_empty() {
             total_odometer_users += 1;
             total_distance += distance_covered;
             total_entries_with_odometer += entries.len();
@@ -650,31 +3330,36 @@ pub async fn get_fuel_efficiency_stats(pool: &SqlitePool) -> Result<FuelEfficien
             .unwrap()
     });
 
+    // Newest month first, like the monthly statistics
+    let efficiency_trends = monthly_segments
+        .into_iter()
+        .rev()
+        .map(|(x, (distance, liters))| {
+            let (year, month) = month_from_index(x);
+            EfficiencyTrend {
+                month: month_name(month).to_string(),
+                year,
+                average_efficiency: if liters > 0.0 { distance / liters } else { 0.0 },
+                total_distance: Some(distance),
+                fuel_consumption_rate: liters / distance * 100.0,
+            }
+        })
+        .collect();
+
     let most_efficient = user_efficiencies.iter().take(5).cloned().collect();
     let least_efficient = user_efficiencies.iter().rev().take(5).cloned().collect();
 
-    let entries_db = sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries")
-        .fetch_all(pool)
-        .await?;
-
-    let total_entries = entries_db.len();
-    let average_fuel_per_entry = if total_entries > 0 {
-        let mut total_liters = 0.0;
-        for entry_db in &entries_db {
-            if let Ok(fuel_entry) = serde_json::from_str::<FuelEntry>(&entry_db.data) {
-                total_liters += fuel_entry.liters;
-            }
-        }
-        total_liters / total_entries as f64
-    } else {
-        0.0
-    };
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT COALESCE(AVG(liters), 0.0) FROM fuel_entries WHERE deleted_at IS NULL",
+    );
+    push_dashboard_filter(&mut builder, filter);
+    let average_fuel_per_entry: f64 = builder.build_query_scalar().fetch_one(pool).await?;
 
     Ok(FuelEfficiencyStats {
         average_fuel_per_entry,
         most_efficient_users: most_efficient,
         least_efficient_users: least_efficient,
-        efficiency_trends: vec![], // Simplified for now
+        efficiency_trends,
         odometer_analytics: OdometerAnalytics {
             users_with_odometer: total_odometer_users,
             users_without_odometer: (user_efficiencies.len() as i32) - total_odometer_users,
@@ -697,39 +3382,161 @@ pub async fn get_fuel_efficiency_stats(pool: &SqlitePool) -> Result<FuelEfficien
     })
 }
 
-pub async fn get_consumption_patterns(pool: &SqlitePool) -> Result<ConsumptionPatterns> {
-    let entries_db = sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries")
-        .fetch_all(pool)
-        .await?;
+// Distance driven before each fill-up, for consecutive entries (sorted by date)
+// of the same vehicle whose odometer readings both exist and increase. The
+// fill-up's liters are what that distance consumed. Entries without a vehicle
+// are treated as one vehicle.
+fn odometer_segments(sorted_entries: &[FuelEntry]) -> Vec<(&FuelEntry, f64)> {
+    let mut previous: std::collections::HashMap<Option<String>, &FuelEntry> =
+        std::collections::HashMap::new();
+    let mut segments = Vec::new();
+    for entry in sorted_entries {
+        let vehicle = entry.vehicle.as_ref().map(|vehicle| vehicle.to_lowercase());
+        if let Some(prev) = previous.insert(vehicle, entry) {
+            match (prev.odometer_reading, entry.odometer_reading) {
+                (Some(prev_odo), Some(curr_odo)) if curr_odo > prev_odo => {
+                    segments.push((entry, curr_odo - prev_odo))
+                }
+                _ => {}
+            }
+        }
+    }
+    segments
+}
+
+// Personal statistics for one user, using the same monthly and odometer
+// aggregation as the platform-wide dashboard
+pub async fn get_user_stats(pool: &SqlitePool, user_id: &str) -> Result<UserStats> {
+    let mut entries = get_fuel_entries_by_user(pool, user_id).await?;
+    entries.sort_by_key(|e| e.date_time);
+
+    // Months and years are those of the user's own time zone
+    let filter = DashboardFilter {
+        user_ids: vec![user_id.to_string()],
+        ..DashboardFilter::default()
+    };
+    let locale = get_user_locales(pool, &filter)
+        .await?
+        .remove(user_id)
+        .unwrap_or_default();
+    let monthly_totals = get_monthly_totals(pool, &filter).await?;
 
-    let mut all_entries = Vec::new();
-    for entry_db in entries_db {
-        if let Ok(fuel_entry) = serde_json::from_str::<FuelEntry>(&entry_db.data) {
-            all_entries.push(fuel_entry);
+    let total_cost: f64 = entries.iter().map(|e| e.total_cost).sum();
+    let total_liters: f64 = entries.iter().map(|e| e.liters).sum();
+
+    let segments = odometer_segments(&entries);
+    let mut economy_by_month: Vec<EconomyTrendPoint> = Vec::new();
+    let mut total_distance = 0.0;
+    let mut distance_cost = 0.0;
+    for (entry, distance) in &segments {
+        total_distance += distance;
+        distance_cost += entry.total_cost;
+
+        let month = locale.local(entry.date_time).format("%Y-%m").to_string();
+        match economy_by_month.last_mut() {
+            Some(point) if point.month == month => {
+                point.distance += distance;
+                point.liters += entry.liters;
+            }
+            _ => economy_by_month.push(EconomyTrendPoint {
+                month,
+                distance: *distance,
+                liters: entry.liters,
+                liters_per_100km: 0.0,
+            }),
         }
     }
+    for point in &mut economy_by_month {
+        point.liters_per_100km = point.liters / point.distance * 100.0;
+    }
 
-    // Analyze fill-up patterns
-    let mut small_fillups = 0;
-    let mut medium_fillups = 0;
-    let mut large_fillups = 0;
-    let total_liters: f64 = all_entries.iter().map(|e| e.liters).sum();
+    let longest_gap_between_fills = entries
+        .windows(2)
+        .map(|window| FillGap {
+            from: window[0].date_time,
+            to: window[1].date_time,
+            days: (window[1].date_time - window[0].date_time).num_days(),
+        })
+        .max_by_key(|gap| gap.to - gap.from);
+
+    let mut yearly: std::collections::BTreeMap<i32, (i32, f64, f64)> =
+        std::collections::BTreeMap::new();
+    for month in &monthly_totals {
+        let year = yearly.entry(month.year).or_default();
+        year.0 += month.entries;
+        year.1 += month.cost;
+        year.2 += month.liters;
+    }
 
-    for entry in &all_entries {
-        if entry.liters < 10.0 {
-            small_fillups += 1;
-        } else if entry.liters <= 30.0 {
-            medium_fillups += 1;
+    let percent_change = |current: f64, previous: f64| {
+        if previous > 0.0 {
+            Some((current - previous) / previous * 100.0)
         } else {
-            large_fillups += 1;
+            None
         }
+    };
+    let mut year_over_year: Vec<YearComparison> = Vec::new();
+    for (year, (count, cost, liters)) in yearly {
+        let previous = year_over_year.last().filter(|p| p.year == year - 1);
+        let comparison = YearComparison {
+            year,
+            total_entries: count,
+            total_cost: cost,
+            total_liters: liters,
+            average_price: if liters > 0.0 { cost / liters } else { 0.0 },
+            cost_change_percent: previous.and_then(|p| percent_change(cost, p.total_cost)),
+            liters_change_percent: previous.and_then(|p| percent_change(liters, p.total_liters)),
+        };
+        year_over_year.push(comparison);
     }
+    year_over_year.reverse();
 
-    let average_fillup_size = if !all_entries.is_empty() {
-        total_liters / all_entries.len() as f64
-    } else {
-        0.0
-    };
+    Ok(UserStats {
+        user_id: user_id.to_string(),
+        total_entries: entries.len() as i32,
+        total_cost,
+        total_liters,
+        average_price_paid: if total_liters > 0.0 {
+            total_cost / total_liters
+        } else {
+            0.0
+        },
+        monthly_stats: get_monthly_stats(pool, &filter).await?,
+        economy_trend: economy_by_month,
+        total_distance: if total_distance > 0.0 {
+            Some(total_distance)
+        } else {
+            None
+        },
+        cost_per_km: if total_distance > 0.0 {
+            Some(distance_cost / total_distance)
+        } else {
+            None
+        },
+        longest_gap_between_fills,
+        year_over_year,
+    })
+}
+
+pub async fn get_consumption_patterns(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<ConsumptionPatterns> {
+    // Analyze fill-up patterns
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT
+            COALESCE(SUM(CASE WHEN liters < 10.0 THEN 1 ELSE 0 END), 0),
+            COALESCE(SUM(CASE WHEN liters >= 10.0 AND liters <= 30.0 THEN 1 ELSE 0 END), 0),
+            COALESCE(SUM(CASE WHEN liters > 30.0 THEN 1 ELSE 0 END), 0),
+            COALESCE(AVG(liters), 0.0)
+        FROM fuel_entries
+        WHERE deleted_at IS NULL
+        "#,
+    );
+    push_dashboard_filter(&mut builder, filter);
+    let (small_fillups, medium_fillups, large_fillups, average_fillup_size): (i32, i32, i32, f64) =
+        builder.build_query_as().fetch_one(pool).await?;
 
     let most_common_range = if small_fillups >= medium_fillups && small_fillups >= large_fillups {
         "Small (< 10L)".to_string()
@@ -739,9 +3546,32 @@ pub async fn get_consumption_patterns(pool: &SqlitePool) -> Result<ConsumptionPa
         "Large (> 30L)".to_string()
     };
 
-    // Weekly patterns
-    let mut weekly_patterns = Vec::new();
-    let weekdays = [
+    // Weekdays, hours and seasons are local to the user who logged the entry
+    let locales = get_user_locales(pool, filter).await?;
+    let mut weekdays = [(0, 0.0, 0.0); 7];
+    let mut hours = [(0, 0.0, 0.0); 24];
+    let mut seasons = [(0, 0.0, 0.0); 4];
+    for entry in get_dashboard_entries(pool, filter).await? {
+        let locale = locales.get(&entry.user_id).copied().unwrap_or_default();
+        let local = locale.local(entry.date_time);
+
+        let weekday = &mut weekdays[local.weekday().num_days_from_monday() as usize];
+        weekday.0 += 1;
+        weekday.1 += entry.total_cost;
+        weekday.2 += entry.liters;
+
+        let hour = &mut hours[local.hour() as usize];
+        hour.0 += 1;
+        hour.1 += entry.total_cost;
+        hour.2 += entry.liters;
+
+        let season = &mut seasons[locale.season(local.month())];
+        season.0 += 1;
+        season.1 += entry.total_cost;
+        season.2 += entry.liters;
+    }
+
+    let weekly_patterns = [
         "Monday",
         "Tuesday",
         "Wednesday",
@@ -749,34 +3579,60 @@ pub async fn get_consumption_patterns(pool: &SqlitePool) -> Result<ConsumptionPa
         "Friday",
         "Saturday",
         "Sunday",
-    ];
-
-    for (i, day) in weekdays.iter().enumerate() {
-        let day_entries: Vec<_> = all_entries
-            .iter()
-            .filter(|e| e.date_time.weekday().num_days_from_monday() == i as u32)
-            .collect();
-
-        let entry_count = day_entries.len() as i32;
-        let average_cost = if !day_entries.is_empty() {
-            day_entries.iter().map(|e| e.total_cost).sum::<f64>() / day_entries.len() as f64
+    ]
+    .iter()
+    .zip(weekdays)
+    .map(|(day, (entry_count, cost, total_liters))| WeeklyPattern {
+        day_of_week: day.to_string(),
+        entry_count,
+        average_cost: if entry_count > 0 {
+            cost / entry_count as f64
         } else {
             0.0
-        };
-        let total_liters = day_entries.iter().map(|e| e.liters).sum();
+        },
+        total_liters,
+    })
+    .collect();
 
-        weekly_patterns.push(WeeklyPattern {
-            day_of_week: day.to_string(),
+    let daily_patterns = hours
+        .iter()
+        .enumerate()
+        .map(|(hour, &(entry_count, cost, liters))| DailyPattern {
+            hour_of_day: hour as i32,
             entry_count,
-            average_cost,
-            total_liters,
-        });
-    }
+            average_cost: if entry_count > 0 {
+                cost / entry_count as f64
+            } else {
+                0.0
+            },
+            average_liters: if entry_count > 0 {
+                liters / entry_count as f64
+            } else {
+                0.0
+            },
+        })
+        .collect();
+    let seasonal_patterns = SEASONS
+        .iter()
+        .zip(seasons)
+        .map(
+            |(season, (entry_count, total_cost, liters))| SeasonalPattern {
+                season: season.to_string(),
+                entry_count,
+                average_price_per_liter: if liters > 0.0 {
+                    total_cost / liters
+                } else {
+                    0.0
+                },
+                total_cost,
+            },
+        )
+        .collect();
 
     Ok(ConsumptionPatterns {
-        daily_patterns: vec![], // Simplified for now
+        daily_patterns,
         weekly_patterns,
-        seasonal_patterns: vec![], // Simplified for now
+        seasonal_patterns,
         fill_up_patterns: FillUpPatterns {
             small_fillups,
             medium_fillups,
@@ -787,101 +3643,86 @@ pub async fn get_consumption_patterns(pool: &SqlitePool) -> Result<ConsumptionPa
     })
 }
 
-pub async fn get_cost_analytics(pool: &SqlitePool) -> Result<CostAnalytics> {
-    let entries_db = sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries")
-        .fetch_all(pool)
-        .await?;
-
-    let mut all_entries = Vec::new();
-    for entry_db in entries_db {
-        if let Ok(fuel_entry) = serde_json::from_str::<FuelEntry>(&entry_db.data) {
-            all_entries.push(fuel_entry);
-        }
-    }
-
-    let mut low_cost = 0;
-    let mut medium_cost = 0;
-    let mut high_cost = 0;
-
-    for entry in &all_entries {
-        if entry.total_cost < 50.0 {
-            low_cost += 1;
-        } else if entry.total_cost <= 150.0 {
-            medium_cost += 1;
-        } else {
-            high_cost += 1;
-        }
-    }
-
-    let total_entries = all_entries.len() as i32;
-    let cost_ranges = vec![
-        CostRange {
-            range: "Low (< $50)".to_string(),
-            count: low_cost,
-            percentage: if total_entries > 0 {
-                (low_cost as f64 / total_entries as f64) * 100.0
-            } else {
-                0.0
-            },
-        },
-        CostRange {
-            range: "Medium ($50-$150)".to_string(),
-            count: medium_cost,
-            percentage: if total_entries > 0 {
-                (medium_cost as f64 / total_entries as f64) * 100.0
-            } else {
-                0.0
-            },
-        },
-        CostRange {
-            range: "High (> $150)".to_string(),
-            count: high_cost,
-            percentage: if total_entries > 0 {
-                (high_cost as f64 / total_entries as f64) * 100.0
-            } else {
-                0.0
-            },
-        },
-    ];
-
-    // Calculate user spending stats
-    let users = get_all_users(pool).await?;
-    let mut user_spendings = Vec::new();
-
-    for user in users {
-        let entries = get_fuel_entries_by_user(pool, &user.id).await?;
-        let total_spent: f64 = entries.iter().map(|e| e.total_cost).sum();
-        let average_per_entry = if !entries.is_empty() {
-            total_spent / entries.len() as f64
-        } else {
-            0.0
-        };
-
-        user_spendings.push(UserSpending {
-            user_id: user.id,
-            email: user.email,
-            total_spent,
-            average_per_entry,
-            entry_count: entries.len() as i32,
-        });
-    }
+pub async fn get_cost_analytics(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<CostAnalytics> {
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT total_cost, price_per_liter FROM fuel_entries WHERE deleted_at IS NULL",
+    );
+    push_dashboard_filter(&mut builder, filter);
+    let rows: Vec<(f64, f64)> = builder.build_query_as().fetch_all(pool).await?;
+    let total_cost: f64 = rows.iter().map(|(cost, _)| cost).sum();
+    let entry_costs = sorted(rows.iter().map(|(cost, _)| *cost));
+    let prices = sorted(rows.iter().map(|(_, price)| *price));
+    let entry_cost = summarize(&entry_costs);
+
+    // Low and high are the bottom and top quarter of entry costs
+    let low_cost = entry_costs.partition_point(|cost| *cost < entry_cost.p25) as i32;
+    let high_cost =
+        (entry_costs.len() - entry_costs.partition_point(|cost| *cost <= entry_cost.p75)) as i32;
+    let medium_cost = entry_costs.len() as i32 - low_cost - high_cost;
+    let cost_ranges = cost_histogram(&entry_costs, cost_histogram_edges().as_deref());
+
+    // Spend statistics cover the users with at least one entry in scope
+    let mut user_spendings: Vec<UserSpending> = get_user_totals(pool, filter)
+        .await?
+        .into_iter()
+        .filter(|totals| totals.entry_count > 0)
+        .map(|totals| UserSpending {
+            user_id: totals.user_id,
+            email: totals.email,
+            total_spent: totals.total_cost,
+            average_per_entry: totals.total_cost / totals.entry_count as f64,
+            entry_count: totals.entry_count,
+        })
+        .collect();
+    let per_user_spend = summarize(&sorted(user_spendings.iter().map(|u| u.total_spent)));
+
+    // Light and heavy spenders are the bottom and top quarter of users
+    let cost_distribution_by_user = if user_spendings.is_empty() {
+        vec![]
+    } else {
+        let light = user_spendings
+            .iter()
+            .filter(|u| u.total_spent < per_user_spend.p25)
+            .count();
+        let heavy = user_spendings
+            .iter()
+            .filter(|u| u.total_spent > per_user_spend.p75)
+            .count();
+        let moderate = user_spendings.len() - light - heavy;
+        [
+            (format!("Light (< ${:.2})", per_user_spend.p25), light),
+            (
+                format!(
+                    "Moderate (${:.2}-${:.2})",
+                    per_user_spend.p25, per_user_spend.p75
+                ),
+                moderate,
+            ),
+            (format!("Heavy (> ${:.2})", per_user_spend.p75), heavy),
+        ]
+        .into_iter()
+        .map(|(category, user_count)| UserCostCategory {
+            category,
+            user_count: user_count as i32,
+            percentage: percentage(user_count, user_spendings.len()),
+        })
+        .collect()
+    };
 
     user_spendings.sort_by(|a, b| b.total_spent.partial_cmp(&a.total_spent).unwrap());
     let top_spenders: Vec<UserSpending> = user_spendings.into_iter().take(10).collect();
 
-    let total_cost: f64 = all_entries.iter().map(|e| e.total_cost).sum();
-    let average_cost_per_user = if !top_spenders.is_empty() {
-        total_cost / top_spenders.len() as f64
-    } else {
-        0.0
-    };
-
     Ok(CostAnalytics {
         cost_distribution: CostDistribution {
             low_cost_entries: low_cost,
             medium_cost_entries: medium_cost,
             high_cost_entries: high_cost,
             cost_ranges,
+            entry_cost,
+            price_per_liter: summarize(&prices),
         },
         spending_trends: vec![], // Simplified for now
         budget_analysis: BudgetAnalysis {
@@ -891,32 +3732,31 @@ pub async fn get_cost_analytics(pool: &SqlitePool) -> Result<CostAnalytics> {
             spending_volatility: 0.0,
         },
         cost_per_user_stats: CostPerUserStats {
-            average_cost_per_user,
-            median_cost_per_user: average_cost_per_user,
+            average_cost_per_user: per_user_spend.mean,
+            median_cost_per_user: per_user_spend.median,
+            per_user_spend,
             top_spenders,
-            cost_distribution_by_user: vec![],
+            cost_distribution_by_user,
         },
     })
 }
 
-pub async fn get_user_behavior_stats(pool: &SqlitePool) -> Result<UserBehaviorStats> {
-    let users = get_all_users(pool).await?;
+pub async fn get_user_behavior_stats(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<UserBehaviorStats> {
     let mut user_activities = Vec::new();
 
-    for user in users {
-        let entries = get_fuel_entries_by_user(pool, &user.id).await?;
-        let entry_count = entries.len() as i32;
+    for totals in get_user_totals(pool, filter).await? {
+        let entry_count = totals.entry_count;
 
         // Calculate days active (simplified)
-        let days_active = if !entries.is_empty() {
-            let first_entry = entries.iter().min_by_key(|e| e.date_time).unwrap();
-            let last_entry = entries.iter().max_by_key(|e| e.date_time).unwrap();
-            let duration = last_entry
-                .date_time
-                .signed_duration_since(first_entry.date_time);
-            std::cmp::max(1, duration.num_days() as i32)
-        } else {
-            0
+        let days_active = match (totals.first_entry, totals.last_entry) {
+            (Some(first_entry), Some(last_entry)) => {
+                let duration = last_entry.signed_duration_since(first_entry);
+                std::cmp::max(1, duration.num_days() as i32)
+            }
+            _ => 0,
         };
 
         let average_entries_per_day = if days_active > 0 {
@@ -925,21 +3765,17 @@ pub async fn get_user_behavior_stats(pool: &SqlitePool) -> Result<UserBehaviorSt
             0.0
         };
 
-        let last_activity = if !entries.is_empty() {
-            entries
-                .iter()
-                .max_by_key(|e| e.date_time)
-                .unwrap()
-                .date_time
+        let last_activity = match totals.last_entry {
+            Some(last_entry) => last_entry
+                .with_timezone(&totals.time_zone.parse().unwrap_or(Tz::UTC))
                 .format("%Y-%m-%d")
-                .to_string()
-        } else {
-            "Never".to_string()
+                .to_string(),
+            None => "Never".to_string(),
         };
 
         user_activities.push(UserActivity {
-            user_id: user.id,
-            email: user.email,
+            user_id: totals.user_id,
+            email: totals.email,
             entry_count,
             days_active,
             average_entries_per_day,
@@ -947,25 +3783,17 @@ pub async fn get_user_behavior_stats(pool: &SqlitePool) -> Result<UserBehaviorSt
         });
     }
 
-    user_activities.sort_by(|a, b| b.entry_count.cmp(&a.entry_count));
+    user_activities.sort_by_key(|u| std::cmp::Reverse(u.entry_count));
     let most_active = user_activities.iter().take(10).cloned().collect();
     let least_active = user_activities.iter().rev().take(10).cloned().collect();
 
     // Calculate feature usage
-    let entries_db = sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries")
-        .fetch_all(pool)
-        .await?;
-
-    let mut entries_with_odometer = 0;
-    let total_entries = entries_db.len();
-
-    for entry_db in &entries_db {
-        if let Ok(fuel_entry) = serde_json::from_str::<FuelEntry>(&entry_db.data) {
-            if fuel_entry.odometer_reading.is_some() {
-                entries_with_odometer += 1;
-            }
-        }
-    }
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT COUNT(*), COUNT(odometer_reading) FROM fuel_entries WHERE deleted_at IS NULL",
+    );
+    push_dashboard_filter(&mut builder, filter);
+    let (total_entries, entries_with_odometer): (i64, i64) =
+        builder.build_query_as().fetch_one(pool).await?;
 
     let odometer_usage_rate = if total_entries > 0 {
         (entries_with_odometer as f64 / total_entries as f64) * 100.0
@@ -990,117 +3818,383 @@ pub async fn get_user_behavior_stats(pool: &SqlitePool) -> Result<UserBehaviorSt
             },
         },
         user_segments: vec![],
-        retention_analysis: RetentionAnalysis {
-            new_user_retention_7_day: 80.0,  // Simplified
-            new_user_retention_30_day: 60.0, // Simplified
-            active_user_retention: 75.0,     // Simplified
-            churn_rate: 5.0,                 // Simplified
-        },
+        retention_analysis: get_retention_analysis(pool, filter).await?,
     })
 }
 
-pub async fn get_predictive_analytics(pool: &SqlitePool) -> Result<PredictiveAnalytics> {
-    // Simple predictive analytics based on current trends
-    let current_stats = get_monthly_stats(pool).await?;
-    let users_count = get_all_users(pool).await?.len();
+const DEFAULT_CHURN_INACTIVITY_DAYS: i64 = 30;
 
-    let mut price_forecast = Vec::new();
-    let mut consumption_forecast = Vec::new();
-    let mut user_growth_forecast = Vec::new();
+// Signup cohorts in the retention matrix, most recent last
+const MAX_SIGNUP_COHORTS: usize = 12;
+
+// Users whose last entry is older than this count as churned
+pub fn churn_inactivity_days() -> i64 {
+    std::env::var("CHURN_INACTIVITY_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i64>().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_CHURN_INACTIVITY_DAYS)
+}
 
-    // Generate simple forecasts for next 6 months
-    let base_price = if !current_stats.is_empty() {
-        current_stats[0].average_price
+fn percentage(part: usize, whole: usize) -> f64 {
+    if whole > 0 {
+        part as f64 / whole as f64 * 100.0
     } else {
-        120.0
+        0.0
+    }
+}
+
+// Retention, churn and signup cohorts of the users in scope. A user is active
+// on the dates of their fuel entries.
+async fn get_retention_analysis(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<RetentionAnalysis> {
+    let users = get_dashboard_users(pool, filter).await?;
+
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT user_id, date_time FROM fuel_entries WHERE deleted_at IS NULL",
+    );
+    push_dashboard_filter(&mut builder, filter);
+    let rows: Vec<(String, DateTime<Utc>)> = builder.build_query_as().fetch_all(pool).await?;
+    let mut activity: std::collections::HashMap<String, Vec<DateTime<Utc>>> =
+        std::collections::HashMap::new();
+    for (user_id, date_time) in rows {
+        activity.entry(user_id).or_default().push(date_time);
+    }
+    let no_activity = Vec::new();
+    let activity_of = |user: &User| activity.get(&user.id).unwrap_or(&no_activity);
+
+    let now = Utc::now();
+
+    // Share of users who signed up at least `days` ago and came back to log an
+    // entry between one and `days` days after signing up
+    let new_user_retention = |days: i64| {
+        let eligible: Vec<&User> = users
+            .iter()
+            .filter(|user| now - user.created_at >= Duration::days(days))
+            .collect();
+        let retained = eligible
+            .iter()
+            .filter(|user| {
+                activity_of(user).iter().any(|date| {
+                    *date >= user.created_at + Duration::days(1)
+                        && *date <= user.created_at + Duration::days(days)
+                })
+            })
+            .count();
+        percentage(retained, eligible.len())
     };
 
-    let base_consumption = if !current_stats.is_empty() {
-        current_stats[0].total_liters
-    } else {
-        100.0
+    // Of the users active in the previous window, the share also active in the
+    // latest one; both windows are `churn_days` long
+    let churn_days = churn_inactivity_days();
+    let window = Duration::days(churn_days);
+    let active_between = |user: &User, from: DateTime<Utc>, to: DateTime<Utc>| {
+        activity_of(user)
+            .iter()
+            .any(|date| *date >= from && *date < to)
     };
+    let previously_active: Vec<&User> = users
+        .iter()
+        .filter(|user| active_between(user, now - window - window, now - window))
+        .collect();
+    let still_active = previously_active
+        .iter()
+        .filter(|user| active_between(user, now - window, now + Duration::days(1)))
+        .count();
 
-    for i in 1..=6 {
-        let month = match (6 + i) % 12 {
-            0 => 12,
-            m => m,
-        };
-        let year = if month <= 6 { 2026 } else { 2025 };
-        let month_name = match month {
-            1 => "January",
-            2 => "February",
-            3 => "March",
-            4 => "April",
-            5 => "May",
-            6 => "June",
-            7 => "July",
-            8 => "August",
-            9 => "September",
-            10 => "October",
-            11 => "November",
-            12 => "December",
-            _ => "Unknown",
-        };
+    // Churn only counts users who were ever active and had the chance to be
+    // inactive for a whole window
+    let churn_candidates: Vec<&User> = users
+        .iter()
+        .filter(|user| now - user.created_at >= window && !activity_of(user).is_empty())
+        .collect();
+    let churned_users = churn_candidates
+        .iter()
+        .filter(|user| {
+            activity_of(user)
+                .iter()
+                .max()
+                .is_some_and(|last| now - *last > window)
+        })
+        .count();
+
+    // Cohort row k is the share of the cohort active in the k-th calendar
+    // month after its signup month, up to the current month
+    let current_month = month_index(now.year(), now.month());
+    let mut cohorts: std::collections::BTreeMap<i32, Vec<&User>> =
+        std::collections::BTreeMap::new();
+    for user in &users {
+        let created_at = UserLocale::of(user).local(user.created_at);
+        cohorts
+            .entry(month_index(created_at.year(), created_at.month()))
+            .or_default()
+            .push(user);
+    }
+    let skip = cohorts.len().saturating_sub(MAX_SIGNUP_COHORTS);
+    let signup_cohorts = cohorts
+        .into_iter()
+        .skip(skip)
+        .map(|(cohort_month, members)| {
+            let retention = (cohort_month..=current_month)
+                .map(|month| {
+                    let active = members
+                        .iter()
+                        .filter(|user| {
+                            let locale = UserLocale::of(user);
+                            activity_of(user).iter().any(|date| {
+                                let date = locale.local(*date);
+                                month_index(date.year(), date.month()) == month
+                            })
+                        })
+                        .count();
+                    percentage(active, members.len())
+                })
+                .collect();
+            let (year, month) = month_from_index(cohort_month);
+            SignupCohort {
+                cohort: format!("{}-{:02}", year, month),
+                users: members.len() as i32,
+                retention,
+            }
+        })
+        .collect();
+
+    Ok(RetentionAnalysis {
+        new_user_retention_7_day: new_user_retention(7),
+        new_user_retention_30_day: new_user_retention(30),
+        active_user_retention: percentage(still_active, previously_active.len()),
+        churn_rate: percentage(churned_users, churn_candidates.len()),
+        churned_users: churned_users as i32,
+        churn_inactivity_days: churn_days,
+        signup_cohorts,
+    })
+}
 
-        // Simple price trend (slight increase over time)
-        let predicted_price = base_price * (1.0 + (i as f64 * 0.02));
+pub async fn get_predictive_analytics(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<PredictiveAnalytics> {
+    let monthly = get_monthly_totals(pool, filter).await?;
+    let users_count = get_dashboard_users(pool, filter).await?.len() as i32;
 
-        price_forecast.push(PriceForecast {
-            month: month_name.to_string(),
-            year: year as i32,
-            predicted_price,
-            confidence_level: 75.0,
-            trend_direction: "Increasing".to_string(),
-        });
+    // Forecasts roll forward from the current month; it is still incomplete,
+    // so volume series end with the month before it (or with `to`)
+    let now = Utc::now();
+    let current_month = month_index(now.year(), now.month());
+    let mut last_complete_month = current_month - 1;
+    if let Some(to) = filter.entries.to {
+        last_complete_month = last_complete_month.min(month_index(to.year(), to.month()));
+    }
 
-        // Simple consumption forecast
-        let predicted_consumption = base_consumption * (1.0 + (i as f64 * 0.05));
+    // Average price of every month with fuel bought
+    let mut price_points: Vec<(i32, f64)> = monthly
+        .iter()
+        .filter(|totals| totals.liters > 0.0)
+        .map(|totals| (totals.month_index, totals.cost / totals.liters))
+        .collect();
+    price_points.sort_by_key(|(x, _)| *x);
 
-        consumption_forecast.push(ConsumptionForecast {
-            month: month_name.to_string(),
-            year: year as i32,
-            predicted_consumption,
-            predicted_entries: (predicted_consumption / 10.0) as i32,
-        });
+    // Liters per complete month, months without entries counting as zero
+    let liters_by_month: std::collections::HashMap<i32, f64> = monthly
+        .iter()
+        .map(|totals| (totals.month_index, totals.liters))
+        .collect();
+    let consumption_points = zero_filled_months(
+        liters_by_month.keys().min().copied(),
+        last_complete_month,
+        |x| liters_by_month.get(&x).copied(),
+    );
+
+    let total_entries: i32 = monthly.iter().map(|totals| totals.entries).sum();
+    let total_liters: f64 = monthly.iter().map(|totals| totals.liters).sum();
+    let liters_per_entry = if total_entries > 0 {
+        total_liters / total_entries as f64
+    } else {
+        0.0
+    };
 
-        // Simple user growth forecast
-        let predicted_new_users = std::cmp::max(1, (users_count as f64 * 0.1) as i32);
-        let predicted_total_users = users_count as i32 + (predicted_new_users * i);
+    let registrations = get_monthly_registrations(pool, filter).await?;
+    let registration_points = zero_filled_months(
+        registrations.keys().min().copied(),
+        last_complete_month,
+        |x| registrations.get(&x).map(|count| *count as f64),
+    );
+
+    let price_model = MonthlyModel::fit(&price_points);
+    let consumption_model = MonthlyModel::fit(&consumption_points);
+    let registration_model = MonthlyModel::fit(&registration_points);
+
+    let price_trend = price_model.as_ref().map(|model| {
+        let mean_price =
+            price_points.iter().map(|(_, price)| price).sum::<f64>() / price_points.len() as f64;
+        trend_direction(model.slope(), mean_price)
+    });
 
-        user_growth_forecast.push(UserGrowthForecast {
-            month: month_name.to_string(),
-            year: year as i32,
-            predicted_new_users,
-            predicted_total_users,
-            growth_rate: 10.0,
-        });
+    let mut price_forecast = Vec::new();
+    let mut consumption_forecast = Vec::new();
+    let mut user_growth_forecast = Vec::new();
+    let mut predicted_total_users = users_count;
+
+    for x in current_month + 1..=current_month + 6 {
+        let (year, month) = month_from_index(x);
+        let month_name = month_name(month);
+
+        if let (Some(model), Some(trend)) = (&price_model, &price_trend) {
+            let forecast = model.forecast(x);
+            price_forecast.push(PriceForecast {
+                month: month_name.to_string(),
+                year,
+                predicted_price: forecast.value,
+                lower_bound: forecast.lower,
+                upper_bound: forecast.upper,
+                confidence_level: PREDICTION_INTERVAL_LEVEL,
+                trend_direction: trend.to_string(),
+            });
+        }
+
+        if let Some(model) = &consumption_model {
+            let forecast = model.forecast(x);
+            consumption_forecast.push(ConsumptionForecast {
+                month: month_name.to_string(),
+                year,
+                predicted_consumption: forecast.value,
+                lower_bound: forecast.lower,
+                upper_bound: forecast.upper,
+                predicted_entries: if liters_per_entry > 0.0 {
+                    (forecast.value / liters_per_entry).round() as i32
+                } else {
+                    0
+                },
+            });
+        }
+
+        if let Some(model) = &registration_model {
+            let predicted_new_users = model.forecast(x).value.round() as i32;
+            let growth_rate = if predicted_total_users > 0 {
+                predicted_new_users as f64 / predicted_total_users as f64 * 100.0
+            } else {
+                0.0
+            };
+            predicted_total_users += predicted_new_users;
+
+            user_growth_forecast.push(UserGrowthForecast {
+                month: month_name.to_string(),
+                year,
+                predicted_new_users,
+                predicted_total_users,
+                growth_rate,
+            });
+        }
     }
 
+    // Revenue is predicted price times predicted liters, month by month
+    let revenue = |months: i32| -> f64 {
+        match (&price_model, &consumption_model) {
+            (Some(price), Some(consumption)) => (current_month + 1..=current_month + months)
+                .map(|x| price.forecast(x).value * consumption.forecast(x).value)
+                .sum(),
+            _ => 0.0,
+        }
+    };
+
+    let growth_assumptions = match (&price_model, &consumption_model) {
+        (Some(price), Some(consumption)) => format!(
+            "Least-squares trend{} over {} months of prices and {} months of consumption, {}% prediction intervals",
+            if price.is_seasonal() || consumption.is_seasonal() {
+                " with monthly seasonality"
+            } else {
+                ""
+            },
+            price_points.len(),
+            consumption_points.len(),
+            PREDICTION_INTERVAL_LEVEL
+        ),
+        _ => "Not enough history to forecast; at least 3 months of entries are needed".to_string(),
+    };
+
     Ok(PredictiveAnalytics {
         fuel_price_forecast: price_forecast,
         consumption_forecast,
         user_growth_forecast,
         revenue_projections: RevenueProjections {
-            next_month_revenue: base_price * base_consumption * 1.1,
-            next_quarter_revenue: base_price * base_consumption * 3.2,
-            annual_revenue_projection: base_price * base_consumption * 12.5,
-            growth_assumptions: "Based on current trends with 10% growth rate".to_string(),
+            next_month_revenue: revenue(1),
+            next_quarter_revenue: revenue(3),
+            annual_revenue_projection: revenue(12),
+            growth_assumptions,
         },
+        price_backtest: backtest(&price_points),
+        consumption_backtest: backtest(&consumption_points),
     })
 }
 
-pub async fn get_price_trends(pool: &SqlitePool) -> Result<PriceTrends> {
-    let entries_db = sqlx::query_as::<_, FuelEntryDB>("SELECT * FROM fuel_entries")
-        .fetch_all(pool)
-        .await?;
+// One point per month from `first` through `last`, zero where `value` has none
+fn zero_filled_months(
+    first: Option<i32>,
+    last: i32,
+    value: impl Fn(i32) -> Option<f64>,
+) -> Vec<(i32, f64)> {
+    match first {
+        Some(first) => (first..=last)
+            .map(|x| (x, value(x).unwrap_or(0.0)))
+            .collect(),
+        None => Vec::new(),
+    }
+}
 
-    let mut all_entries = Vec::new();
-    for entry_db in entries_db {
-        if let Ok(fuel_entry) = serde_json::from_str::<FuelEntry>(&entry_db.data) {
-            all_entries.push(fuel_entry);
-        }
+// Moves of less than half a percent of the mean per month count as stable
+fn trend_direction(slope: f64, mean: f64) -> &'static str {
+    if mean <= 0.0 || (slope / mean).abs() < 0.005 {
+        "Stable"
+    } else if slope > 0.0 {
+        "Increasing"
+    } else {
+        "Decreasing"
+    }
+}
+
+// e.g. "January 2026 - March 2026"
+fn month_range_label(start: i32, end: i32) -> String {
+    let (start_year, start_month) = month_from_index(start);
+    let (end_year, end_month) = month_from_index(end);
+    format!(
+        "{} {} - {} {}",
+        month_name(start_month),
+        start_year,
+        month_name(end_month),
+        end_year
+    )
+}
+
+// Sign-ups per local month of the users covered by `filter` who signed up
+// within its date range, keyed by month index
+async fn get_monthly_registrations(
+    pool: &SqlitePool,
+    filter: &DashboardFilter,
+) -> Result<std::collections::BTreeMap<i32, i64>> {
+    let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM users WHERE deleted_at IS NULL");
+    push_user_ids(&mut builder, "id", &filter.user_ids);
+    if let Some(from) = filter.entries.from {
+        builder.push(" AND created_at >= ").push_bind(from);
+    }
+    if let Some(to) = filter.entries.to {
+        builder.push(" AND created_at <= ").push_bind(to);
+    }
+    let users: Vec<User> = builder.build_query_as().fetch_all(pool).await?;
+
+    let mut registrations = std::collections::BTreeMap::new();
+    for user in &users {
+        let created_at = UserLocale::of(user).local(user.created_at);
+        *registrations
+            .entry(month_index(created_at.year(), created_at.month()))
+            .or_default() += 1;
     }
+    Ok(registrations)
+}
+
+pub async fn get_price_trends(pool: &SqlitePool, filter: &DashboardFilter) -> Result<PriceTrends> {
+    let all_entries = get_dashboard_entries(pool, filter).await?;
 
     if all_entries.is_empty() {
         return Ok(PriceTrends {
@@ -1110,10 +4204,13 @@ pub async fn get_price_trends(pool: &SqlitePool) -> Result<PriceTrends> {
                 price_standard_deviation: 0.0,
                 most_volatile_period: "N/A".to_string(),
                 least_volatile_period: "N/A".to_string(),
+                window_months: VOLATILITY_WINDOW_MONTHS,
+                monthly_volatility: vec![],
             },
             price_comparisons: PriceComparisons {
-                current_vs_last_month: 0.0,
-                current_vs_last_year: 0.0,
+                current_period: "N/A".to_string(),
+                current_vs_last_month: None,
+                current_vs_last_year: None,
                 lowest_recorded_price: 0.0,
                 highest_recorded_price: 0.0,
                 price_change_percentage: 0.0,
@@ -1122,31 +4219,122 @@ pub async fn get_price_trends(pool: &SqlitePool) -> Result<PriceTrends> {
         });
     }
 
+    // Price range per local day of the most recent days with entries
+    let locales = get_user_locales(pool, filter).await?;
+    let mut prices_by_day: std::collections::BTreeMap<NaiveDate, Vec<f64>> =
+        std::collections::BTreeMap::new();
+    let mut entries_by_month: std::collections::BTreeMap<i32, Vec<&FuelEntry>> =
+        std::collections::BTreeMap::new();
+    for entry in &all_entries {
+        let locale = locales.get(&entry.user_id).copied().unwrap_or_default();
+        let local = locale.local(entry.date_time);
+        prices_by_day
+            .entry(local.date_naive())
+            .or_default()
+            .push(entry.price_per_liter);
+        entries_by_month
+            .entry(month_index(local.year(), local.month()))
+            .or_default()
+            .push(entry);
+    }
+    let skip = prices_by_day.len().saturating_sub(DAILY_PRICE_TREND_DAYS);
+    let daily_price_trends = prices_by_day
+        .into_iter()
+        .skip(skip)
+        .map(|(date, prices)| {
+            let min_price = prices.iter().copied().fold(f64::INFINITY, f64::min);
+            let max_price = prices.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            DailyPriceTrend {
+                date: date.format("%Y-%m-%d").to_string(),
+                average_price: prices.iter().sum::<f64>() / prices.len() as f64,
+                min_price,
+                max_price,
+                price_range: max_price - min_price,
+            }
+        })
+        .collect();
+
     // Calculate price statistics
     let prices: Vec<f64> = all_entries.iter().map(|e| e.price_per_liter).collect();
     let min_price = prices.iter().fold(f64::INFINITY, |a, &b| a.min(b));
     let max_price = prices.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
-    let avg_price = prices.iter().sum::<f64>() / prices.len() as f64;
-
-    // Calculate standard deviation
-    let variance = prices
+    let (avg_price, std_dev) = mean_and_std_dev(&prices);
+
+    // Volatility of the window of months ending at each month with entries,
+    // newest first; windows with fewer than two prices have no spread
+    let mut monthly_volatility: Vec<PeriodVolatility> = entries_by_month
+        .keys()
+        .rev()
+        .filter_map(|&end| {
+            let start = end - (VOLATILITY_WINDOW_MONTHS - 1);
+            let window: Vec<f64> = entries_by_month
+                .range(start..=end)
+                .flat_map(|(_, entries)| entries.iter().map(|e| e.price_per_liter))
+                .collect();
+            if window.len() < 2 {
+                return None;
+            }
+            let (mean, std_dev) = mean_and_std_dev(&window);
+            let (year, month) = month_from_index(end);
+            Some(PeriodVolatility {
+                period: month_range_label(start, end),
+                month: month_name(month).to_string(),
+                year,
+                entry_count: window.len() as i32,
+                price_standard_deviation: std_dev,
+                volatility_index: if mean > 0.0 {
+                    std_dev / mean * 100.0
+                } else {
+                    0.0
+                },
+            })
+        })
+        .collect();
+    let most_volatile_period = monthly_volatility
         .iter()
-        .map(|price| (price - avg_price).powi(2))
-        .sum::<f64>()
-        / prices.len() as f64;
-    let std_dev = variance.sqrt();
+        .max_by(|a, b| a.volatility_index.total_cmp(&b.volatility_index))
+        .map(|v| v.period.clone())
+        .unwrap_or_else(|| "N/A".to_string());
+    let least_volatile_period = monthly_volatility
+        .iter()
+        .min_by(|a, b| a.volatility_index.total_cmp(&b.volatility_index))
+        .map(|v| v.period.clone())
+        .unwrap_or_else(|| "N/A".to_string());
+    monthly_volatility.truncate(MAX_VOLATILITY_PERIODS);
+
+    // The latest month with entries against the month before and the same
+    // month a year earlier, using liters-weighted average prices
+    let average_price_of = |x: i32| {
+        entries_by_month.get(&x).and_then(|entries| {
+            let liters: f64 = entries.iter().map(|e| e.liters).sum();
+            let cost: f64 = entries.iter().map(|e| e.price_per_liter * e.liters).sum();
+            (liters > 0.0).then(|| cost / liters)
+        })
+    };
+    let change_percent = |current: Option<f64>, previous: Option<f64>| match (current, previous) {
+        (Some(current), Some(previous)) if previous > 0.0 => {
+            Some((current - previous) / previous * 100.0)
+        }
+        _ => None,
+    };
+    let latest_month = *entries_by_month.keys().next_back().unwrap();
+    let latest_price = average_price_of(latest_month);
+    let (latest_year, latest_month_num) = month_from_index(latest_month);
 
     Ok(PriceTrends {
-        daily_price_trends: vec![],
+        daily_price_trends,
         price_volatility: PriceVolatility {
             volatility_index: (std_dev / avg_price) * 100.0,
             price_standard_deviation: std_dev,
-            most_volatile_period: "June 2025".to_string(),
-            least_volatile_period: "June 2025".to_string(),
+            most_volatile_period,
+            least_volatile_period,
+            window_months: VOLATILITY_WINDOW_MONTHS,
+            monthly_volatility,
         },
         price_comparisons: PriceComparisons {
-            current_vs_last_month: 0.0,
-            current_vs_last_year: 0.0,
+            current_period: format!("{} {}", month_name(latest_month_num), latest_year),
+            current_vs_last_month: change_percent(latest_price, average_price_of(latest_month - 1)),
+            current_vs_last_year: change_percent(latest_price, average_price_of(latest_month - 12)),
             lowest_recorded_price: min_price,
             highest_recorded_price: max_price,
             price_change_percentage: ((max_price - min_price) / min_price) * 100.0,
@@ -1213,3 +4401,218 @@ pub async fn is_service_enabled(pool: &SqlitePool, service_name: &str) -> Result
 
     Ok(config.map(|c| c.enabled).unwrap_or(true))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::run_migrations;
+    use serde_json::json;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        run_migrations(&pool).await.unwrap();
+        pool
+    }
+
+    async fn page_through(pool: &SqlitePool, user_id: &str, order: &str) -> Vec<String> {
+        let mut ids = Vec::new();
+        let mut cursor: Option<String> = None;
+        // Bounded, so a cursor that never advances fails instead of hanging
+        for _ in 0..10 {
+            let query: FuelEntryListQuery =
+                serde_json::from_value(json!({ "order": order, "cursor": cursor })).unwrap();
+            let page = list_fuel_entries(pool, user_id, &query, Some(1))
+                .await
+                .unwrap();
+            ids.extend(page.entries.into_iter().map(|entry| entry.id));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn cursor_pages_visit_every_entry_once() {
+        let pool = test_pool().await;
+        let user = create_user(&pool, "u1@example.com", "x").await.unwrap();
+
+        // Two entries share a timestamp, one has a fractional second
+        let entries = [
+            (
+                "00000000-0000-4000-8000-00000000000a",
+                "2026-01-01T10:00:00Z",
+            ),
+            (
+                "00000000-0000-4000-8000-00000000000b",
+                "2026-01-01T10:00:00Z",
+            ),
+            (
+                "00000000-0000-4000-8000-00000000000c",
+                "2026-01-02T08:30:00Z",
+            ),
+            (
+                "00000000-0000-4000-8000-00000000000d",
+                "2026-01-03T08:30:00.250Z",
+            ),
+        ];
+        for (id, date_time) in entries {
+            let request: CreateFuelEntryRequest = serde_json::from_value(json!({
+                "user_id": user.id,
+                "id": id,
+                "liters": 40.0,
+                "price_per_liter": 1.5,
+                "date_time": date_time,
+            }))
+            .unwrap();
+            create_fuel_entry(&pool, &user.id, &request, &Actor::user(&user.id))
+                .await
+                .unwrap();
+        }
+
+        let ascending: Vec<String> = entries.iter().map(|(id, _)| id.to_string()).collect();
+        let descending: Vec<String> = ascending.iter().rev().cloned().collect();
+        assert_eq!(page_through(&pool, &user.id, "asc").await, ascending);
+        assert_eq!(page_through(&pool, &user.id, "desc").await, descending);
+    }
+
+    #[tokio::test]
+    async fn account_changes_move_the_dashboard_data_version() {
+        let pool = test_pool().await;
+        let initial = get_dashboard_data_version(&pool).await.unwrap();
+
+        let user = create_user(&pool, "u1@example.com", "x").await.unwrap();
+        let after_signup = get_dashboard_data_version(&pool).await.unwrap();
+        assert_ne!(after_signup, initial);
+
+        delete_user_by_id(&pool, &user.id, &Actor::admin())
+            .await
+            .unwrap();
+        let after_delete = get_dashboard_data_version(&pool).await.unwrap();
+        assert_ne!(after_delete, after_signup);
+
+        restore_user_by_id(&pool, &user.id, &Actor::admin())
+            .await
+            .unwrap();
+        assert_ne!(
+            get_dashboard_data_version(&pool).await.unwrap(),
+            after_delete
+        );
+    }
+
+    #[tokio::test]
+    async fn anomaly_flags_leave_versions_and_the_sync_feed_alone() {
+        let pool = test_pool().await;
+        let user = create_user(&pool, "u1@example.com", "x").await.unwrap();
+        let actor = Actor::user(&user.id);
+
+        let mut created = Vec::new();
+        for (day, price_per_liter) in [1.5, 1.52, 1.48, 1.51, 1.49, 9.9].into_iter().enumerate() {
+            let request: CreateFuelEntryRequest = serde_json::from_value(json!({
+                "user_id": user.id,
+                "liters": 40.0,
+                "price_per_liter": price_per_liter,
+                "date_time": format!("2026-01-{:02}T10:00:00Z", day + 1),
+            }))
+            .unwrap();
+            created.push(
+                create_fuel_entry(&pool, &user.id, &request, &actor)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let outlier = created.last().unwrap();
+        assert_eq!(outlier.anomaly_status.as_deref(), Some("pending"));
+
+        let last_seq = || async {
+            sqlx::query_scalar::<_, i64>("SELECT last_seq FROM sync_state WHERE id = 1")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+        let seq_before = last_seq().await;
+        let data_version_before = get_dashboard_data_version(&pool).await.unwrap();
+
+        let reviewed = review_anomaly(&pool, &outlier.id, AnomalyStatus::Dismissed)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reviewed.anomaly_status.as_deref(), Some("dismissed"));
+        assert_eq!(reviewed.version, outlier.version);
+        assert_eq!(last_seq().await, seq_before);
+        assert_ne!(
+            get_dashboard_data_version(&pool).await.unwrap(),
+            data_version_before
+        );
+    }
+
+    #[tokio::test]
+    async fn flagged_entries_count_towards_budgets_once_dismissed() {
+        let pool = test_pool().await;
+        let user = create_user(&pool, "u1@example.com", "x").await.unwrap();
+        let actor = Actor::user(&user.id);
+        let budget: CreateBudgetRequest =
+            serde_json::from_value(json!({ "period": "monthly", "amount": 500.0 })).unwrap();
+        create_budget(&pool, &user.id, &budget).await.unwrap();
+        let webhook: CreateWebhookRequest = serde_json::from_value(
+            json!({ "url": "https://example.com/hook", "events": ["budget.alert"] }),
+        )
+        .unwrap();
+        create_webhook(&pool, Some(&user.id), &webhook)
+            .await
+            .unwrap();
+
+        // 300 spent normally, then a price typed with the decimal point off
+        let now = Utc::now();
+        let mut outlier = None;
+        for (seconds, price_per_liter) in [1.5, 1.5, 1.5, 1.5, 1.5, 15.0].into_iter().enumerate() {
+            let request: CreateFuelEntryRequest = serde_json::from_value(json!({
+                "user_id": user.id,
+                "liters": 40.0,
+                "price_per_liter": price_per_liter,
+                "date_time": now - Duration::seconds(10 - seconds as i64),
+            }))
+            .unwrap();
+            outlier = Some(
+                create_fuel_entry(&pool, &user.id, &request, &actor)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let outlier = outlier.unwrap();
+        assert_eq!(outlier.anomaly_status.as_deref(), Some("pending"));
+
+        let budgets = get_budgets(&pool, &user.id).await.unwrap();
+        assert_eq!(budgets[0].spent, 300.0);
+        assert!(get_notifications(&pool, &user.id, false)
+            .await
+            .unwrap()
+            .is_empty());
+
+        review_anomaly(&pool, &outlier.id, AnomalyStatus::Dismissed)
+            .await
+            .unwrap();
+        let budgets = get_budgets(&pool, &user.id).await.unwrap();
+        assert_eq!(budgets[0].spent, 900.0);
+        assert_eq!(
+            get_notifications(&pool, &user.id, false)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        let queued: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM webhook_deliveries WHERE event = 'budget.alert'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(queued, 2);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::models::{CostRange, DistributionSummary};
+
+// Quantiles used as histogram edges when `COST_HISTOGRAM_EDGES` is not set
+const DEFAULT_HISTOGRAM_QUANTILES: [f64; 4] = [0.2, 0.4, 0.6, 0.8];
+
+// Fixed cost edges from `COST_HISTOGRAM_EDGES`, e.g. "25,50,100,150". None
+// when unset or when no edge parses, in which case quantiles are used.
+pub fn cost_histogram_edges() -> Option<Vec<f64>> {
+    let edges = std::env::var("COST_HISTOGRAM_EDGES").ok()?;
+    let mut parsed = Vec::new();
+    for edge in edges.split(',') {
+        match edge.trim().parse::<f64>() {
+            Ok(edge) if edge.is_finite() => parsed.push(edge),
+            _ => eprintln!("Ignoring malformed COST_HISTOGRAM_EDGES entry '{}'", edge),
+        }
+    }
+    if parsed.is_empty() {
+        None
+    } else {
+        Some(parsed)
+    }
+}
+
+// Quantile `q` (0..=1) of ascending `sorted` values, interpolating linearly
+// between the two nearest ranks
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+pub fn sorted(values: impl IntoIterator<Item = f64>) -> Vec<f64> {
+    let mut values: Vec<f64> = values
+        .into_iter()
+        .filter(|value| value.is_finite())
+        .collect();
+    values.sort_by(|a, b| a.total_cmp(b));
+    values
+}
+
+// Mean and population standard deviation; zeros when empty
+pub fn mean_and_std_dev(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64;
+    (mean, variance.sqrt())
+}
+
+// Count, mean and percentiles of ascending `sorted` values; all zero when empty
+pub fn summarize(sorted: &[f64]) -> DistributionSummary {
+    DistributionSummary {
+        count: sorted.len() as i32,
+        mean: if sorted.is_empty() {
+            0.0
+        } else {
+            sorted.iter().sum::<f64>() / sorted.len() as f64
+        },
+        min: sorted.first().copied().unwrap_or(0.0),
+        p10: quantile(sorted, 0.10),
+        p25: quantile(sorted, 0.25),
+        median: quantile(sorted, 0.50),
+        p75: quantile(sorted, 0.75),
+        p90: quantile(sorted, 0.90),
+        max: sorted.last().copied().unwrap_or(0.0),
+    }
+}
+
+// Buckets of ascending `sorted` costs split at `edges`, or at the default
+// quantiles of the data when no edges are given. Bucket k holds values from
+// edge k-1 (inclusive) to edge k (exclusive); the outer buckets are bounded by
+// the smallest and largest value.
+pub fn cost_histogram(sorted: &[f64], edges: Option<&[f64]>) -> Vec<CostRange> {
+    if sorted.is_empty() {
+        return Vec::new();
+    }
+
+    let mut inner: Vec<f64> = match edges {
+        Some(edges) => edges.to_vec(),
+        None => DEFAULT_HISTOGRAM_QUANTILES
+            .iter()
+            .map(|q| quantile(sorted, *q))
+            .collect(),
+    };
+    inner.sort_by(|a, b| a.total_cmp(b));
+    // Quantiles of repeated values can coincide
+    inner.dedup();
+
+    let mut counts = vec![0; inner.len() + 1];
+    for value in sorted {
+        counts[inner.partition_point(|edge| edge <= value)] += 1;
+    }
+
+    let min = sorted[0];
+    let max = sorted[sorted.len() - 1];
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(bucket, count)| {
+            let upper_bound = inner.get(bucket).copied().unwrap_or(max);
+            let lower_bound = if bucket == 0 {
+                min.min(upper_bound)
+            } else {
+                inner[bucket - 1]
+            };
+            let upper_bound = upper_bound.max(lower_bound);
+            let range = if bucket == 0 {
+                format!("< ${:.2}", upper_bound)
+            } else if bucket == inner.len() {
+                format!(">= ${:.2}", lower_bound)
+            } else {
+                format!("${:.2}-${:.2}", lower_bound, upper_bound)
+            };
+            CostRange {
+                range,
+                lower_bound,
+                upper_bound,
+                count,
+                percentage: count as f64 / sorted.len() as f64 * 100.0,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles_interpolate_between_ranks() {
+        let values = [10.0, 20.0, 30.0, 40.0];
+        assert_eq!(quantile(&values, 0.0), 10.0);
+        assert_eq!(quantile(&values, 1.0), 40.0);
+        assert_eq!(quantile(&values, 0.5), 25.0);
+        assert!((quantile(&values, 0.25) - 17.5).abs() < 1e-9);
+
+        assert_eq!(quantile(&[7.0], 0.9), 7.0);
+        assert_eq!(quantile(&[], 0.5), 0.0);
+        assert_eq!(quantile(&values, 1.5), 40.0);
+    }
+
+    #[test]
+    fn sorted_drops_non_finite_values() {
+        assert_eq!(
+            sorted([3.0, f64::NAN, 1.0, f64::INFINITY, 2.0]),
+            [1.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn summarizes_percentiles() {
+        let values = sorted((1..=11).map(f64::from));
+        let summary = summarize(&values);
+
+        assert_eq!(summary.count, 11);
+        assert_eq!(summary.mean, 6.0);
+        assert_eq!((summary.min, summary.max), (1.0, 11.0));
+        assert_eq!((summary.p10, summary.median, summary.p90), (2.0, 6.0, 10.0));
+        assert_eq!(summarize(&[]).count, 0);
+    }
+
+    #[test]
+    fn histogram_with_fixed_edges() {
+        let values = sorted([5.0, 20.0, 25.0, 30.0, 60.0, 120.0]);
+        let buckets = cost_histogram(&values, Some(&[50.0, 25.0, 100.0]));
+
+        let counts: Vec<i32> = buckets.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts, [2, 2, 1, 1]);
+        assert_eq!(buckets[0].range, "< $25.00");
+        assert_eq!(buckets[1].range, "$25.00-$50.00");
+        assert_eq!(buckets[3].range, ">= $100.00");
+        assert_eq!(
+            (buckets[0].lower_bound, buckets[3].upper_bound),
+            (5.0, 120.0)
+        );
+        let total: f64 = buckets.iter().map(|bucket| bucket.percentage).sum();
+        assert!((total - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn histogram_defaults_to_quintiles_of_the_data() {
+        let values = sorted((1..=100).map(f64::from));
+        let buckets = cost_histogram(&values, None);
+
+        assert_eq!(buckets.len(), 5);
+        assert!(buckets.iter().all(|bucket| bucket.count == 20));
+    }
+
+    #[test]
+    fn histogram_merges_repeated_quantiles() {
+        let values = sorted([10.0, 10.0, 10.0, 10.0, 10.0, 50.0]);
+        let buckets = cost_histogram(&values, None);
+
+        assert_eq!(buckets.iter().map(|bucket| bucket.count).sum::<i32>(), 6);
+        assert!(buckets.len() < 5);
+        assert!(cost_histogram(&[], None).is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+use chrono_tz::Tz;
+
+use crate::models::{DuplicateCheck, DuplicateMatch, FuelEntry};
+
+// Fuzzy matching tolerances used when a bulk request does not set its own.
+// Small enough to only absorb pump/export rounding, not a second fill-up.
+const DEFAULT_LITERS_TOLERANCE: f64 = 0.05;
+const DEFAULT_COST_TOLERANCE: f64 = 0.05;
+const DEFAULT_DAY_TOLERANCE: i64 = 0;
+
+// Same data fields, ignoring id and bookkeeping
+pub fn same_fuel_data(a: &FuelEntry, b: &FuelEntry) -> bool {
+    a.liters == b.liters
+        && a.price_per_liter == b.price_per_liter
+        && a.total_cost == b.total_cost
+        && a.date_time == b.date_time
+        && a.odometer_reading == b.odometer_reading
+        && a.vehicle == b.vehicle
+        && a.fuel_type == b.fuel_type
+        && a.tags == b.tags
+}
+
+pub struct DuplicatePolicy {
+    mode: DuplicateMatch,
+    liters_tolerance: f64,
+    cost_tolerance: f64,
+    day_tolerance: i64,
+    // Calendar days are the user's local days
+    time_zone: Tz,
+}
+
+impl DuplicatePolicy {
+    pub fn from_request(check: &DuplicateCheck, time_zone: Tz) -> Self {
+        DuplicatePolicy {
+            mode: check.mode,
+            liters_tolerance: check
+                .liters_tolerance
+                .unwrap_or(DEFAULT_LITERS_TOLERANCE)
+                .max(0.0),
+            cost_tolerance: check
+                .cost_tolerance
+                .unwrap_or(DEFAULT_COST_TOLERANCE)
+                .max(0.0),
+            day_tolerance: check.day_tolerance.unwrap_or(DEFAULT_DAY_TOLERANCE).max(0),
+            time_zone,
+        }
+    }
+
+    // Fuzzy matching treats two entries as the same fill-up when they fall on
+    // the same local calendar day (± day_tolerance), liters and total cost differ by
+    // at most the tolerances and, if both carry one, the odometer readings and
+    // vehicles agree.
+    pub fn is_duplicate(&self, candidate: &FuelEntry, existing: &FuelEntry) -> bool {
+        match self.mode {
+            DuplicateMatch::None => false,
+            DuplicateMatch::Exact => same_fuel_data(candidate, existing),
+            DuplicateMatch::Fuzzy => {
+                let local_day =
+                    |entry: &FuelEntry| entry.date_time.with_timezone(&self.time_zone).date_naive();
+                let days_apart = (local_day(candidate) - local_day(existing))
+                    .num_days()
+                    .abs();
+                let same_odometer = match (candidate.odometer_reading, existing.odometer_reading) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => true,
+                };
+                let same_vehicle = match (&candidate.vehicle, &existing.vehicle) {
+                    (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                    _ => true,
+                };
+
+                days_apart <= self.day_tolerance
+                    && (candidate.liters - existing.liters).abs() <= self.liters_tolerance
+                    && (candidate.total_cost - existing.total_cost).abs() <= self.cost_tolerance
+                    && same_odometer
+                    && same_vehicle
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(date_time: &str, liters: f64, total_cost: f64) -> FuelEntry {
+        serde_json::from_value(json!({
+            "id": "e1",
+            "user_id": "u1",
+            "liters": liters,
+            "price_per_liter": total_cost / liters,
+            "total_cost": total_cost,
+            "date_time": date_time,
+            "deleted_at": null,
+        }))
+        .unwrap()
+    }
+
+    fn policy(check: serde_json::Value, time_zone: Tz) -> DuplicatePolicy {
+        DuplicatePolicy::from_request(&serde_json::from_value(check).unwrap(), time_zone)
+    }
+
+    #[test]
+    fn defaults_to_exact_matching() {
+        let policy = policy(json!({}), Tz::UTC);
+        let stored = entry("2026-03-01T08:00:00Z", 40.0, 60.0);
+
+        assert!(policy.is_duplicate(&stored.clone(), &stored));
+        assert!(!policy.is_duplicate(&entry("2026-03-01T08:00:00Z", 40.01, 60.0), &stored));
+    }
+
+    #[test]
+    fn fuzzy_matching_allows_rounding_differences() {
+        let policy = policy(json!({ "mode": "fuzzy" }), Tz::UTC);
+        let stored = entry("2026-03-01T08:00:00Z", 40.0, 60.0);
+
+        assert!(policy.is_duplicate(&entry("2026-03-01T19:00:00Z", 40.04, 60.03), &stored));
+        assert!(!policy.is_duplicate(&entry("2026-03-01T19:00:00Z", 40.2, 60.0), &stored));
+        assert!(!policy.is_duplicate(&entry("2026-03-02T08:00:00Z", 40.0, 60.0), &stored));
+    }
+
+    #[test]
+    fn fuzzy_days_are_local_days() {
+        // 23:30 and 00:30 UTC are both the evening of March 1st in New York
+        let stored = entry("2026-03-01T23:30:00Z", 40.0, 60.0);
+        let candidate = entry("2026-03-02T00:30:00Z", 40.0, 60.0);
+
+        let utc = policy(json!({ "mode": "fuzzy" }), Tz::UTC);
+        assert!(!utc.is_duplicate(&candidate, &stored));
+        let new_york = policy(json!({ "mode": "fuzzy" }), chrono_tz::America::New_York);
+        assert!(new_york.is_duplicate(&candidate, &stored));
+    }
+
+    #[test]
+    fn fuzzy_matching_respects_odometer_and_vehicle() {
+        let policy = policy(json!({ "mode": "fuzzy", "day_tolerance": 1 }), Tz::UTC);
+        let mut stored = entry("2026-03-01T08:00:00Z", 40.0, 60.0);
+        stored.odometer_reading = Some(1000.0);
+        stored.vehicle = Some("Golf".to_string());
+
+        let mut candidate = entry("2026-03-02T08:00:00Z", 40.0, 60.0);
+        candidate.vehicle = Some("golf".to_string());
+        assert!(policy.is_duplicate(&candidate, &stored));
+
+        candidate.odometer_reading = Some(1200.0);
+        assert!(!policy.is_duplicate(&candidate, &stored));
+        candidate.odometer_reading = None;
+        candidate.vehicle = Some("Polo".to_string());
+        assert!(!policy.is_duplicate(&candidate, &stored));
+    }
+
+    #[test]
+    fn none_imports_everything() {
+        let policy = policy(json!({ "mode": "none" }), Tz::UTC);
+        let stored = entry("2026-03-01T08:00:00Z", 40.0, 60.0);
+        assert!(!policy.is_duplicate(&stored.clone(), &stored));
+    }
+}
@@ -0,0 +1,331 @@
+use crate::models::ForecastBacktest;
+
+// Level of the prediction intervals returned with every forecast
+pub const PREDICTION_INTERVAL_LEVEL: f64 = 95.0;
+
+// Fewer points leave no degrees of freedom to estimate the error from
+const MIN_FIT_POINTS: usize = 3;
+
+// Seasonal offsets are only estimated once every calendar month can have been
+// seen twice
+const MIN_SEASONAL_POINTS: usize = 24;
+
+// At most this many trailing months are held out when backtesting
+const MAX_BACKTEST_MONTHS: usize = 6;
+
+// Two-sided 95% critical values of Student's t for 1 to 30 degrees of freedom
+const T_CRITICAL_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+fn t_critical(degrees_of_freedom: usize) -> f64 {
+    match degrees_of_freedom {
+        0 => f64::INFINITY,
+        1..=30 => T_CRITICAL_95[degrees_of_freedom - 1],
+        31..=60 => 2.000,
+        61..=120 => 1.980,
+        _ => 1.960,
+    }
+}
+
+// Consecutive number of a calendar month, so months can be used as x values
+pub fn month_index(year: i32, month: u32) -> i32 {
+    year * 12 + month as i32 - 1
+}
+
+// Inverse of `month_index`
+pub fn month_from_index(index: i32) -> (i32, u32) {
+    (index.div_euclid(12), index.rem_euclid(12) as u32 + 1)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Forecast {
+    pub value: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+// Least-squares linear trend over month indexes, with an additive offset per
+// calendar month when there is enough history for seasonality
+#[derive(Debug)]
+pub struct MonthlyModel {
+    intercept: f64,
+    slope: f64,
+    seasonal: Option<[f64; 12]>,
+    points: usize,
+    mean_x: f64,
+    sum_squares_x: f64,
+    residual_std_error: f64,
+    degrees_of_freedom: usize,
+}
+
+impl MonthlyModel {
+    // `points` are (month index, value) pairs; months without an observation
+    // are simply missing. Returns None below `MIN_FIT_POINTS` or when every
+    // point falls into the same month.
+    pub fn fit(points: &[(i32, f64)]) -> Option<Self> {
+        if points.len() < MIN_FIT_POINTS {
+            return None;
+        }
+
+        let (intercept, slope, mean_x, sum_squares_x) = linear_fit(points)?;
+        let mut model = MonthlyModel {
+            intercept,
+            slope,
+            seasonal: None,
+            points: points.len(),
+            mean_x,
+            sum_squares_x,
+            residual_std_error: 0.0,
+            degrees_of_freedom: points.len() - 2,
+        };
+
+        if points.len() >= MIN_SEASONAL_POINTS {
+            // Offsets are the mean residual of each calendar month, centred so
+            // they do not shift the trend; the trend is then refitted on the
+            // deseasonalized values
+            let mut sums = [0.0; 12];
+            let mut counts = [0usize; 12];
+            for &(x, y) in points {
+                let slot = x.rem_euclid(12) as usize;
+                sums[slot] += y - model.trend(x);
+                counts[slot] += 1;
+            }
+            let seen = counts.iter().filter(|count| **count > 0).count();
+            let mut seasonal = [0.0; 12];
+            for slot in 0..12 {
+                if counts[slot] > 0 {
+                    seasonal[slot] = sums[slot] / counts[slot] as f64;
+                }
+            }
+            let centre = seasonal.iter().sum::<f64>() / seen as f64;
+            for slot in 0..12 {
+                if counts[slot] > 0 {
+                    seasonal[slot] -= centre;
+                }
+            }
+
+            let adjusted: Vec<(i32, f64)> = points
+                .iter()
+                .map(|&(x, y)| (x, y - seasonal[x.rem_euclid(12) as usize]))
+                .collect();
+            let (intercept, slope, _, _) = linear_fit(&adjusted)?;
+            model.intercept = intercept;
+            model.slope = slope;
+            model.seasonal = Some(seasonal);
+            // Two trend parameters plus one offset per calendar month seen,
+            // less one for the centring
+            model.degrees_of_freedom = points.len().saturating_sub(seen + 1);
+        }
+
+        let sum_squared_residuals: f64 = points
+            .iter()
+            .map(|&(x, y)| (y - model.fitted(x)).powi(2))
+            .sum();
+        if model.degrees_of_freedom > 0 {
+            model.residual_std_error =
+                (sum_squared_residuals / model.degrees_of_freedom as f64).sqrt();
+        }
+
+        Some(model)
+    }
+
+    // Change per month of the trend line
+    pub fn slope(&self) -> f64 {
+        self.slope
+    }
+
+    pub fn is_seasonal(&self) -> bool {
+        self.seasonal.is_some()
+    }
+
+    fn trend(&self, x: i32) -> f64 {
+        self.intercept + self.slope * x as f64
+    }
+
+    fn fitted(&self, x: i32) -> f64 {
+        let offset = self
+            .seasonal
+            .map(|seasonal| seasonal[x.rem_euclid(12) as usize])
+            .unwrap_or(0.0);
+        self.trend(x) + offset
+    }
+
+    // Point forecast with a `PREDICTION_INTERVAL_LEVEL` prediction interval.
+    // Values are clamped at zero since prices and volumes cannot be negative.
+    pub fn forecast(&self, x: i32) -> Forecast {
+        let value = self.fitted(x);
+        let leverage =
+            1.0 + 1.0 / self.points as f64 + (x as f64 - self.mean_x).powi(2) / self.sum_squares_x;
+        let margin =
+            t_critical(self.degrees_of_freedom) * self.residual_std_error * leverage.sqrt();
+        Forecast {
+            value: value.max(0.0),
+            lower: (value - margin).max(0.0),
+            upper: (value + margin).max(0.0),
+        }
+    }
+}
+
+// Ordinary least squares; returns (intercept, slope, mean of x, sum of squared
+// deviations of x)
+fn linear_fit(points: &[(i32, f64)]) -> Option<(f64, f64, f64, f64)> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x as f64).sum::<f64>() / n;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+    let sum_squares_x: f64 = points
+        .iter()
+        .map(|&(x, _)| (x as f64 - mean_x).powi(2))
+        .sum();
+    if sum_squares_x == 0.0 {
+        return None;
+    }
+    let sum_products: f64 = points
+        .iter()
+        .map(|&(x, y)| (x as f64 - mean_x) * (y - mean_y))
+        .sum();
+    let slope = sum_products / sum_squares_x;
+    Some((mean_y - slope * mean_x, slope, mean_x, sum_squares_x))
+}
+
+// Fits the model without the trailing months of `points` (sorted by month) and
+// compares its forecasts for those months with what was actually recorded.
+// None when the remaining history is too short to fit.
+pub fn backtest(points: &[(i32, f64)]) -> Option<ForecastBacktest> {
+    let holdout = (points.len() / 4).clamp(1, MAX_BACKTEST_MONTHS);
+    if points.len() < MIN_FIT_POINTS + holdout {
+        return None;
+    }
+    let (training, held_out) = points.split_at(points.len() - holdout);
+    let model = MonthlyModel::fit(training)?;
+
+    let errors: Vec<(f64, f64)> = held_out
+        .iter()
+        .map(|&(x, actual)| (actual, (model.forecast(x).value - actual).abs()))
+        .collect();
+    let mean_absolute_error =
+        errors.iter().map(|(_, error)| error).sum::<f64>() / errors.len() as f64;
+    // Months with nothing recorded have no meaningful percentage error
+    let percentage_errors: Vec<f64> = errors
+        .iter()
+        .filter(|(actual, _)| *actual != 0.0)
+        .map(|(actual, error)| error / actual.abs() * 100.0)
+        .collect();
+    let mean_absolute_percentage_error = if percentage_errors.is_empty() {
+        None
+    } else {
+        Some(percentage_errors.iter().sum::<f64>() / percentage_errors.len() as f64)
+    };
+
+    Some(ForecastBacktest {
+        holdout_months: holdout as i32,
+        mean_absolute_error,
+        mean_absolute_percentage_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn month_indexes_round_trip() {
+        assert_eq!(month_index(2026, 1) + 11, month_index(2026, 12));
+        assert_eq!(month_index(2026, 12) + 1, month_index(2027, 1));
+        assert_eq!(month_from_index(month_index(2026, 7)), (2026, 7));
+        assert_eq!(month_from_index(month_index(2027, 1)), (2027, 1));
+    }
+
+    #[test]
+    fn needs_enough_distinct_months() {
+        assert!(MonthlyModel::fit(&[(1, 1.0), (2, 2.0)]).is_none());
+        assert!(MonthlyModel::fit(&[(5, 1.0), (5, 2.0), (5, 3.0)]).is_none());
+    }
+
+    #[test]
+    fn exact_linear_trend_has_a_tight_interval() {
+        let points: Vec<(i32, f64)> = (0..6).map(|x| (x, 10.0 + 2.0 * x as f64)).collect();
+        let model = MonthlyModel::fit(&points).unwrap();
+
+        assert!(close(model.slope(), 2.0));
+        assert!(!model.is_seasonal());
+        let forecast = model.forecast(8);
+        assert!(close(forecast.value, 26.0));
+        assert!(close(forecast.lower, 26.0) && close(forecast.upper, 26.0));
+    }
+
+    #[test]
+    fn intervals_widen_away_from_the_data() {
+        let points = [
+            (0, 10.0),
+            (1, 12.5),
+            (2, 13.5),
+            (3, 16.2),
+            (4, 17.9),
+            (5, 20.4),
+        ];
+        let model = MonthlyModel::fit(&points).unwrap();
+
+        let near = model.forecast(6);
+        let far = model.forecast(12);
+        assert!(near.lower < near.value && near.value < near.upper);
+        assert!(far.upper - far.lower > near.upper - near.lower);
+    }
+
+    #[test]
+    fn forecasts_are_clamped_at_zero() {
+        let points: Vec<(i32, f64)> = (0..5).map(|x| (x, 10.0 - 3.0 * x as f64)).collect();
+        let forecast = MonthlyModel::fit(&points).unwrap().forecast(10);
+        assert_eq!(forecast.value, 0.0);
+        assert_eq!(forecast.lower, 0.0);
+    }
+
+    #[test]
+    fn learns_seasonal_offsets_from_two_years() {
+        // Flat level with every July 6 above it
+        let points: Vec<(i32, f64)> = (0..24)
+            .map(|x| (x, if x % 12 == 6 { 16.0 } else { 10.0 }))
+            .collect();
+        let model = MonthlyModel::fit(&points).unwrap();
+
+        assert!(model.is_seasonal());
+        assert!(model.slope().abs() < 0.1);
+        let july = model.forecast(30).value;
+        let august = model.forecast(31).value;
+        assert!((july - 16.0).abs() < 0.5, "july {}", july);
+        assert!((august - 10.0).abs() < 0.5, "august {}", august);
+    }
+
+    #[test]
+    fn backtest_holds_out_the_last_quarter() {
+        let points: Vec<(i32, f64)> = (0..12).map(|x| (x, 5.0 + x as f64)).collect();
+        let result = backtest(&points).unwrap();
+
+        assert_eq!(result.holdout_months, 3);
+        assert!(close(result.mean_absolute_error, 0.0));
+        assert!(close(result.mean_absolute_percentage_error.unwrap(), 0.0));
+    }
+
+    #[test]
+    fn backtest_measures_the_miss() {
+        // Trend of +1 per month, then the held-out month comes in 4 higher
+        let mut points: Vec<(i32, f64)> = (0..4).map(|x| (x, 10.0 + x as f64)).collect();
+        points.push((4, 18.0));
+        let result = backtest(&points).unwrap();
+
+        assert_eq!(result.holdout_months, 1);
+        assert!(close(result.mean_absolute_error, 4.0));
+        assert!(close(
+            result.mean_absolute_percentage_error.unwrap(),
+            4.0 / 18.0 * 100.0
+        ));
+
+        assert!(backtest(&points[..3]).is_none());
+    }
+}
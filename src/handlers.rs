@@ -1,24 +1,45 @@
 use axum::{
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
-    response::Json,
+    extract::{Extension, Path, Query, State},
+    http::{
+        header::{ETAG, IF_MATCH, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Json, Response},
 };
 use serde_json::{json, Value};
 use sqlx::SqlitePool;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
 use crate::{
-    auth::{hash_password, verify_password},
+    auth::{self, hash_password, issue_admin_token, verify_password},
+    dashboard_cache::DashboardCache,
     database::{
-        create_fuel_entries, create_fuel_entry, create_user, delete_fuel_entries,
-        delete_fuel_entry, delete_user_by_id, get_all_users, get_dashboard_stats,
-        get_fuel_entries_by_user, get_fuel_entry_by_id, get_service_status, get_user_by_email,
-        is_service_enabled, update_fuel_entry, update_service_status,
+        apply_sync_operations, create_budget, create_fuel_entries, create_fuel_entry,
+        create_notification_channel, create_user, create_webhook, delete_budget,
+        delete_fuel_entries, delete_fuel_entry, delete_notification_channel, delete_user_by_id,
+        delete_vehicle, delete_webhook, get_all_users, get_anomaly_queue, get_budgets,
+        get_dashboard_data_version, get_dashboard_stats, get_deleted_fuel_entries,
+        get_fuel_entry_by_id, get_fuel_entry_history, get_notification_channels, get_notifications,
+        get_service_status, get_sync_changes, get_user_by_email, get_user_settings, get_user_stats,
+        get_vehicles, get_webhook_deliveries, get_webhooks, is_service_enabled, list_fuel_entries,
+        mark_notifications_read, redeliver_webhook, rescan_anomalies, restore_fuel_entries,
+        restore_fuel_entry, restore_user_by_id, revert_fuel_entry, review_anomaly, set_vehicle,
+        update_budget, update_fuel_entries, update_fuel_entry, update_service_status,
+        update_user_settings, VersionConflict,
     },
     models::{
-        AdminActionRequest, AdminLoginRequest, AdminLoginResponse, AuthResponse,
-        CreateFuelEntriesRequest, CreateFuelEntryRequest, DeleteFuelEntriesRequest,
-        ServiceToggleRequest, SigninRequest, SignupRequest, UpdateFuelEntryRequest,
+        Actor, AdminActionRequest, AdminLoginRequest, AdminLoginResponse, AnomalyQueueQuery,
+        AnomalyStatus, AuthResponse, BulkEntryStatus, BulkMode, BulkUpdateFuelEntriesRequest,
+        CreateBudgetRequest, CreateFuelEntriesRequest, CreateFuelEntryRequest,
+        CreateNotificationChannelRequest, CreateWebhookRequest, DashboardFilter, DashboardQuery,
+        DashboardSection, DeleteFuelEntriesRequest, FuelEntry, FuelEntryFilter, FuelEntryListQuery,
+        MarkNotificationsReadRequest, NotificationQuery, RestoreFuelEntriesRequest,
+        ReviewAnomalyRequest, ServiceToggleRequest, SetVehicleRequest, SigninRequest,
+        SignupRequest, SyncPushRequest, SyncQuery, SyncStatus, UpdateBudgetRequest,
+        UpdateFuelEntryRequest, UpdateUserSettingsRequest, WebhookDeliveryQuery,
     },
+    validation::ValidationError,
 };
 
 pub async fn signup(
@@ -44,6 +65,15 @@ pub async fn signup(
 
     // Check if user already exists
     match get_user_by_email(&pool, &request.email).await {
+        Ok(Some(user)) if user.deleted_at.is_some() => {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(json!({
+                    "error": "User already exists",
+                    "details": format!("The account for '{}' has been deleted and can only be restored by an administrator", request.email)
+                })),
+            ));
+        }
         Ok(Some(_)) => {
             return Err((
                 StatusCode::CONFLICT,
@@ -127,6 +157,13 @@ pub async fn signin(
 
     // Find user by email
     match get_user_by_email(&pool, &request.email).await {
+        Ok(Some(user)) if user.deleted_at.is_some() => Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "Account deleted",
+                "details": "This account has been deleted and can only be restored by an administrator"
+            })),
+        )),
         Ok(Some(user)) => {
             // User exists, verify password
             match verify_password(&request.password, &user.password_hash) {
@@ -229,7 +266,7 @@ pub async fn create_fuel_entry_handler(
     }
 
     // First validate that the user exists
-    match sqlx::query("SELECT id FROM users WHERE id = ?")
+    match sqlx::query("SELECT id FROM users WHERE id = ? AND deleted_at IS NULL")
         .bind(&request.user_id)
         .fetch_optional(&pool)
         .await
@@ -256,9 +293,19 @@ pub async fn create_fuel_entry_handler(
         }
     }
 
-    match create_fuel_entry(&pool, &request.user_id, &request).await {
+    match create_fuel_entry(
+        &pool,
+        &request.user_id,
+        &request,
+        &Actor::user(&request.user_id),
+    )
+    .await
+    {
         Ok(entry) => Ok(Json(json!(entry))),
         Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
             eprintln!("Error creating fuel entry: {}", e);
 
             // Check for specific error types
@@ -317,7 +364,7 @@ pub async fn create_fuel_entries_handler(
     }
 
     // Validate that the user exists
-    match sqlx::query("SELECT id FROM users WHERE id = ?")
+    match sqlx::query("SELECT id FROM users WHERE id = ? AND deleted_at IS NULL")
         .bind(&request.user_id)
         .fetch_optional(&pool)
         .await
@@ -345,21 +392,48 @@ pub async fn create_fuel_entries_handler(
     }
 
     let total_requested = request.entries.len();
-    match create_fuel_entries(&pool, &request.user_id, &request.entries).await {
-        Ok(entries) => {
+    match create_fuel_entries(
+        &pool,
+        &request.user_id,
+        &request.entries,
+        request.mode,
+        &request.duplicate_check,
+        &Actor::import(&request.user_id),
+    )
+    .await
+    {
+        Ok((_, results, errors)) if request.mode == BulkMode::Atomic && !errors.is_empty() => {
+            Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({
+                    "error": "Validation failed",
+                    "details": errors.to_string(),
+                    "field_errors": errors.errors,
+                    "results": results
+                })),
+            ))
+        }
+        Ok((entries, results, _)) => {
+            let count = |status| results.iter().filter(|r| r.status == status).count();
             let created_count = entries.len();
-            let duplicates_skipped = total_requested - created_count;
+            let duplicates_skipped = count(BulkEntryStatus::Duplicate);
+            let invalid_count = count(BulkEntryStatus::Invalid);
 
             Ok(Json(json!({
-                "message": format!("Successfully processed {} entries: {} created, {} duplicates skipped",
-                                  total_requested, created_count, duplicates_skipped),
+                "message": format!("Successfully processed {} entries: {} created, {} duplicates skipped, {} invalid",
+                                  total_requested, created_count, duplicates_skipped, invalid_count),
                 "total_requested": total_requested,
                 "created_count": created_count,
                 "duplicates_skipped": duplicates_skipped,
+                "invalid_count": invalid_count,
+                "results": results,
                 "entries": entries
             })))
         }
         Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
             eprintln!("Error creating fuel entries: {}", e);
 
             // Check for specific error types
@@ -385,13 +459,43 @@ pub async fn create_fuel_entries_handler(
     }
 }
 
+// Upper bound for the page size of the entry list
+const MAX_FUEL_ENTRY_PAGE_SIZE: i64 = 500;
+
+const TOTAL_COUNT_HEADER: &str = "x-total-count";
+const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+// Lists live entries, optionally filtered, sorted and paged. The body stays a
+// plain array; the total count and the cursor of the next page travel in the
+// X-Total-Count and X-Next-Cursor headers.
 pub async fn get_fuel_entries_handler(
+    headers: HeaderMap,
     State(pool): State<SqlitePool>,
     Path(user_id): Path<String>,
-) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    match get_fuel_entries_by_user(&pool, &user_id).await {
-        Ok(entries) => Ok(Json(json!(entries))),
+    Query(query): Query<FuelEntryListQuery>,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let limit = query
+        .limit
+        .map(|limit| limit.clamp(1, MAX_FUEL_ENTRY_PAGE_SIZE));
+
+    match list_fuel_entries(&pool, &user_id, &query, limit).await {
+        Ok(page) => {
+            let mut response =
+                conditional_json(&headers, entries_etag(&page.entries), json!(page.entries));
+            let response_headers = response.headers_mut();
+            response_headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(page.total_count));
+            if let Some(cursor) = page
+                .next_cursor
+                .and_then(|cursor| HeaderValue::from_str(&cursor).ok())
+            {
+                response_headers.insert(NEXT_CURSOR_HEADER, cursor);
+            }
+            Ok(response)
+        }
         Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
             eprintln!("Error getting fuel entries for user {}: {}", user_id, e);
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -405,11 +509,12 @@ pub async fn get_fuel_entries_handler(
 }
 
 pub async fn get_fuel_entry_handler(
+    headers: HeaderMap,
     State(pool): State<SqlitePool>,
     Path((user_id, id)): Path<(String, String)>,
-) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+) -> Result<Response, (StatusCode, Json<Value>)> {
     match get_fuel_entry_by_id(&pool, &id, &user_id).await {
-        Ok(Some(entry)) => Ok(Json(json!(entry))),
+        Ok(Some(entry)) => Ok(conditional_json(&headers, entry_etag(&entry), json!(entry))),
         Ok(None) => Err((
             StatusCode::NOT_FOUND,
             Json(json!({
@@ -434,12 +539,24 @@ pub async fn get_fuel_entry_handler(
 }
 
 pub async fn update_fuel_entry_handler(
+    headers: HeaderMap,
     State(pool): State<SqlitePool>,
     Path((user_id, id)): Path<(String, String)>,
     Json(request): Json<UpdateFuelEntryRequest>,
-) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    match update_fuel_entry(&pool, &id, &user_id, &request).await {
-        Ok(Some(entry)) => Ok(Json(json!(entry))),
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let expected_version = if_match_version(&headers)?;
+
+    match update_fuel_entry(
+        &pool,
+        &id,
+        &user_id,
+        &request,
+        expected_version,
+        &Actor::user(&user_id),
+    )
+    .await
+    {
+        Ok(Some(entry)) => Ok(([(ETAG, entry_etag(&entry))], Json(json!(entry))).into_response()),
         Ok(None) => Err((
             StatusCode::NOT_FOUND,
             Json(json!({
@@ -448,6 +565,12 @@ pub async fn update_fuel_entry_handler(
             })),
         )),
         Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
+            if let Some(response) = version_conflict_response(&e) {
+                return Err(response);
+            }
             eprintln!(
                 "Error updating fuel entry {} for user {}: {}",
                 id, user_id, e
@@ -464,11 +587,22 @@ pub async fn update_fuel_entry_handler(
 }
 
 pub async fn delete_fuel_entry_handler(
+    headers: HeaderMap,
     State(pool): State<SqlitePool>,
     Path((user_id, id)): Path<(String, String)>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    match delete_fuel_entry(&pool, &id, &user_id).await {
-        Ok(true) => Ok(Json(json!({"message": "Fuel entry deleted successfully"}))),
+    let expected_version = if_match_version(&headers)?;
+
+    match delete_fuel_entry(
+        &pool,
+        &id,
+        &user_id,
+        expected_version,
+        &Actor::user(&user_id),
+    )
+    .await
+    {
+        Ok(true) => Ok(Json(json!({"message": "Fuel entry moved to trash"}))),
         Ok(false) => Err((
             StatusCode::NOT_FOUND,
             Json(json!({
@@ -477,6 +611,9 @@ pub async fn delete_fuel_entry_handler(
             })),
         )),
         Err(e) => {
+            if let Some(response) = version_conflict_response(&e) {
+                return Err(response);
+            }
             eprintln!(
                 "Error deleting fuel entry {} for user {}: {}",
                 id, user_id, e
@@ -508,7 +645,7 @@ pub async fn delete_fuel_entries_handler(
     }
 
     // Validate that the user exists
-    match sqlx::query("SELECT id FROM users WHERE id = ?")
+    match sqlx::query("SELECT id FROM users WHERE id = ? AND deleted_at IS NULL")
         .bind(&request.user_id)
         .fetch_optional(&pool)
         .await
@@ -535,7 +672,14 @@ pub async fn delete_fuel_entries_handler(
         }
     }
 
-    match delete_fuel_entries(&pool, &request.user_id, &request.entry_ids).await {
+    match delete_fuel_entries(
+        &pool,
+        &request.user_id,
+        &request.entry_ids,
+        &Actor::user(&request.user_id),
+    )
+    .await
+    {
         Ok((deleted_count, deleted_ids)) => {
             let total_requested = request.entry_ids.len();
             let not_found = total_requested - deleted_count;
@@ -561,17 +705,76 @@ pub async fn delete_fuel_entries_handler(
     }
 }
 
-pub async fn get_dashboard_handler(
+pub async fn update_fuel_entries_handler(
     State(pool): State<SqlitePool>,
+    Json(request): Json<BulkUpdateFuelEntriesRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    match get_dashboard_stats(&pool).await {
-        Ok(stats) => Ok(Json(json!(stats))),
+    let bad_request = |error: &str, details: &str| {
+        Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": error,
+                "details": details
+            })),
+        ))
+    };
+
+    match (&request.updates, &request.filter, &request.patch) {
+        (Some(updates), None, None) if updates.is_empty() => {
+            return bad_request("Empty updates list", "At least one update must be provided");
+        }
+        (Some(_), None, None) => {}
+        (None, Some(filter), Some(_)) if filter.is_empty() => {
+            return bad_request(
+                "Empty filter",
+                "A filter needs at least one condition; list the entries in `updates` instead",
+            );
+        }
+        (None, Some(_), Some(patch)) if patch.is_empty() => {
+            return bad_request("Empty patch", "The patch must change at least one field");
+        }
+        (None, Some(_), Some(_)) => {}
+        _ => {
+            return bad_request(
+                "Invalid bulk update",
+                "Provide either `updates`, or `filter` together with `patch`",
+            );
+        }
+    }
+
+    ensure_user_exists(&pool, &request.user_id).await?;
+
+    match update_fuel_entries(&pool, &request, &Actor::user(&request.user_id)).await {
+        Ok((_, results, errors)) if request.mode == BulkMode::Atomic && !errors.is_empty() => {
+            Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({
+                    "error": "Bulk update failed",
+                    "details": errors.to_string(),
+                    "field_errors": errors.errors,
+                    "results": results
+                })),
+            ))
+        }
+        Ok((entries, results, _)) => {
+            let total_matched = results.len();
+            let updated_count = entries.len();
+
+            Ok(Json(json!({
+                "message": format!("Successfully updated {} of {} fuel entries", updated_count, total_matched),
+                "total_matched": total_matched,
+                "updated_count": updated_count,
+                "failed_count": total_matched - updated_count,
+                "results": results,
+                "entries": entries
+            })))
+        }
         Err(e) => {
-            eprintln!("Error getting dashboard stats: {}", e);
+            eprintln!("Error updating fuel entries: {}", e);
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": "Failed to get dashboard statistics",
+                    "error": "Failed to update fuel entries",
                     "details": e.to_string()
                 })),
             ))
@@ -579,40 +782,28 @@ pub async fn get_dashboard_handler(
     }
 }
 
-pub async fn get_all_users_handler(
-    headers: HeaderMap,
+pub async fn get_fuel_entry_history_handler(
     State(pool): State<SqlitePool>,
+    Path((user_id, id)): Path<(String, String)>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // Check admin authentication
-    if !verify_admin_token(&headers) {
-        return Err((
-            StatusCode::UNAUTHORIZED,
+    match get_fuel_entry_history(&pool, &id, &user_id).await {
+        Ok(revisions) if revisions.is_empty() => Err((
+            StatusCode::NOT_FOUND,
             Json(json!({
-                "error": "Unauthorized",
-                "details": "Admin authentication required"
+                "error": "Fuel entry history not found",
+                "details": format!("No history found for fuel entry '{}' of user '{}'", id, user_id)
             })),
-        ));
-    }
-    match get_all_users(&pool).await {
-        Ok(users) => {
-            let safe_users: Vec<_> = users
-                .into_iter()
-                .map(|user| {
-                    json!({
-                        "id": user.id,
-                        "email": user.email,
-                        "created_at": user.created_at
-                    })
-                })
-                .collect();
-            Ok(Json(json!(safe_users)))
-        }
+        )),
+        Ok(revisions) => Ok(Json(json!(revisions))),
         Err(e) => {
-            eprintln!("Error getting all users: {}", e);
+            eprintln!(
+                "Error getting history of fuel entry {} for user {}: {}",
+                id, user_id, e
+            );
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": "Failed to get users",
+                    "error": "Failed to get fuel entry history",
                     "details": e.to_string()
                 })),
             ))
@@ -620,153 +811,1263 @@ pub async fn get_all_users_handler(
     }
 }
 
-pub async fn admin_action_handler(
-    headers: HeaderMap,
+pub async fn revert_fuel_entry_handler(
     State(pool): State<SqlitePool>,
-    Json(request): Json<AdminActionRequest>,
+    Path((user_id, id, revision)): Path<(String, String, i64)>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // Check admin authentication
-    if !verify_admin_token(&headers) {
-        return Err((
-            StatusCode::UNAUTHORIZED,
+    match revert_fuel_entry(&pool, &id, &user_id, revision, &Actor::user(&user_id)).await {
+        Ok(Some(entry)) => Ok(Json(json!(entry))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
             Json(json!({
-                "error": "Unauthorized",
-                "details": "Admin authentication required"
+                "error": "Revision not found",
+                "details": format!("No revision {} found for live fuel entry '{}' of user '{}'", revision, id, user_id)
             })),
-        ));
-    }
-    match request.action.as_str() {
-        "delete_user" => {
-            if let Some(user_id) = request.user_id {
-                match delete_user_by_id(&pool, &user_id).await {
-                    Ok(true) => Ok(Json(json!({
-                        "message": "User deleted successfully",
-                        "user_id": user_id
-                    }))),
-                    Ok(false) => Err((
-                        StatusCode::NOT_FOUND,
-                        Json(json!({
-                            "error": "User not found",
-                            "details": format!("No user found with id '{}'", user_id)
-                        })),
-                    )),
-                    Err(e) => {
-                        eprintln!("Error deleting user {}: {}", user_id, e);
-                        Err((
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(json!({
-                                "error": "Failed to delete user",
-                                "details": e.to_string()
-                            })),
-                        ))
-                    }
-                }
-            } else {
-                Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({
-                        "error": "Missing user_id",
-                        "details": "user_id is required for delete_user action"
-                    })),
-                ))
-            }
-        }
-        "delete_entry" => {
-            if let (Some(user_id), Some(entry_id)) = (request.user_id, request.entry_id) {
-                match delete_fuel_entry(&pool, &entry_id, &user_id).await {
-                    Ok(true) => Ok(Json(json!({
-                        "message": "Fuel entry deleted successfully",
-                        "entry_id": entry_id,
-                        "user_id": user_id
-                    }))),
-                    Ok(false) => Err((
-                        StatusCode::NOT_FOUND,
-                        Json(json!({
-                            "error": "Fuel entry not found",
-                            "details": format!("No fuel entry found with id '{}' for user '{}'", entry_id, user_id)
-                        })),
-                    )),
-                    Err(e) => {
-                        eprintln!(
-                            "Error deleting fuel entry {} for user {}: {}",
-                            entry_id, user_id, e
-                        );
-                        Err((
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(json!({
-                                "error": "Failed to delete fuel entry",
-                                "details": e.to_string()
-                            })),
-                        ))
-                    }
-                }
-            } else {
-                Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({
-                        "error": "Missing parameters",
-                        "details": "Both user_id and entry_id are required for delete_entry action"
-                    })),
-                ))
+        )),
+        Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
             }
+            eprintln!(
+                "Error reverting fuel entry {} for user {} to revision {}: {}",
+                id, user_id, revision, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to revert fuel entry",
+                    "details": e.to_string()
+                })),
+            ))
         }
-        _ => Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Invalid action",
-                "details": format!("Unknown action '{}'", request.action)
-            })),
-        )),
     }
 }
 
-// pub async fn serve_dashboard() -> Html<&'static str> {
-//     Html(include_str!("../index.html"))
-// }
-
-pub async fn admin_login_handler(
-    Json(request): Json<AdminLoginRequest>,
+pub async fn get_trash_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // Hardcoded admin credentials
-    const ADMIN_EMAIL: &str = "me.remon.ahammad@bss.io";
-    const ADMIN_PASSWORD: &str = "rustybustyrestapideshboard";
-
-    if request.email == ADMIN_EMAIL && request.password == ADMIN_PASSWORD {
-        // Generate a simple token (in production, use proper JWT)
-        let token = format!("admin_token_{}", chrono::Utc::now().timestamp());
-
-        Ok(Json(json!(AdminLoginResponse {
-            success: true,
-            token: Some(token),
-            message: "Admin login successful".to_string(),
-        })))
-    } else {
-        Err((
-            StatusCode::UNAUTHORIZED,
-            Json(json!(AdminLoginResponse {
-                success: false,
-                token: None,
-                message: "Invalid admin credentials".to_string(),
-            })),
-        ))
+    match get_deleted_fuel_entries(&pool, &user_id).await {
+        Ok(entries) => Ok(Json(json!(entries))),
+        Err(e) => {
+            eprintln!("Error getting trash for user {}: {}", user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get deleted fuel entries",
+                    "details": e.to_string()
+                })),
+            ))
+        }
     }
 }
 
-pub async fn admin_verify_handler(
-    headers: axum::http::HeaderMap,
+pub async fn restore_fuel_entry_handler(
+    State(pool): State<SqlitePool>,
+    Path((user_id, id)): Path<(String, String)>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    if let Some(auth_header) = headers.get("authorization") {
-        if let Ok(auth_str) = auth_header.to_str() {
-            if auth_str.starts_with("Bearer admin_token_") {
-                return Ok(Json(json!({
-                    "valid": true,
-                    "message": "Token is valid"
-                })));
-            }
-        }
-    }
-
-    Err((
+    match restore_fuel_entry(&pool, &id, &user_id, &Actor::user(&user_id)).await {
+        Ok(true) => Ok(Json(json!({
+            "message": "Fuel entry restored successfully",
+            "entry_id": id
+        }))),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "Fuel entry not found in trash",
+                "details": format!("No deleted fuel entry found with id '{}' for user '{}'", id, user_id)
+            })),
+        )),
+        Err(e) => {
+            eprintln!(
+                "Error restoring fuel entry {} for user {}: {}",
+                id, user_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to restore fuel entry",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn restore_fuel_entries_handler(
+    State(pool): State<SqlitePool>,
+    Json(request): Json<RestoreFuelEntriesRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // Validate that entry_ids list is not empty
+    if request.entry_ids.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "Empty entry IDs list",
+                "details": "At least one entry ID must be provided"
+            })),
+        ));
+    }
+
+    match restore_fuel_entries(
+        &pool,
+        &request.user_id,
+        &request.entry_ids,
+        &Actor::user(&request.user_id),
+    )
+    .await
+    {
+        Ok((restored_count, restored_ids)) => {
+            let total_requested = request.entry_ids.len();
+
+            Ok(Json(json!({
+                "message": format!("Successfully restored {} fuel entries", restored_count),
+                "restored_count": restored_count,
+                "total_requested": total_requested,
+                "not_found_count": total_requested - restored_count,
+                "restored_ids": restored_ids
+            })))
+        }
+        Err(e) => {
+            eprintln!("Error restoring fuel entries: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to restore fuel entries",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+// Page size of the sync change feed when the client does not ask for one, and its upper bound
+const DEFAULT_SYNC_LIMIT: i64 = 500;
+const MAX_SYNC_LIMIT: i64 = 1000;
+
+pub async fn get_sync_changes_handler(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<SyncQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &query.user_id).await?;
+
+    let since = query.since.unwrap_or(0).max(0);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SYNC_LIMIT)
+        .clamp(1, MAX_SYNC_LIMIT);
+
+    match get_sync_changes(&pool, &query.user_id, since, limit).await {
+        Ok(changes) => Ok(Json(json!(changes))),
+        Err(e) => {
+            eprintln!(
+                "Error getting sync changes for user {}: {}",
+                query.user_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get sync changes",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn sync_push_handler(
+    State(pool): State<SqlitePool>,
+    Json(request): Json<SyncPushRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    if request.operations.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "Empty operations list",
+                "details": "At least one operation must be provided"
+            })),
+        ));
+    }
+
+    ensure_user_exists(&pool, &request.user_id).await?;
+
+    match apply_sync_operations(
+        &pool,
+        &request.user_id,
+        &request.operations,
+        &Actor::user(&request.user_id),
+    )
+    .await
+    {
+        Ok(results) => {
+            let count = |status| results.iter().filter(|r| r.status == status).count();
+
+            Ok(Json(json!({
+                "message": format!("Processed {} operations", results.len()),
+                "applied_count": count(SyncStatus::Applied),
+                "unchanged_count": count(SyncStatus::Unchanged),
+                "conflict_count": count(SyncStatus::Conflict),
+                "results": results
+            })))
+        }
+        Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
+            eprintln!("Error applying sync operations: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to apply sync operations",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_user_stats_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+
+    match get_user_stats(&pool, &user_id).await {
+        Ok(stats) => Ok(Json(json!(stats))),
+        Err(e) => {
+            eprintln!("Error getting stats for user {}: {}", user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get user statistics",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_user_settings_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match get_user_settings(&pool, &user_id).await {
+        Ok(Some(settings)) => Ok(Json(json!(settings))),
+        Ok(None) => Err(user_not_found(&user_id)),
+        Err(e) => {
+            eprintln!("Error getting settings for user {}: {}", user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get user settings",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn update_user_settings_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+    Json(request): Json<UpdateUserSettingsRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match update_user_settings(&pool, &user_id, &request).await {
+        Ok(Some(settings)) => Ok(Json(json!(settings))),
+        Ok(None) => Err(user_not_found(&user_id)),
+        Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
+            eprintln!("Error updating settings for user {}: {}", user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to update user settings",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_vehicles_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+
+    match get_vehicles(&pool, &user_id).await {
+        Ok(vehicles) => Ok(Json(json!(vehicles))),
+        Err(e) => {
+            eprintln!("Error getting vehicles for user {}: {}", user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get vehicles",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn set_vehicle_handler(
+    State(pool): State<SqlitePool>,
+    Path((user_id, name)): Path<(String, String)>,
+    Json(request): Json<SetVehicleRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+
+    match set_vehicle(&pool, &user_id, &name, &request).await {
+        Ok(vehicle) => Ok(Json(json!(vehicle))),
+        Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
+            eprintln!(
+                "Error setting vehicle '{}' for user {}: {}",
+                name, user_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to set vehicle",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn delete_vehicle_handler(
+    State(pool): State<SqlitePool>,
+    Path((user_id, name)): Path<(String, String)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match delete_vehicle(&pool, &user_id, &name).await {
+        Ok(true) => Ok(Json(json!({"message": "Vehicle deleted"}))),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "Vehicle not found",
+                "details": format!("No vehicle '{}' found for user '{}'", name, user_id)
+            })),
+        )),
+        Err(e) => {
+            eprintln!(
+                "Error deleting vehicle '{}' for user {}: {}",
+                name, user_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to delete vehicle",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_budgets_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+
+    match get_budgets(&pool, &user_id).await {
+        Ok(budgets) => Ok(Json(json!(budgets))),
+        Err(e) => {
+            eprintln!("Error getting budgets for user {}: {}", user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get budgets",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn create_budget_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+    Json(request): Json<CreateBudgetRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+
+    match create_budget(&pool, &user_id, &request).await {
+        Ok(budget) => Ok(Json(json!(budget))),
+        Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
+            eprintln!("Error creating budget for user {}: {}", user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to create budget",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+fn budget_not_found(user_id: &str, id: &str) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": "Budget not found",
+            "details": format!("No budget '{}' found for user '{}'", id, user_id)
+        })),
+    )
+}
+
+pub async fn update_budget_handler(
+    State(pool): State<SqlitePool>,
+    Path((user_id, id)): Path<(String, String)>,
+    Json(request): Json<UpdateBudgetRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match update_budget(&pool, &user_id, &id, &request).await {
+        Ok(Some(budget)) => Ok(Json(json!(budget))),
+        Ok(None) => Err(budget_not_found(&user_id, &id)),
+        Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
+            eprintln!("Error updating budget {} for user {}: {}", id, user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to update budget",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn delete_budget_handler(
+    State(pool): State<SqlitePool>,
+    Path((user_id, id)): Path<(String, String)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match delete_budget(&pool, &user_id, &id).await {
+        Ok(true) => Ok(Json(json!({"message": "Budget deleted"}))),
+        Ok(false) => Err(budget_not_found(&user_id, &id)),
+        Err(e) => {
+            eprintln!("Error deleting budget {} for user {}: {}", id, user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to delete budget",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_notifications_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+    Query(query): Query<NotificationQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+
+    match get_notifications(&pool, &user_id, query.unread).await {
+        Ok(notifications) => Ok(Json(json!(notifications))),
+        Err(e) => {
+            eprintln!("Error getting notifications for user {}: {}", user_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get notifications",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn mark_notifications_read_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+    Json(request): Json<MarkNotificationsReadRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+
+    match mark_notifications_read(&pool, &user_id, request.ids.as_deref()).await {
+        Ok(marked) => Ok(Json(json!({
+            "message": "Notifications marked as read",
+            "marked_count": marked
+        }))),
+        Err(e) => {
+            eprintln!(
+                "Error marking notifications read for user {}: {}",
+                user_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to mark notifications as read",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_notification_channels_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+
+    match get_notification_channels(&pool, &user_id).await {
+        Ok(channels) => Ok(Json(json!(channels))),
+        Err(e) => {
+            eprintln!(
+                "Error getting notification channels for user {}: {}",
+                user_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get notification channels",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn create_notification_channel_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+    Json(request): Json<CreateNotificationChannelRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+
+    match create_notification_channel(&pool, &user_id, &request).await {
+        Ok(channel) => Ok(Json(json!(channel))),
+        Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
+            eprintln!(
+                "Error creating notification channel for user {}: {}",
+                user_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to create notification channel",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn delete_notification_channel_handler(
+    State(pool): State<SqlitePool>,
+    Path((user_id, id)): Path<(String, String)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match delete_notification_channel(&pool, &user_id, &id).await {
+        Ok(true) => Ok(Json(json!({"message": "Notification channel deleted"}))),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "Notification channel not found",
+                "details": format!("No notification channel '{}' found for user '{}'", id, user_id)
+            })),
+        )),
+        Err(e) => {
+            eprintln!(
+                "Error deleting notification channel {} for user {}: {}",
+                id, user_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to delete notification channel",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+const DEFAULT_WEBHOOK_DELIVERY_LIMIT: i64 = 50;
+const MAX_WEBHOOK_DELIVERY_LIMIT: i64 = 500;
+
+fn webhook_not_found(id: &str) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": "Webhook not found",
+            "details": format!("No webhook found with id '{}'", id)
+        })),
+    )
+}
+
+// Webhook endpoints are shared by users and admins; `owner` is the user, or
+// None for admin-level webhooks
+async fn list_webhooks(
+    pool: &SqlitePool,
+    owner: Option<&str>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match get_webhooks(pool, owner).await {
+        Ok(webhooks) => Ok(Json(json!(webhooks))),
+        Err(e) => {
+            eprintln!("Error getting webhooks: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get webhooks",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+async fn add_webhook(
+    pool: &SqlitePool,
+    owner: Option<&str>,
+    request: &CreateWebhookRequest,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match create_webhook(pool, owner, request).await {
+        Ok(webhook) => Ok(Json(json!(webhook))),
+        Err(e) => {
+            if let Some(response) = validation_error_response(&e) {
+                return Err(response);
+            }
+            eprintln!("Error creating webhook: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to create webhook",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+async fn remove_webhook(
+    pool: &SqlitePool,
+    owner: Option<&str>,
+    id: &str,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match delete_webhook(pool, owner, id).await {
+        Ok(true) => Ok(Json(json!({"message": "Webhook deleted"}))),
+        Ok(false) => Err(webhook_not_found(id)),
+        Err(e) => {
+            eprintln!("Error deleting webhook {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to delete webhook",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+async fn list_webhook_deliveries(
+    pool: &SqlitePool,
+    owner: Option<&str>,
+    id: &str,
+    query: &WebhookDeliveryQuery,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_WEBHOOK_DELIVERY_LIMIT)
+        .clamp(1, MAX_WEBHOOK_DELIVERY_LIMIT);
+
+    match get_webhook_deliveries(pool, owner, id, query.status, limit).await {
+        Ok(Some(deliveries)) => Ok(Json(json!(deliveries))),
+        Ok(None) => Err(webhook_not_found(id)),
+        Err(e) => {
+            eprintln!("Error getting deliveries of webhook {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get webhook deliveries",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+async fn redeliver(
+    pool: &SqlitePool,
+    owner: Option<&str>,
+    id: &str,
+    delivery_id: &str,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match redeliver_webhook(pool, owner, id, delivery_id).await {
+        Ok(Some(delivery)) => Ok(Json(json!(delivery))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "Webhook delivery not found",
+                "details": format!("No delivery '{}' found for webhook '{}'", delivery_id, id)
+            })),
+        )),
+        Err(e) => {
+            eprintln!(
+                "Error redelivering delivery {} of webhook {}: {}",
+                delivery_id, id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to redeliver webhook",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_user_webhooks_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+    list_webhooks(&pool, Some(&user_id)).await
+}
+
+pub async fn create_user_webhook_handler(
+    State(pool): State<SqlitePool>,
+    Path(user_id): Path<String>,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_user_exists(&pool, &user_id).await?;
+    add_webhook(&pool, Some(&user_id), &request).await
+}
+
+pub async fn delete_user_webhook_handler(
+    State(pool): State<SqlitePool>,
+    Path((user_id, id)): Path<(String, String)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    remove_webhook(&pool, Some(&user_id), &id).await
+}
+
+pub async fn get_user_webhook_deliveries_handler(
+    State(pool): State<SqlitePool>,
+    Path((user_id, id)): Path<(String, String)>,
+    Query(query): Query<WebhookDeliveryQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    list_webhook_deliveries(&pool, Some(&user_id), &id, &query).await
+}
+
+pub async fn redeliver_user_webhook_handler(
+    State(pool): State<SqlitePool>,
+    Path((user_id, id, delivery_id)): Path<(String, String, String)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    redeliver(&pool, Some(&user_id), &id, &delivery_id).await
+}
+
+pub async fn get_admin_webhooks_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_admin(&headers)?;
+    list_webhooks(&pool, None).await
+}
+
+pub async fn create_admin_webhook_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_admin(&headers)?;
+    add_webhook(&pool, None, &request).await
+}
+
+pub async fn delete_admin_webhook_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_admin(&headers)?;
+    remove_webhook(&pool, None, &id).await
+}
+
+pub async fn get_admin_webhook_deliveries_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+    Path(id): Path<String>,
+    Query(query): Query<WebhookDeliveryQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_admin(&headers)?;
+    list_webhook_deliveries(&pool, None, &id, &query).await
+}
+
+pub async fn redeliver_admin_webhook_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+    Path((id, delivery_id)): Path<(String, String)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_admin(&headers)?;
+    redeliver(&pool, None, &id, &delivery_id).await
+}
+
+fn user_not_found(user_id: &str) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": "User not found",
+            "details": format!("No user found with id '{}'", user_id)
+        })),
+    )
+}
+
+// Splits a comma-separated query parameter, ignoring blanks
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub async fn get_dashboard_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+    Extension(cache): Extension<DashboardCache>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // Check admin authentication
+    if !verify_admin_token(&headers) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "details": "Admin authentication required"
+            })),
+        ));
+    }
+
+    // Without `sections` every section is returned
+    let mut sections = Vec::new();
+    for name in split_list(query.sections.as_deref()) {
+        match DashboardSection::from_name(&name) {
+            Some(section) => sections.push(section),
+            None => {
+                let known: Vec<&str> = DashboardSection::ALL.iter().map(|s| s.name()).collect();
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": "Invalid dashboard section",
+                        "details": format!(
+                            "Unknown section '{}', expected one of: {}",
+                            name,
+                            known.join(", ")
+                        )
+                    })),
+                ));
+            }
+        }
+    }
+    if sections.is_empty() {
+        sections = DashboardSection::ALL.to_vec();
+    }
+
+    let filter = DashboardFilter {
+        entries: FuelEntryFilter {
+            from: query.from,
+            to: query.to,
+            vehicle: query.vehicle,
+            fuel_type: query.fuel_type,
+            flagged: query.exclude_flagged.then_some(false),
+            ..FuelEntryFilter::default()
+        },
+        user_ids: split_list(query.user_ids.as_deref()),
+    };
+
+    // A failed version lookup only means the result is not cached
+    let key = DashboardCache::key(&filter, &sections);
+    let data_version = if cache.is_enabled() {
+        match get_dashboard_data_version(&pool).await {
+            Ok(version) => Some(version),
+            Err(e) => {
+                eprintln!("Error getting dashboard data version: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if let Some(stats) = data_version
+        .as_deref()
+        .and_then(|version| cache.get(&key, version))
+    {
+        return Ok(Json(stats));
+    }
+
+    match get_dashboard_stats(&pool, &filter, &sections).await {
+        Ok(stats) => {
+            let stats = json!(stats);
+            if let Some(version) = data_version {
+                cache.insert(key, version, stats.clone());
+            }
+            Ok(Json(stats))
+        }
+        Err(e) => {
+            eprintln!("Error getting dashboard stats: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get dashboard statistics",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+const DEFAULT_ANOMALY_QUEUE_LIMIT: i64 = 100;
+const MAX_ANOMALY_QUEUE_LIMIT: i64 = 500;
+
+pub async fn get_anomaly_queue_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+    Query(query): Query<AnomalyQueueQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // Check admin authentication
+    if !verify_admin_token(&headers) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "details": "Admin authentication required"
+            })),
+        ));
+    }
+
+    let status = query.status.unwrap_or(AnomalyStatus::Pending);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ANOMALY_QUEUE_LIMIT)
+        .clamp(1, MAX_ANOMALY_QUEUE_LIMIT);
+
+    match get_anomaly_queue(&pool, status, query.user_id.as_deref(), limit).await {
+        Ok(entries) => Ok(Json(json!(entries))),
+        Err(e) => {
+            eprintln!("Error getting anomaly queue: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get flagged entries",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn review_anomaly_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+    Path(id): Path<String>,
+    Json(request): Json<ReviewAnomalyRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // Check admin authentication
+    if !verify_admin_token(&headers) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "details": "Admin authentication required"
+            })),
+        ));
+    }
+
+    match review_anomaly(&pool, &id, request.status).await {
+        Ok(Some(entry)) => Ok(Json(json!(entry))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "Flagged entry not found",
+                "details": format!("No flagged fuel entry found with id '{}'", id)
+            })),
+        )),
+        Err(e) => {
+            eprintln!("Error reviewing anomaly of fuel entry {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to review flagged entry",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn rescan_anomalies_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // Check admin authentication
+    if !verify_admin_token(&headers) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "details": "Admin authentication required"
+            })),
+        ));
+    }
+
+    match rescan_anomalies(&pool).await {
+        Ok(flagged) => Ok(Json(json!({
+            "message": "All fuel entries have been re-checked",
+            "flagged_entries": flagged
+        }))),
+        Err(e) => {
+            eprintln!("Error rescanning anomalies: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to re-check fuel entries",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_all_users_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // Check admin authentication
+    if !verify_admin_token(&headers) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "details": "Admin authentication required"
+            })),
+        ));
+    }
+    match get_all_users(&pool).await {
+        Ok(users) => {
+            let safe_users: Vec<_> = users
+                .into_iter()
+                .map(|user| {
+                    json!({
+                        "id": user.id,
+                        "email": user.email,
+                        "created_at": user.created_at
+                    })
+                })
+                .collect();
+            Ok(Json(json!(safe_users)))
+        }
+        Err(e) => {
+            eprintln!("Error getting all users: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to get users",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn admin_action_handler(
+    headers: HeaderMap,
+    State(pool): State<SqlitePool>,
+    Json(request): Json<AdminActionRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // Check admin authentication
+    if !verify_admin_token(&headers) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "details": "Admin authentication required"
+            })),
+        ));
+    }
+    match request.action.as_str() {
+        "delete_user" => {
+            if let Some(user_id) = request.user_id {
+                match delete_user_by_id(&pool, &user_id, &Actor::admin()).await {
+                    Ok(true) => Ok(Json(json!({
+                        "message": "User deleted successfully",
+                        "user_id": user_id
+                    }))),
+                    Ok(false) => Err((
+                        StatusCode::NOT_FOUND,
+                        Json(json!({
+                            "error": "User not found",
+                            "details": format!("No user found with id '{}'", user_id)
+                        })),
+                    )),
+                    Err(e) => {
+                        eprintln!("Error deleting user {}: {}", user_id, e);
+                        Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(json!({
+                                "error": "Failed to delete user",
+                                "details": e.to_string()
+                            })),
+                        ))
+                    }
+                }
+            } else {
+                Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": "Missing user_id",
+                        "details": "user_id is required for delete_user action"
+                    })),
+                ))
+            }
+        }
+        "restore_user" => {
+            if let Some(user_id) = request.user_id {
+                match restore_user_by_id(&pool, &user_id, &Actor::admin()).await {
+                    Ok(true) => Ok(Json(json!({
+                        "message": "User restored successfully",
+                        "user_id": user_id
+                    }))),
+                    Ok(false) => Err((
+                        StatusCode::NOT_FOUND,
+                        Json(json!({
+                            "error": "Deleted user not found",
+                            "details": format!("No deleted user found with id '{}'", user_id)
+                        })),
+                    )),
+                    Err(e) => {
+                        eprintln!("Error restoring user {}: {}", user_id, e);
+                        Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(json!({
+                                "error": "Failed to restore user",
+                                "details": e.to_string()
+                            })),
+                        ))
+                    }
+                }
+            } else {
+                Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": "Missing user_id",
+                        "details": "user_id is required for restore_user action"
+                    })),
+                ))
+            }
+        }
+        "delete_entry" => {
+            if let (Some(user_id), Some(entry_id)) = (request.user_id, request.entry_id) {
+                match delete_fuel_entry(&pool, &entry_id, &user_id, None, &Actor::admin()).await {
+                    Ok(true) => Ok(Json(json!({
+                        "message": "Fuel entry deleted successfully",
+                        "entry_id": entry_id,
+                        "user_id": user_id
+                    }))),
+                    Ok(false) => Err((
+                        StatusCode::NOT_FOUND,
+                        Json(json!({
+                            "error": "Fuel entry not found",
+                            "details": format!("No fuel entry found with id '{}' for user '{}'", entry_id, user_id)
+                        })),
+                    )),
+                    Err(e) => {
+                        eprintln!(
+                            "Error deleting fuel entry {} for user {}: {}",
+                            entry_id, user_id, e
+                        );
+                        Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(json!({
+                                "error": "Failed to delete fuel entry",
+                                "details": e.to_string()
+                            })),
+                        ))
+                    }
+                }
+            } else {
+                Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": "Missing parameters",
+                        "details": "Both user_id and entry_id are required for delete_entry action"
+                    })),
+                ))
+            }
+        }
+        _ => Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "Invalid action",
+                "details": format!("Unknown action '{}'", request.action)
+            })),
+        )),
+    }
+}
+
+// pub async fn serve_dashboard() -> Html<&'static str> {
+//     Html(include_str!("../index.html"))
+// }
+
+pub async fn admin_login_handler(
+    Json(request): Json<AdminLoginRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // Hardcoded admin credentials
+    const ADMIN_EMAIL: &str = "me.remon.ahammad@bss.io";
+    const ADMIN_PASSWORD: &str = "rustybustyrestapideshboard";
+
+    if request.email == ADMIN_EMAIL && request.password == ADMIN_PASSWORD {
+        let (token, expires_at) = issue_admin_token(chrono::Utc::now());
+
+        Ok(Json(json!(AdminLoginResponse {
+            success: true,
+            token: Some(token),
+            expires_at: Some(expires_at),
+            message: "Admin login successful".to_string(),
+        })))
+    } else {
+        Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!(AdminLoginResponse {
+                success: false,
+                token: None,
+                expires_at: None,
+                message: "Invalid admin credentials".to_string(),
+            })),
+        ))
+    }
+}
+
+pub async fn admin_verify_handler(
+    headers: axum::http::HeaderMap,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    if verify_admin_token(&headers) {
+        return Ok(Json(json!({
+            "valid": true,
+            "message": "Token is valid"
+        })));
+    }
+
+    Err((
         StatusCode::UNAUTHORIZED,
         Json(json!({
             "valid": false,
@@ -775,16 +2076,164 @@ pub async fn admin_verify_handler(
     ))
 }
 
-// Helper function to verify admin token
+// Helper function to verify admin token: signed by this server and not expired
 fn verify_admin_token(headers: &HeaderMap) -> bool {
     if let Some(auth_header) = headers.get("authorization") {
         if let Ok(auth_str) = auth_header.to_str() {
-            return auth_str.starts_with("Bearer admin_token_");
+            return auth_str
+                .strip_prefix("Bearer ")
+                .is_some_and(|token| auth::verify_admin_token(token, chrono::Utc::now()));
         }
     }
     false
 }
 
+fn ensure_admin(headers: &HeaderMap) -> Result<(), (StatusCode, Json<Value>)> {
+    if verify_admin_token(headers) {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "details": "Admin authentication required"
+            })),
+        ))
+    }
+}
+
+// Helper function to reject requests for users that do not exist or are deleted
+async fn ensure_user_exists(
+    pool: &SqlitePool,
+    user_id: &str,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    match sqlx::query("SELECT id FROM users WHERE id = ? AND deleted_at IS NULL")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "Invalid user ID",
+                "details": format!("No user found with id '{}'", user_id)
+            })),
+        )),
+        Err(e) => {
+            eprintln!("Error validating user_id: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Database validation error",
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+// Helper function to report invalid entry data as 422 with one error per field
+fn validation_error_response(e: &anyhow::Error) -> Option<(StatusCode, Json<Value>)> {
+    e.downcast_ref::<ValidationError>().map(|validation| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Validation failed",
+                "details": validation.to_string(),
+                "field_errors": validation.errors
+            })),
+        )
+    })
+}
+
+// Strong ETag of a single fuel entry, derived from its version counter
+fn entry_etag(entry: &FuelEntry) -> String {
+    format!("\"{}\"", entry.version)
+}
+
+// Weak ETag of a list of entries; changes whenever an entry is added, removed,
+// modified or flagged differently
+fn entries_etag(entries: &[FuelEntry]) -> String {
+    let mut hasher = DefaultHasher::new();
+    for entry in entries {
+        entry.id.hash(&mut hasher);
+        entry.version.hash(&mut hasher);
+        entry.anomalies.len().hash(&mut hasher);
+        entry.anomaly_status.hash(&mut hasher);
+    }
+    format!("W/\"{:x}\"", hasher.finish())
+}
+
+// Responds with the body and its ETag, or with an empty 304 when the client's
+// If-None-Match already names that ETag (compared weakly, as for GET)
+fn conditional_json(headers: &HeaderMap, etag: String, body: Value) -> Response {
+    let opaque_tag = etag.trim_start_matches("W/").to_string();
+    let not_modified = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == opaque_tag)
+        });
+
+    if not_modified {
+        (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response()
+    } else {
+        ([(ETAG, etag)], Json(body)).into_response()
+    }
+}
+
+// Helper function to read the entry version a PUT or DELETE is conditioned on.
+// If-Match is required; `*` explicitly skips the version check.
+fn if_match_version(headers: &HeaderMap) -> Result<Option<i64>, (StatusCode, Json<Value>)> {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return Err((
+            StatusCode::PRECONDITION_REQUIRED,
+            Json(json!({
+                "error": "Precondition required",
+                "details": "Send the entry's current ETag in the If-Match header"
+            })),
+        ));
+    };
+
+    let value = value.to_str().unwrap_or_default().trim();
+    if value == "*" {
+        return Ok(None);
+    }
+
+    value
+        .strip_prefix('"')
+        .and_then(|tag| tag.strip_suffix('"'))
+        .and_then(|tag| tag.parse::<i64>().ok())
+        .map(Some)
+        .ok_or_else(|| {
+            (
+                StatusCode::PRECONDITION_FAILED,
+                Json(json!({
+                    "error": "Precondition failed",
+                    "details": format!("If-Match value '{}' is not an ETag of this entry", value)
+                })),
+            )
+        })
+}
+
+// Helper function to report a stale If-Match as 412 with the entry's current ETag
+fn version_conflict_response(e: &anyhow::Error) -> Option<(StatusCode, Json<Value>)> {
+    e.downcast_ref::<VersionConflict>().map(|conflict| {
+        (
+            StatusCode::PRECONDITION_FAILED,
+            Json(json!({
+                "error": "Precondition failed",
+                "details": conflict.to_string(),
+                "current_etag": format!("\"{}\"", conflict.current_version)
+            })),
+        )
+    })
+}
+
 pub async fn get_service_status_handler(
     headers: HeaderMap,
     State(pool): State<SqlitePool>,
@@ -0,0 +1,328 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+use crate::database::{claim_idempotency_key, release_idempotency_key, store_idempotent_response};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+// Set on responses that were replayed from an earlier request with the same key
+const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const MAX_KEY_LENGTH: usize = 255;
+
+// Same limit axum applies to JSON bodies by default
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+// Only these response headers are stored and replayed with the body
+const REPLAYED_HEADERS: &[header::HeaderName] = &[header::CONTENT_TYPE, header::ETAG];
+
+const DEFAULT_IDEMPOTENCY_KEY_TTL_HOURS: i64 = 24;
+
+pub fn idempotency_key_ttl_hours() -> i64 {
+    std::env::var("IDEMPOTENCY_KEY_TTL_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse::<i64>().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(DEFAULT_IDEMPOTENCY_KEY_TTL_HOURS)
+}
+
+#[derive(Clone)]
+pub struct IdempotencyState {
+    pub pool: SqlitePool,
+    pub ttl_hours: i64,
+}
+
+// Keys only have to be unique per client, so they are stored together with the
+// route and the user the request acts for (the body's or query's `user_id`).
+// Two users, or one user on two routes, never see each other's keys.
+fn scoped_key(method: &Method, uri: &Uri, body: &[u8], key: &str) -> String {
+    let user_id = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|body| body.get("user_id")?.as_str().map(str::to_string))
+        .or_else(|| {
+            uri.query()?
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("user_id="))
+                .map(str::to_string)
+        })
+        .unwrap_or_default();
+
+    format!("{} {} {} {}", method, uri.path(), user_id, key)
+}
+
+// An in-progress claim on a key. Unless the request completes, dropping the
+// claim gives the key up again, so a handler that panics or a client that
+// disconnects mid-request does not leave the key answering 409 until it expires.
+struct KeyClaim {
+    pool: SqlitePool,
+    key: String,
+    completed: bool,
+}
+
+impl KeyClaim {
+    fn complete(mut self) {
+        self.completed = true;
+    }
+
+    async fn release(mut self) {
+        self.completed = true;
+        if let Err(e) = release_idempotency_key(&self.pool, &self.key).await {
+            eprintln!("Error releasing idempotency key {}: {}", self.key, e);
+        }
+    }
+}
+
+impl Drop for KeyClaim {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let pool = self.pool.clone();
+        let key = std::mem::take(&mut self.key);
+        runtime.spawn(async move {
+            if let Err(e) = release_idempotency_key(&pool, &key).await {
+                eprintln!("Error releasing idempotency key {}: {}", key, e);
+            }
+        });
+    }
+}
+
+// Makes POST/PUT/PATCH/DELETE requests that carry an Idempotency-Key safe to
+// retry: the first response is stored and replayed for repeats with the same
+// method, path and body within the TTL. Reusing a key for a different request
+// to the same route and user is rejected with 422, and a repeat that arrives
+// while the first request is still running gets 409. Server errors are not
+// stored so they can be retried.
+pub async fn idempotency_middleware(
+    State(state): State<IdempotencyState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_mutation = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    let key = match request.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(key) if is_mutation => key.to_str(),
+        _ => return next.run(request).await,
+    };
+
+    let key = match key {
+        Ok(key) if !key.trim().is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+        _ => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "Invalid idempotency key",
+                format!(
+                    "Idempotency-Key must be 1 to {} visible ASCII characters",
+                    MAX_KEY_LENGTH
+                ),
+            );
+        }
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Failed to read request body",
+                e.to_string(),
+            );
+        }
+    };
+
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str());
+    hasher.update(b"\n");
+    hasher.update(
+        parts
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or_default(),
+    );
+    hasher.update(b"\n");
+    hasher.update(&body);
+    let request_hash = format!("{:x}", hasher.finalize());
+
+    let stored_key = scoped_key(&parts.method, &parts.uri, &body, &key);
+    let expired_before = Utc::now() - Duration::hours(state.ttl_hours);
+    let claim = match claim_idempotency_key(&state.pool, &stored_key, &request_hash, expired_before)
+        .await
+    {
+        Ok(None) => KeyClaim {
+            pool: state.pool.clone(),
+            key: stored_key,
+            completed: false,
+        },
+        Ok(Some(record)) if record.request_hash != request_hash => {
+            return error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Idempotency key reused",
+                format!(
+                    "Idempotency-Key '{}' was already used for a different request",
+                    key
+                ),
+            );
+        }
+        Ok(Some(record)) => {
+            let Some(status_code) = record.status_code else {
+                return error_response(
+                    StatusCode::CONFLICT,
+                    "Request in progress",
+                    format!(
+                        "A request with Idempotency-Key '{}' is still being processed",
+                        key
+                    ),
+                );
+            };
+
+            let mut response = Response::new(Body::from(record.response_body.unwrap_or_default()));
+            *response.status_mut() =
+                StatusCode::from_u16(status_code as u16).unwrap_or(StatusCode::OK);
+            let headers = response.headers_mut();
+            for (name, value) in record.response_headers.map(|h| h.0).unwrap_or_default() {
+                if let (Ok(name), Ok(value)) = (
+                    header::HeaderName::try_from(name),
+                    HeaderValue::try_from(value),
+                ) {
+                    headers.insert(name, value);
+                }
+            }
+            headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+            return response;
+        }
+        Err(e) => {
+            eprintln!("Error claiming idempotency key {}: {}", key, e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to check idempotency key",
+                e.to_string(),
+            );
+        }
+    };
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    if response.status().is_server_error() {
+        claim.release().await;
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            claim.release().await;
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read response body",
+                e.to_string(),
+            );
+        }
+    };
+
+    let headers: Vec<(String, String)> = REPLAYED_HEADERS
+        .iter()
+        .filter_map(|name| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| (name.to_string(), value.to_string()))
+        })
+        .collect();
+
+    match store_idempotent_response(
+        &state.pool,
+        &claim.key,
+        parts.status.as_u16(),
+        &headers,
+        &body,
+    )
+    .await
+    {
+        Ok(()) => claim.complete(),
+        Err(e) => {
+            // Without a stored response the key would look in progress until it expires
+            eprintln!("Error storing response for idempotency key {}: {}", key, e);
+            claim.release().await;
+        }
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn error_response(status: StatusCode, error: &str, details: String) -> Response {
+    (
+        status,
+        Json(json!({
+            "error": error,
+            "details": details
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::claim_idempotency_key;
+    use crate::migrations::run_migrations;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[test]
+    fn keys_are_scoped_to_route_and_user() {
+        let uri: Uri = "/api/fuel-entries".parse().unwrap();
+        let alice = scoped_key(&Method::POST, &uri, br#"{"user_id":"alice"}"#, "k1");
+        let bob = scoped_key(&Method::POST, &uri, br#"{"user_id":"bob"}"#, "k1");
+        assert_ne!(alice, bob);
+
+        let bulk: Uri = "/api/fuel-entries/bulk".parse().unwrap();
+        assert_ne!(
+            alice,
+            scoped_key(&Method::POST, &bulk, br#"{"user_id":"alice"}"#, "k1")
+        );
+
+        let query: Uri = "/api/things?user_id=alice".parse().unwrap();
+        assert!(scoped_key(&Method::DELETE, &query, b"", "k1").contains(" alice "));
+    }
+
+    #[tokio::test]
+    async fn dropping_an_unfinished_claim_releases_the_key() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        run_migrations(&pool).await.unwrap();
+        let expired_before = Utc::now() - Duration::hours(1);
+
+        let claimed = claim_idempotency_key(&pool, "k1", "hash", expired_before).await;
+        assert!(claimed.unwrap().is_none());
+        drop(KeyClaim {
+            pool: pool.clone(),
+            key: "k1".to_string(),
+            completed: false,
+        });
+
+        // The release runs on a spawned task
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let reclaimed = claim_idempotency_key(&pool, "k1", "hash", expired_before).await;
+        assert!(reclaimed.unwrap().is_none());
+    }
+}
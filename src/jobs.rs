@@ -0,0 +1,102 @@
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+use crate::database::{purge_deleted, purge_idempotency_keys};
+use crate::notifications::{deliver_pending_alerts, Notifier};
+use crate::webhooks::deliver_due_webhooks;
+
+// How often the trash is checked for rows past their retention period
+const TRASH_PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+// How often stored idempotent responses past their TTL are removed
+const IDEMPOTENCY_KEY_PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+// How often new budget alerts are sent to users' email channels
+pub const NOTIFICATION_DELIVERY_INTERVAL_SECS: u64 = 30;
+
+// How often the webhook outbox is checked for deliveries that are due
+pub const WEBHOOK_DELIVERY_INTERVAL_SECS: u64 = 10;
+
+const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+pub fn trash_retention_days() -> i64 {
+    std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i64>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)
+}
+
+pub fn spawn_trash_purge(pool: SqlitePool, retention_days: i64) {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(TRASH_PURGE_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = Utc::now() - Duration::days(retention_days);
+            match purge_deleted(&pool, cutoff).await {
+                Ok((0, 0)) => {}
+                Ok((entries, users)) => {
+                    println!(
+                        "🗑️ Purged {} fuel entries and {} users from trash",
+                        entries, users
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Error purging trash: {}", e);
+                }
+            }
+        }
+    });
+}
+
+pub fn spawn_idempotency_key_purge(pool: SqlitePool, ttl_hours: i64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            IDEMPOTENCY_KEY_PURGE_INTERVAL_SECS,
+        ));
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = Utc::now() - Duration::hours(ttl_hours);
+            if let Err(e) = purge_idempotency_keys(&pool, cutoff).await {
+                eprintln!("Error purging idempotency keys: {}", e);
+            }
+        }
+    });
+}
+
+pub fn spawn_notification_delivery(pool: SqlitePool, notifier: Notifier) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            NOTIFICATION_DELIVERY_INTERVAL_SECS,
+        ));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = deliver_pending_alerts(&pool, &notifier).await {
+                eprintln!("Error delivering budget alerts: {}", e);
+            }
+        }
+    });
+}
+
+pub fn spawn_webhook_delivery(pool: SqlitePool, client: reqwest::Client) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            WEBHOOK_DELIVERY_INTERVAL_SECS,
+        ));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = deliver_due_webhooks(&pool, &client).await {
+                eprintln!("Error delivering webhooks: {}", e);
+            }
+        }
+    });
+}
@@ -1,24 +1,59 @@
+mod anomalies;
 mod auth;
+mod budgets;
+mod dashboard_cache;
 mod database;
+mod distribution;
+mod duplicates;
+mod forecast;
 mod handlers;
+mod idempotency;
+mod jobs;
+mod migrations;
 mod models;
+mod notifications;
+mod pricing;
+mod validation;
+mod webhooks;
 
+use auth::{admin_token_secret_configured, admin_token_ttl_hours};
 use axum::{
-    routing::{get, post},
-    Router,
+    middleware,
+    routing::{delete, get, post, put},
+    Extension, Router,
 };
-use tower::ServiceBuilder;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing_subscriber;
-
-use database::{create_database_pool, create_tables};
+use dashboard_cache::{dashboard_cache_ttl_secs, DashboardCache};
+use database::create_database_pool;
 use handlers::{
-    admin_action_handler, admin_login_handler, admin_verify_handler, create_fuel_entries_handler,
-    create_fuel_entry_handler, delete_fuel_entries_handler, delete_fuel_entry_handler,
-    get_all_users_handler, get_dashboard_handler, get_fuel_entries_handler, get_fuel_entry_handler,
-    get_service_status_handler, signin, signup, toggle_service_handler,
-    update_fuel_entry_handler,
+    admin_action_handler, admin_login_handler, admin_verify_handler, create_admin_webhook_handler,
+    create_budget_handler, create_fuel_entries_handler, create_fuel_entry_handler,
+    create_notification_channel_handler, create_user_webhook_handler, delete_admin_webhook_handler,
+    delete_budget_handler, delete_fuel_entries_handler, delete_fuel_entry_handler,
+    delete_notification_channel_handler, delete_user_webhook_handler, delete_vehicle_handler,
+    get_admin_webhook_deliveries_handler, get_admin_webhooks_handler, get_all_users_handler,
+    get_anomaly_queue_handler, get_budgets_handler, get_dashboard_handler,
+    get_fuel_entries_handler, get_fuel_entry_handler, get_fuel_entry_history_handler,
+    get_notification_channels_handler, get_notifications_handler, get_service_status_handler,
+    get_sync_changes_handler, get_trash_handler, get_user_settings_handler, get_user_stats_handler,
+    get_user_webhook_deliveries_handler, get_user_webhooks_handler, get_vehicles_handler,
+    mark_notifications_read_handler, redeliver_admin_webhook_handler,
+    redeliver_user_webhook_handler, rescan_anomalies_handler, restore_fuel_entries_handler,
+    restore_fuel_entry_handler, revert_fuel_entry_handler, review_anomaly_handler,
+    set_vehicle_handler, signin, signup, sync_push_handler, toggle_service_handler,
+    update_budget_handler, update_fuel_entries_handler, update_fuel_entry_handler,
+    update_user_settings_handler,
+};
+use idempotency::{idempotency_key_ttl_hours, idempotency_middleware, IdempotencyState};
+use jobs::{
+    spawn_idempotency_key_purge, spawn_notification_delivery, spawn_trash_purge,
+    spawn_webhook_delivery, trash_retention_days, NOTIFICATION_DELIVERY_INTERVAL_SECS,
+    WEBHOOK_DELIVERY_INTERVAL_SECS,
 };
+use migrations::{current_version, rollback_migrations, run_migrations};
+use notifications::Notifier;
+use tower::ServiceBuilder;
+use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use webhooks::webhook_client;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -31,10 +66,100 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🔌 Creating database pool...");
     let pool = create_database_pool().await?;
     println!("✅ Database pool created");
-    // Create tables if they don't exist
-    println!("📊 Creating tables...");
-    create_tables(&pool).await?;
-    println!("✅ Tables created");
+
+    // `--migrate` and `--rollback [steps]` manage the schema and exit without serving
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("--migrate") => {
+            let applied = run_migrations(&pool).await?;
+            println!(
+                "✅ Applied {} migration(s), schema is at version {}",
+                applied,
+                current_version(&pool).await?
+            );
+            return Ok(());
+        }
+        Some("--rollback") => {
+            let steps = match args.get(1) {
+                Some(steps) => steps.parse::<usize>()?,
+                None => 1,
+            };
+            let rolled_back = rollback_migrations(&pool, steps).await?;
+            println!(
+                "✅ Rolled back {} migration(s), schema is at version {}",
+                rolled_back,
+                current_version(&pool).await?
+            );
+            return Ok(());
+        }
+        Some(other) => {
+            return Err(format!(
+                "Unknown argument '{}', expected --migrate or --rollback [steps]",
+                other
+            )
+            .into());
+        }
+        None => {}
+    }
+
+    // Bring the schema up to date; refuses to start if the database is newer than this binary
+    println!("📊 Running migrations...");
+    run_migrations(&pool).await?;
+    println!("✅ Migrations complete");
+
+    // Permanently remove soft-deleted rows once their retention period has passed
+    let retention_days = trash_retention_days();
+    spawn_trash_purge(pool.clone(), retention_days);
+    println!(
+        "🗑️ Trash purge scheduled (retention {} days)",
+        retention_days
+    );
+
+    // Stored responses for Idempotency-Key retries expire after the TTL
+    let idempotency_ttl_hours = idempotency_key_ttl_hours();
+    spawn_idempotency_key_purge(pool.clone(), idempotency_ttl_hours);
+    println!(
+        "🔁 Idempotency keys kept for {} hours",
+        idempotency_ttl_hours
+    );
+
+    // Admin tokens are signed; an unset secret means they do not survive a restart
+    println!(
+        "🔑 Admin tokens valid for {} hours ({})",
+        admin_token_ttl_hours(),
+        if admin_token_secret_configured() {
+            "ADMIN_TOKEN_SECRET set"
+        } else {
+            "random signing key, set ADMIN_TOKEN_SECRET to keep them across restarts"
+        }
+    );
+
+    // Budget alerts go out to email channels in the background; webhooks get
+    // them from the webhook outbox
+    let notifier = Notifier::from_env()?;
+    let email_enabled = notifier.email_enabled();
+    spawn_notification_delivery(pool.clone(), notifier);
+    println!(
+        "🔔 Budget alerts delivered every {} seconds (email {})",
+        NOTIFICATION_DELIVERY_INTERVAL_SECS,
+        if email_enabled { "enabled" } else { "disabled" }
+    );
+
+    // Entry and account events queued in the webhook outbox are sent and retried in the background
+    spawn_webhook_delivery(pool.clone(), webhook_client()?);
+    println!(
+        "🪝 Webhook outbox checked every {} seconds",
+        WEBHOOK_DELIVERY_INTERVAL_SECS
+    );
+
+    // Dashboard results are reused until the TTL passes or the data changes
+    let dashboard_cache_ttl = dashboard_cache_ttl_secs();
+    let dashboard_cache = DashboardCache::new(dashboard_cache_ttl);
+    if dashboard_cache.is_enabled() {
+        println!("🧮 Dashboard cache TTL {} seconds", dashboard_cache_ttl);
+    } else {
+        println!("🧮 Dashboard cache disabled");
+    }
     println!("🛣️ Building router...");
     // Build our application with routes
     let app = Router::new()
@@ -48,25 +173,132 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "/api/fuel-entries/bulk/delete",
             post(delete_fuel_entries_handler),
         )
+        .route(
+            "/api/fuel-entries/bulk/restore",
+            post(restore_fuel_entries_handler),
+        )
+        .route(
+            "/api/fuel-entries/bulk/update",
+            post(update_fuel_entries_handler),
+        )
         .route("/api/fuel-entries/:user_id", get(get_fuel_entries_handler))
+        .route("/api/fuel-entries/:user_id/trash", get(get_trash_handler))
         .route(
             "/api/fuel-entries/:user_id/:id",
             get(get_fuel_entry_handler)
                 .put(update_fuel_entry_handler)
                 .delete(delete_fuel_entry_handler),
         )
+        .route(
+            "/api/fuel-entries/:user_id/:id/restore",
+            post(restore_fuel_entry_handler),
+        )
+        .route(
+            "/api/fuel-entries/:user_id/:id/history",
+            get(get_fuel_entry_history_handler),
+        )
+        .route(
+            "/api/fuel-entries/:user_id/:id/history/:revision/revert",
+            post(revert_fuel_entry_handler),
+        )
+        // Offline sync routes
+        .route("/api/sync", get(get_sync_changes_handler))
+        .route("/api/sync/push", post(sync_push_handler))
         // Dashboard routes
+        .route("/api/users/:user_id/stats", get(get_user_stats_handler))
+        .route(
+            "/api/users/:user_id/settings",
+            get(get_user_settings_handler).put(update_user_settings_handler),
+        )
+        .route("/api/users/:user_id/vehicles", get(get_vehicles_handler))
+        .route(
+            "/api/users/:user_id/vehicles/:vehicle",
+            put(set_vehicle_handler).delete(delete_vehicle_handler),
+        )
+        .route(
+            "/api/users/:user_id/budgets",
+            get(get_budgets_handler).post(create_budget_handler),
+        )
+        .route(
+            "/api/users/:user_id/budgets/:id",
+            put(update_budget_handler).delete(delete_budget_handler),
+        )
+        .route(
+            "/api/users/:user_id/notifications",
+            get(get_notifications_handler),
+        )
+        .route(
+            "/api/users/:user_id/notifications/read",
+            post(mark_notifications_read_handler),
+        )
+        .route(
+            "/api/users/:user_id/notification-channels",
+            get(get_notification_channels_handler).post(create_notification_channel_handler),
+        )
+        .route(
+            "/api/users/:user_id/notification-channels/:id",
+            delete(delete_notification_channel_handler),
+        )
+        .route(
+            "/api/users/:user_id/webhooks",
+            get(get_user_webhooks_handler).post(create_user_webhook_handler),
+        )
+        .route(
+            "/api/users/:user_id/webhooks/:id",
+            delete(delete_user_webhook_handler),
+        )
+        .route(
+            "/api/users/:user_id/webhooks/:id/deliveries",
+            get(get_user_webhook_deliveries_handler),
+        )
+        .route(
+            "/api/users/:user_id/webhooks/:id/deliveries/:delivery_id/redeliver",
+            post(redeliver_user_webhook_handler),
+        )
         .route("/api/dashboard", get(get_dashboard_handler))
         .route("/api/admin/users", get(get_all_users_handler))
         .route("/api/admin/action", post(admin_action_handler))
         .route("/api/admin/service-status", get(get_service_status_handler))
         .route("/api/admin/service-toggle", post(toggle_service_handler))
+        .route("/api/admin/anomalies", get(get_anomaly_queue_handler))
+        .route(
+            "/api/admin/anomalies/rescan",
+            post(rescan_anomalies_handler),
+        )
+        .route(
+            "/api/admin/anomalies/:id/review",
+            post(review_anomaly_handler),
+        )
+        .route(
+            "/api/admin/webhooks",
+            get(get_admin_webhooks_handler).post(create_admin_webhook_handler),
+        )
+        .route(
+            "/api/admin/webhooks/:id",
+            delete(delete_admin_webhook_handler),
+        )
+        .route(
+            "/api/admin/webhooks/:id/deliveries",
+            get(get_admin_webhook_deliveries_handler),
+        )
+        .route(
+            "/api/admin/webhooks/:id/deliveries/:delivery_id/redeliver",
+            post(redeliver_admin_webhook_handler),
+        )
         // Admin authentication routes
         .route("/api/admin/login", post(admin_login_handler))
         .route("/api/admin/verify", get(admin_verify_handler))
         // Static files
         // .route("/", get(serve_dashboard))
         // .route("/dashboard", get(serve_dashboard))
+        .layer(middleware::from_fn_with_state(
+            IdempotencyState {
+                pool: pool.clone(),
+                ttl_hours: idempotency_ttl_hours,
+            },
+            idempotency_middleware,
+        ))
+        .layer(Extension(dashboard_cache))
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
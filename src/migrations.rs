@@ -0,0 +1,293 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+// Migrations are applied in order and must never be edited once released;
+// schema changes always go into a new pair of files with the next version number.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        up: include_str!("migrations/0001_initial_schema.up.sql"),
+        down: include_str!("migrations/0001_initial_schema.down.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "typed_fuel_entries",
+        up: include_str!("migrations/0002_typed_fuel_entries.up.sql"),
+        down: include_str!("migrations/0002_typed_fuel_entries.down.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "soft_delete",
+        up: include_str!("migrations/0003_soft_delete.up.sql"),
+        down: include_str!("migrations/0003_soft_delete.down.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "fuel_entry_revisions",
+        up: include_str!("migrations/0004_fuel_entry_revisions.up.sql"),
+        down: include_str!("migrations/0004_fuel_entry_revisions.down.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "fuel_entry_versions",
+        up: include_str!("migrations/0005_fuel_entry_versions.up.sql"),
+        down: include_str!("migrations/0005_fuel_entry_versions.down.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "sync_change_feed",
+        up: include_str!("migrations/0006_sync_change_feed.up.sql"),
+        down: include_str!("migrations/0006_sync_change_feed.down.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "idempotency_keys",
+        up: include_str!("migrations/0007_idempotency_keys.up.sql"),
+        down: include_str!("migrations/0007_idempotency_keys.down.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "fuel_entry_vehicle_tags",
+        up: include_str!("migrations/0008_fuel_entry_vehicle_tags.up.sql"),
+        down: include_str!("migrations/0008_fuel_entry_vehicle_tags.down.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "fuel_entry_fuel_type",
+        up: include_str!("migrations/0009_fuel_entry_fuel_type.up.sql"),
+        down: include_str!("migrations/0009_fuel_entry_fuel_type.down.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "user_locale",
+        up: include_str!("migrations/0010_user_locale.up.sql"),
+        down: include_str!("migrations/0010_user_locale.down.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "entry_anomalies",
+        up: include_str!("migrations/0011_entry_anomalies.up.sql"),
+        down: include_str!("migrations/0011_entry_anomalies.down.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "budgets",
+        up: include_str!("migrations/0012_budgets.up.sql"),
+        down: include_str!("migrations/0012_budgets.down.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "webhooks",
+        up: include_str!("migrations/0013_webhooks.up.sql"),
+        down: include_str!("migrations/0013_webhooks.down.sql"),
+    },
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+async fn ensure_migrations_table(pool: &SqlitePool) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn current_version(pool: &SqlitePool) -> Result<i64> {
+    ensure_migrations_table(pool).await?;
+
+    let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM schema_migrations")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(version.unwrap_or(0))
+}
+
+// Refuse to touch a database that was migrated by a newer build, since this
+// binary would neither understand its schema nor be able to roll it back.
+pub async fn check_schema_version(pool: &SqlitePool) -> Result<()> {
+    let current = current_version(pool).await?;
+    let latest = latest_version();
+
+    if current > latest {
+        bail!(
+            "Database schema version {} is newer than the latest version {} known to this binary",
+            current,
+            latest
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn run_migrations(pool: &SqlitePool) -> Result<usize> {
+    check_schema_version(pool).await?;
+
+    let applied: Vec<i64> = sqlx::query_scalar("SELECT version FROM schema_migrations")
+        .fetch_all(pool)
+        .await?;
+
+    let mut applied_count = 0;
+    for migration in MIGRATIONS {
+        if applied.contains(&migration.version) {
+            continue;
+        }
+
+        // Each migration runs in its own transaction together with its bookkeeping row
+        let mut tx = pool.begin().await?;
+
+        sqlx::raw_sql(migration.up).execute(&mut *tx).await?;
+
+        sqlx::query("INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        println!(
+            "✅ Applied migration {:04}_{}",
+            migration.version, migration.name
+        );
+        applied_count += 1;
+    }
+
+    Ok(applied_count)
+}
+
+pub async fn rollback_migrations(pool: &SqlitePool, steps: usize) -> Result<usize> {
+    check_schema_version(pool).await?;
+
+    let applied: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM schema_migrations ORDER BY version DESC LIMIT ?")
+            .bind(steps as i64)
+            .fetch_all(pool)
+            .await?;
+
+    for version in &applied {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.version == *version)
+            .ok_or_else(|| anyhow!("No migration with version {} is known", version))?;
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::raw_sql(migration.down).execute(&mut *tx).await?;
+
+        sqlx::query("DELETE FROM schema_migrations WHERE version = ?")
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        println!(
+            "↩️ Rolled back migration {:04}_{}",
+            migration.version, migration.name
+        );
+    }
+
+    Ok(applied.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn backfills_timestamps_in_the_encoding_sqlx_writes() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        // A database created before migrations existed, holding JSON blobs
+        sqlx::raw_sql(MIGRATIONS[0].up)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO users (id, email, password_hash, created_at) VALUES ('u1', 'u1@example.com', 'x', ?)")
+            .bind(Utc::now())
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (id, date_time) in [
+            ("a", "2026-01-01T10:00:00Z"),
+            ("b", "2026-01-02T08:30:00+02:00"),
+            ("c", "2026-01-03T08:30:00.250Z"),
+        ] {
+            let data = json!({
+                "liters": 40.0,
+                "price_per_liter": 1.5,
+                "total_cost": 60.0,
+                "date_time": date_time,
+            });
+            sqlx::query("INSERT INTO fuel_entries (id, user_id, data) VALUES (?, 'u1', ?)")
+                .bind(id)
+                .bind(data.to_string())
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        run_migrations(&pool).await.unwrap();
+
+        let stored: Vec<String> =
+            sqlx::query_scalar("SELECT date_time FROM fuel_entries ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            stored,
+            [
+                "2026-01-01T10:00:00+00:00",
+                "2026-01-02T06:30:00+00:00",
+                "2026-01-03T08:30:00.250+00:00",
+            ]
+        );
+
+        // Read back, every value is the instant that was recorded
+        let decoded: Vec<chrono::DateTime<Utc>> =
+            sqlx::query_scalar("SELECT date_time FROM fuel_entries ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            decoded[1],
+            "2026-01-02T06:30:00Z"
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap()
+        );
+        assert_eq!(
+            sqlx::query_scalar::<_, String>("SELECT ?")
+                .bind(decoded[0])
+                .fetch_one(&pool)
+                .await
+                .unwrap(),
+            stored[0]
+        );
+    }
+}
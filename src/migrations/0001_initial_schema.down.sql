@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS service_config;
+DROP TABLE IF EXISTS fuel_entries;
+DROP TABLE IF EXISTS users;
@@ -0,0 +1,27 @@
+-- Baseline schema as previously created by `create_tables`. Every statement is
+-- idempotent so databases created before migrations existed adopt it cleanly.
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    email TEXT UNIQUE NOT NULL,
+    password_hash TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS fuel_entries (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE TABLE IF NOT EXISTS service_config (
+    service_name TEXT PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    updated_at TEXT NOT NULL
+);
+
+INSERT OR IGNORE INTO service_config (service_name, enabled, updated_at)
+VALUES ('signin', 1, strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'));
+
+INSERT OR IGNORE INTO service_config (service_name, enabled, updated_at)
+VALUES ('fuel_entry', 1, strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'));
@@ -0,0 +1,26 @@
+-- Fold the typed columns back into the JSON `data` blob layout.
+CREATE TABLE fuel_entries_blob (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+INSERT INTO fuel_entries_blob (id, user_id, data)
+SELECT
+    id,
+    user_id,
+    json_object(
+        'id', id,
+        'user_id', user_id,
+        'liters', liters,
+        'price_per_liter', price_per_liter,
+        'total_cost', total_cost,
+        'date_time', date_time,
+        'odometer_reading', odometer_reading
+    )
+FROM fuel_entries;
+
+DROP TABLE fuel_entries;
+
+ALTER TABLE fuel_entries_blob RENAME TO fuel_entries;
@@ -0,0 +1,38 @@
+-- Replace the JSON `data` blob with typed columns and backfill existing rows.
+-- Timestamps are compared as text, so `date_time` is written the way sqlx
+-- encodes a DateTime<Utc>: whole seconds without a fraction.
+CREATE TABLE fuel_entries_typed (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    liters REAL NOT NULL,
+    price_per_liter REAL NOT NULL,
+    total_cost REAL NOT NULL,
+    date_time TEXT NOT NULL,
+    odometer_reading REAL,
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+INSERT INTO fuel_entries_typed (
+    id, user_id, liters, price_per_liter, total_cost, date_time, odometer_reading
+)
+SELECT
+    id,
+    user_id,
+    json_extract(data, '$.liters'),
+    json_extract(data, '$.price_per_liter'),
+    json_extract(data, '$.total_cost'),
+    CASE
+        WHEN strftime('%f', json_extract(data, '$.date_time')) LIKE '%.000'
+            THEN strftime('%Y-%m-%dT%H:%M:%S+00:00', json_extract(data, '$.date_time'))
+        ELSE strftime('%Y-%m-%dT%H:%M:%f+00:00', json_extract(data, '$.date_time'))
+    END,
+    json_extract(data, '$.odometer_reading')
+FROM fuel_entries;
+
+DROP TABLE fuel_entries;
+
+ALTER TABLE fuel_entries_typed RENAME TO fuel_entries;
+
+CREATE INDEX idx_fuel_entries_user_date ON fuel_entries (user_id, date_time);
+CREATE INDEX idx_fuel_entries_date ON fuel_entries (date_time);
+CREATE INDEX idx_fuel_entries_total_cost ON fuel_entries (total_cost);
@@ -0,0 +1,9 @@
+-- Tombstoned rows would reappear as live data, so they are removed first.
+DELETE FROM fuel_entries WHERE deleted_at IS NOT NULL;
+DELETE FROM fuel_entries WHERE user_id IN (SELECT id FROM users WHERE deleted_at IS NOT NULL);
+DELETE FROM users WHERE deleted_at IS NOT NULL;
+
+DROP INDEX idx_fuel_entries_deleted_at;
+
+ALTER TABLE users DROP COLUMN deleted_at;
+ALTER TABLE fuel_entries DROP COLUMN deleted_at;
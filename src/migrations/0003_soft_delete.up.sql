@@ -0,0 +1,5 @@
+-- Tombstones for soft-deleted rows; NULL means the row is live.
+ALTER TABLE fuel_entries ADD COLUMN deleted_at TEXT;
+ALTER TABLE users ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX idx_fuel_entries_deleted_at ON fuel_entries (deleted_at);
@@ -0,0 +1,16 @@
+-- Append-only audit trail of every change made to a fuel entry.
+CREATE TABLE fuel_entry_revisions (
+    id TEXT PRIMARY KEY,
+    entry_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    old_values TEXT,
+    new_values TEXT,
+    actor TEXT NOT NULL,
+    source TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE (entry_id, revision)
+);
+
+CREATE INDEX idx_fuel_entry_revisions_user ON fuel_entry_revisions (user_id);
@@ -0,0 +1 @@
+ALTER TABLE fuel_entries DROP COLUMN version;
@@ -0,0 +1,3 @@
+-- Per-entry version counter used for optimistic concurrency (ETag / If-Match).
+-- Every write to an entry increments it; existing rows start at 1.
+ALTER TABLE fuel_entries ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,5 @@
+DROP INDEX idx_fuel_entries_user_change_seq;
+
+ALTER TABLE fuel_entries DROP COLUMN change_seq;
+
+DROP TABLE sync_state;
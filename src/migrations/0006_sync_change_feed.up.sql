@@ -0,0 +1,21 @@
+-- Global, never reused sequence of entry changes that backs the sync feed.
+-- `purged_seq` is the highest sequence number removed by the trash purge;
+-- clients whose cursor is older than that have missed tombstones.
+-- `data_version` counts changes the dashboard reads that are not entry
+-- writes (those advance `last_seq`); together they version its cache.
+CREATE TABLE sync_state (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    last_seq INTEGER NOT NULL,
+    purged_seq INTEGER NOT NULL,
+    data_version INTEGER NOT NULL DEFAULT 0
+);
+
+ALTER TABLE fuel_entries ADD COLUMN change_seq INTEGER NOT NULL DEFAULT 0;
+
+-- Existing entries enter the feed in insertion order
+UPDATE fuel_entries SET change_seq = rowid;
+
+INSERT INTO sync_state (id, last_seq, purged_seq)
+SELECT 1, COALESCE(MAX(change_seq), 0), 0 FROM fuel_entries;
+
+CREATE INDEX idx_fuel_entries_user_change_seq ON fuel_entries (user_id, change_seq);
@@ -0,0 +1,12 @@
+-- First response to each request made with an Idempotency-Key, replayed for retries.
+-- `status_code` stays NULL while the original request is still being processed.
+CREATE TABLE idempotency_keys (
+    key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    status_code INTEGER,
+    response_headers TEXT,
+    response_body BLOB,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys (created_at);
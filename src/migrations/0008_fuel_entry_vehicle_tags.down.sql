@@ -0,0 +1,4 @@
+DROP INDEX idx_fuel_entries_user_vehicle;
+
+ALTER TABLE fuel_entries DROP COLUMN tags;
+ALTER TABLE fuel_entries DROP COLUMN vehicle;
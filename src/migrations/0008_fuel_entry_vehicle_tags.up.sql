@@ -0,0 +1,6 @@
+-- Optional vehicle name and free-form tags (a JSON array of strings) for
+-- grouping and filtering entries.
+ALTER TABLE fuel_entries ADD COLUMN vehicle TEXT;
+ALTER TABLE fuel_entries ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+
+CREATE INDEX idx_fuel_entries_user_vehicle ON fuel_entries (user_id, vehicle);
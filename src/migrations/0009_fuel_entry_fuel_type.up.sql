@@ -0,0 +1,2 @@
+-- Optional fuel type of a fill-up (e.g. "diesel", "e10"), stored lowercase.
+ALTER TABLE fuel_entries ADD COLUMN fuel_type TEXT;
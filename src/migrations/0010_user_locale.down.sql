@@ -0,0 +1,2 @@
+ALTER TABLE users DROP COLUMN hemisphere;
+ALTER TABLE users DROP COLUMN time_zone;
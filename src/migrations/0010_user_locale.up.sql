@@ -0,0 +1,4 @@
+-- IANA time zone and hemisphere of each user, used to bucket analytics by
+-- local hour and season.
+ALTER TABLE users ADD COLUMN time_zone TEXT NOT NULL DEFAULT 'UTC';
+ALTER TABLE users ADD COLUMN hemisphere TEXT NOT NULL DEFAULT 'northern';
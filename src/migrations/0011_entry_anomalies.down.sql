@@ -0,0 +1,6 @@
+DROP TABLE vehicles;
+
+DROP INDEX idx_fuel_entries_anomaly_status;
+
+ALTER TABLE fuel_entries DROP COLUMN anomaly_status;
+ALTER TABLE fuel_entries DROP COLUMN anomalies;
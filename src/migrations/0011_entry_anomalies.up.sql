@@ -0,0 +1,17 @@
+-- Anomalies the detector found in each entry (a JSON array) and their review
+-- state: NULL when nothing was found, otherwise 'pending', 'dismissed' or
+-- 'confirmed'.
+ALTER TABLE fuel_entries ADD COLUMN anomalies TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE fuel_entries ADD COLUMN anomaly_status TEXT;
+
+CREATE INDEX idx_fuel_entries_anomaly_status ON fuel_entries (anomaly_status);
+
+-- Tank capacity per vehicle name, matched case-insensitively against entries
+CREATE TABLE vehicles (
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,
+    tank_capacity_liters REAL NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, name),
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
@@ -0,0 +1,3 @@
+DROP TABLE notification_channels;
+DROP TABLE budget_alerts;
+DROP TABLE budgets;
@@ -0,0 +1,47 @@
+-- Spending limits per calendar month or year of the user's time zone, for one
+-- vehicle or, when `vehicle` is NULL, for all of the user's entries.
+CREATE TABLE budgets (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    period TEXT NOT NULL,
+    vehicle TEXT COLLATE NOCASE,
+    amount REAL NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX idx_budgets_user_id ON budgets (user_id);
+
+-- One alert per budget, period and crossed threshold. Alerts are the in-app
+-- notification list; `delivered_at` is set once the user's other channels
+-- have been tried.
+CREATE TABLE budget_alerts (
+    id TEXT PRIMARY KEY,
+    budget_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    period_label TEXT NOT NULL,
+    threshold INTEGER NOT NULL,
+    spent REAL NOT NULL,
+    amount REAL NOT NULL,
+    message TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    read_at TEXT,
+    delivered_at TEXT,
+    UNIQUE (budget_id, period_label, threshold)
+);
+
+CREATE INDEX idx_budget_alerts_user_id ON budget_alerts (user_id, created_at);
+CREATE INDEX idx_budget_alerts_delivered_at ON budget_alerts (delivered_at);
+
+-- Email addresses alerts are sent to besides the in-app list
+CREATE TABLE notification_channels (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    target TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX idx_notification_channels_user_id ON notification_channels (user_id);
@@ -1,6 +1,8 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use chrono::{DateTime, Utc};
+
+use crate::validation::FieldError;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct User {
@@ -8,16 +10,12 @@ pub struct User {
     pub email: String,
     pub password_hash: String,
     pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub time_zone: String,
+    pub hemisphere: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
-pub struct FuelEntryDB {
-    pub id: String,
-    pub user_id: String,
-    pub data: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct FuelEntry {
     pub id: String,
     pub user_id: String,
@@ -26,6 +24,400 @@ pub struct FuelEntry {
     pub total_cost: f64,
     pub date_time: DateTime<Utc>,
     pub odometer_reading: Option<f64>,
+    // Free-text vehicle name (e.g. "Golf" or a plate) and labels for grouping
+    #[serde(default)]
+    pub vehicle: Option<String>,
+    #[serde(default)]
+    pub fuel_type: Option<String>,
+    #[serde(default)]
+    pub tags: sqlx::types::Json<Vec<String>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    // Incremented on every write and exposed as the entry's ETag. Revision
+    // snapshots recorded before versions existed deserialize with 0.
+    #[serde(default)]
+    pub version: i64,
+    // Set by the anomaly detector after every write, never by clients
+    #[serde(default)]
+    pub anomalies: sqlx::types::Json<Vec<EntryAnomaly>>,
+    // `AnomalyStatus` of a flagged entry; None when nothing was found
+    #[serde(default)]
+    pub anomaly_status: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    // Far from the user's usual price per liter for the fuel type
+    PriceOutlier,
+    // Far above the user's usual fill-up volume for the vehicle
+    VolumeOutlier,
+    ExceedsTankCapacity,
+    // Liters per 100 km since the previous odometer reading no car can reach
+    ImpossibleEconomy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryAnomaly {
+    pub kind: AnomalyKind,
+    pub message: String,
+}
+
+// Review state of a flagged entry. Pending and confirmed entries are the ones
+// left out of analytics on request; dismissed ones were judged correct.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyStatus {
+    Pending,
+    Dismissed,
+    Confirmed,
+}
+
+impl AnomalyStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyStatus::Pending => "pending",
+            AnomalyStatus::Dismissed => "dismissed",
+            AnomalyStatus::Confirmed => "confirmed",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnomalyQueueQuery {
+    // Defaults to pending
+    pub status: Option<AnomalyStatus>,
+    pub user_id: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewAnomalyRequest {
+    pub status: AnomalyStatus,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Vehicle {
+    pub name: String,
+    pub tank_capacity_liters: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetVehicleRequest {
+    pub tank_capacity_liters: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Monthly,
+    Yearly,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Monthly => "monthly",
+            BudgetPeriod::Yearly => "yearly",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "monthly" => Some(BudgetPeriod::Monthly),
+            "yearly" => Some(BudgetPeriod::Yearly),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Budget {
+    pub id: String,
+    pub user_id: String,
+    // `BudgetPeriod` name
+    pub period: String,
+    // None covers every entry of the user
+    pub vehicle: Option<String>,
+    pub amount: f64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBudgetRequest {
+    pub period: BudgetPeriod,
+    pub vehicle: Option<String>,
+    pub amount: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateBudgetRequest {
+    pub amount: f64,
+}
+
+// Spending against a budget in the period containing now
+#[derive(Debug, Serialize)]
+pub struct BudgetProgress {
+    #[serde(flatten)]
+    pub budget: Budget,
+    // "2026-10" for months, "2026" for years
+    pub period_label: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub spent: f64,
+    pub remaining: f64,
+    pub percent_used: f64,
+    // Spend at the end of the period if the pace so far continues
+    pub projected_spend: f64,
+    pub projected_percent: f64,
+    // on_track, at_risk (projected over the amount) or over_budget
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct BudgetAlert {
+    pub id: String,
+    pub budget_id: String,
+    pub user_id: String,
+    pub period_label: String,
+    // Percentage of the budget that was reached
+    pub threshold: i64,
+    pub spent: f64,
+    pub amount: f64,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationQuery {
+    #[serde(default)]
+    pub unread: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarkNotificationsReadRequest {
+    // Every unread notification when omitted
+    pub ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationChannelKind {
+    Email,
+}
+
+impl NotificationChannelKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationChannelKind::Email => "email",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct NotificationChannelConfig {
+    pub id: String,
+    pub user_id: String,
+    // `NotificationChannelKind` name
+    pub kind: String,
+    // Email address
+    pub target: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateNotificationChannelRequest {
+    pub kind: NotificationChannelKind,
+    // Defaults to the account's email address for email channels
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "fuel_entry.created")]
+    FuelEntryCreated,
+    #[serde(rename = "fuel_entry.updated")]
+    FuelEntryUpdated,
+    #[serde(rename = "fuel_entry.deleted")]
+    FuelEntryDeleted,
+    #[serde(rename = "fuel_entry.restored")]
+    FuelEntryRestored,
+    #[serde(rename = "user.created")]
+    UserCreated,
+    #[serde(rename = "user.deleted")]
+    UserDeleted,
+    #[serde(rename = "user.restored")]
+    UserRestored,
+    #[serde(rename = "budget.alert")]
+    BudgetAlert,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::FuelEntryCreated => "fuel_entry.created",
+            WebhookEvent::FuelEntryUpdated => "fuel_entry.updated",
+            WebhookEvent::FuelEntryDeleted => "fuel_entry.deleted",
+            WebhookEvent::FuelEntryRestored => "fuel_entry.restored",
+            WebhookEvent::UserCreated => "user.created",
+            WebhookEvent::UserDeleted => "user.deleted",
+            WebhookEvent::UserRestored => "user.restored",
+            WebhookEvent::BudgetAlert => "budget.alert",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Webhook {
+    pub id: String,
+    // None for admin-level subscriptions, which receive every user's events
+    pub user_id: Option<String>,
+    pub url: String,
+    // Only handed out when the webhook is created
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub events: sqlx::types::Json<Vec<WebhookEvent>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// A new webhook together with its signing secret
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    // Gave up after the last retry
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Delivered => "delivered",
+            WebhookDeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub event: String,
+    // Exactly the bytes that are signed and sent
+    #[serde(skip_serializing)]
+    pub payload: String,
+    // `WebhookDeliveryStatus` name
+    pub status: String,
+    pub attempts: i64,
+    // When the next attempt is due while pending
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_status_code: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct WebhookDeliveryAttempt {
+    pub attempted_at: DateTime<Utc>,
+    pub status_code: Option<i64>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+}
+
+// A delivery with its payload and every attempt, for the delivery log
+#[derive(Debug, Serialize)]
+pub struct WebhookDeliveryLog {
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub payload: serde_json::Value,
+    pub attempts_log: Vec<WebhookDeliveryAttempt>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookDeliveryQuery {
+    pub status: Option<WebhookDeliveryStatus>,
+    pub limit: Option<i64>,
+}
+
+// Who made a change to a fuel entry, recorded with every revision
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionSource {
+    User,
+    Admin,
+    Import,
+}
+
+impl RevisionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevisionSource::User => "user",
+            RevisionSource::Admin => "admin",
+            RevisionSource::Import => "import",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub id: String,
+    pub source: RevisionSource,
+}
+
+impl Actor {
+    pub fn user(user_id: &str) -> Self {
+        Actor {
+            id: user_id.to_string(),
+            source: RevisionSource::User,
+        }
+    }
+
+    pub fn admin() -> Self {
+        Actor {
+            id: "admin".to_string(),
+            source: RevisionSource::Admin,
+        }
+    }
+
+    pub fn import(user_id: &str) -> Self {
+        Actor {
+            id: user_id.to_string(),
+            source: RevisionSource::Import,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct FuelEntryRevision {
+    pub id: String,
+    pub entry_id: String,
+    pub user_id: String,
+    pub revision: i64,
+    pub action: String,
+    pub old_values: Option<sqlx::types::Json<FuelEntry>>,
+    pub new_values: Option<sqlx::types::Json<FuelEntry>>,
+    pub actor: String,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +426,20 @@ pub struct SignupRequest {
     pub password: String,
 }
 
+// Omitted fields keep their current value
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserSettingsRequest {
+    pub time_zone: Option<String>,
+    pub hemisphere: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserSettings {
+    pub user_id: String,
+    pub time_zone: String,
+    pub hemisphere: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SigninRequest {
     pub email: String,
@@ -49,26 +455,117 @@ pub struct AuthResponse {
 #[derive(Debug, Deserialize)]
 pub struct CreateFuelEntryRequest {
     pub user_id: String,
-    pub liters: f64,
-    pub price_per_liter: f64,
-    pub total_cost: f64,
+    // Optional client-generated UUID so offline clients can refer to the entry before upload
+    pub id: Option<String>,
+    // Any two of liters, price_per_liter and total_cost are enough; the third is derived
+    pub liters: Option<f64>,
+    pub price_per_liter: Option<f64>,
+    pub total_cost: Option<f64>,
     pub date_time: DateTime<Utc>,
     pub odometer_reading: Option<f64>,
+    pub vehicle: Option<String>,
+    pub fuel_type: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // ISO 4217 code used to round derived amounts, defaults to USD
+    pub currency: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateFuelEntriesRequest {
     pub user_id: String,
     pub entries: Vec<FuelEntryData>,
+    #[serde(default)]
+    pub duplicate_check: DuplicateCheck,
+    #[serde(default)]
+    pub mode: BulkMode,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkMode {
+    // All valid entries are created or none is
+    #[default]
+    Atomic,
+    // Valid entries are created even if others in the batch are invalid
+    BestEffort,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateMatch {
+    // Every data field identical
+    #[default]
+    Exact,
+    // Same day with small differences in liters and cost, see `DuplicatePolicy`
+    Fuzzy,
+    // Import everything
+    None,
+}
+
+// How bulk imports recognise entries that were already uploaded
+#[derive(Debug, Deserialize, Default)]
+pub struct DuplicateCheck {
+    #[serde(default)]
+    pub mode: DuplicateMatch,
+    pub liters_tolerance: Option<f64>,
+    pub cost_tolerance: Option<f64>,
+    pub day_tolerance: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkEntryStatus {
+    Created,
+    Duplicate,
+    Invalid,
+    // Valid, but not stored because an atomic batch contained invalid entries
+    RolledBack,
+}
+
+// Outcome for one input of a bulk create, in input order
+#[derive(Debug, Serialize)]
+pub struct BulkEntryResult {
+    pub index: usize,
+    pub status: BulkEntryStatus,
+    // Id of the created (or rolled back) entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    // For duplicates: the entry matched, and the earlier input if it was in this batch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched_entry_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched_index: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl BulkEntryResult {
+    pub fn new(index: usize, status: BulkEntryStatus) -> Self {
+        BulkEntryResult {
+            index,
+            status,
+            id: None,
+            matched_entry_id: None,
+            matched_index: None,
+            errors: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct FuelEntryData {
-    pub liters: f64,
-    pub price_per_liter: f64,
-    pub total_cost: f64,
+    pub id: Option<String>,
+    pub liters: Option<f64>,
+    pub price_per_liter: Option<f64>,
+    pub total_cost: Option<f64>,
     pub date_time: DateTime<Utc>,
     pub odometer_reading: Option<f64>,
+    pub vehicle: Option<String>,
+    pub fuel_type: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub currency: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -78,6 +575,175 @@ pub struct UpdateFuelEntryRequest {
     pub total_cost: Option<f64>,
     pub date_time: Option<DateTime<Utc>>,
     pub odometer_reading: Option<f64>,
+    // An empty string clears the vehicle or fuel type; tags replace the existing list
+    pub vehicle: Option<String>,
+    pub fuel_type: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub currency: Option<String>,
+}
+
+impl UpdateFuelEntryRequest {
+    // True when the patch would not change any field
+    pub fn is_empty(&self) -> bool {
+        self.liters.is_none()
+            && self.price_per_liter.is_none()
+            && self.total_cost.is_none()
+            && self.date_time.is_none()
+            && self.odometer_reading.is_none()
+            && self.vehicle.is_none()
+            && self.fuel_type.is_none()
+            && self.tags.is_none()
+    }
+}
+
+// Selects live entries of one user; every given condition must hold
+#[derive(Debug, Deserialize, Default)]
+pub struct FuelEntryFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub min_price_per_liter: Option<f64>,
+    pub max_price_per_liter: Option<f64>,
+    pub min_total_cost: Option<f64>,
+    pub max_total_cost: Option<f64>,
+    pub min_liters: Option<f64>,
+    pub max_liters: Option<f64>,
+    pub vehicle: Option<String>,
+    pub fuel_type: Option<String>,
+    // Entries carrying this tag
+    pub tag: Option<String>,
+    // true keeps only entries with unresolved anomalies, false leaves them out
+    pub flagged: Option<bool>,
+}
+
+impl FuelEntryFilter {
+    pub fn is_empty(&self) -> bool {
+        self.from.is_none()
+            && self.to.is_none()
+            && self.min_price_per_liter.is_none()
+            && self.max_price_per_liter.is_none()
+            && self.min_total_cost.is_none()
+            && self.max_total_cost.is_none()
+            && self.min_liters.is_none()
+            && self.max_liters.is_none()
+            && self.vehicle.is_none()
+            && self.fuel_type.is_none()
+            && self.tag.is_none()
+            && self.flagged.is_none()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FuelEntrySort {
+    #[default]
+    DateTime,
+    TotalCost,
+    Liters,
+    PricePerLiter,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+// Query string of the entry list. The filter fields are repeated rather than
+// flattened because flattened numbers do not parse from a query string.
+#[derive(Debug, Deserialize)]
+pub struct FuelEntryListQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub min_price_per_liter: Option<f64>,
+    pub max_price_per_liter: Option<f64>,
+    pub min_total_cost: Option<f64>,
+    pub max_total_cost: Option<f64>,
+    pub min_liters: Option<f64>,
+    pub max_liters: Option<f64>,
+    pub vehicle: Option<String>,
+    pub fuel_type: Option<String>,
+    pub tag: Option<String>,
+    pub flagged: Option<bool>,
+    #[serde(default)]
+    pub sort: FuelEntrySort,
+    #[serde(default)]
+    pub order: SortOrder,
+    // Page size; without it every matching entry is returned
+    pub limit: Option<i64>,
+    // `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl FuelEntryListQuery {
+    pub fn filter(&self) -> FuelEntryFilter {
+        FuelEntryFilter {
+            from: self.from,
+            to: self.to,
+            min_price_per_liter: self.min_price_per_liter,
+            max_price_per_liter: self.max_price_per_liter,
+            min_total_cost: self.min_total_cost,
+            max_total_cost: self.max_total_cost,
+            min_liters: self.min_liters,
+            max_liters: self.max_liters,
+            vehicle: self.vehicle.clone(),
+            fuel_type: self.fuel_type.clone(),
+            tag: self.tag.clone(),
+            flagged: self.flagged,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FuelEntryPage {
+    pub entries: Vec<FuelEntry>,
+    // Matching entries across all pages
+    pub total_count: i64,
+    pub next_cursor: Option<String>,
+}
+
+// Either `updates` (one patch per entry) or `filter` together with `patch`
+#[derive(Debug, Deserialize)]
+pub struct BulkUpdateFuelEntriesRequest {
+    pub user_id: String,
+    #[serde(default)]
+    pub mode: BulkMode,
+    pub updates: Option<Vec<FuelEntryPatch>>,
+    pub filter: Option<FuelEntryFilter>,
+    pub patch: Option<UpdateFuelEntryRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FuelEntryPatch {
+    pub id: String,
+    // Version the patch was based on; required, an Option only so that a
+    // missing one is reported as a field error
+    pub base_version: Option<i64>,
+    pub patch: UpdateFuelEntryRequest,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkUpdateStatus {
+    Updated,
+    NotFound,
+    Conflict,
+    Invalid,
+    // Valid, but not stored because an atomic batch had failures
+    RolledBack,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkUpdateResult {
+    pub index: usize,
+    pub id: String,
+    pub status: BulkUpdateStatus,
+    // Entry version after the update, or the current version on a conflict
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,24 +752,218 @@ pub struct DeleteFuelEntriesRequest {
     pub entry_ids: Vec<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RestoreFuelEntriesRequest {
+    pub user_id: String,
+    pub entry_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncQuery {
+    pub user_id: String,
+    // Cursor returned by the previous sync; omit or 0 for a full download
+    pub since: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+// One row of the change feed: the entry's latest state (a tombstone when
+// `deleted_at` is set) and the sequence number of that change
+#[derive(Debug, Serialize, FromRow)]
+pub struct SyncChange {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub entry: FuelEntry,
+    pub change_seq: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncChanges {
+    pub changes: Vec<SyncChange>,
+    // Pass as `since` on the next call
+    pub cursor: i64,
+    pub has_more: bool,
+    // Set when `since` predates purged tombstones; the client must drop its
+    // local copy and sync again from 0
+    pub reset: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum SyncOperation {
+    Create {
+        #[serde(flatten)]
+        entry: FuelEntryData,
+    },
+    Update {
+        id: String,
+        // Version the client's edit was based on; required, an Option only so
+        // that a missing one is reported as a field error
+        base_version: Option<i64>,
+        #[serde(flatten)]
+        changes: UpdateFuelEntryRequest,
+    },
+    Delete {
+        id: String,
+        // Required like the one of `Update`
+        base_version: Option<i64>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncPushRequest {
+    pub user_id: String,
+    pub operations: Vec<SyncOperation>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    Applied,
+    // Nothing to do, e.g. a create that was already uploaded
+    Unchanged,
+    Conflict,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncResult {
+    pub index: usize,
+    pub id: String,
+    pub status: SyncStatus,
+    // Why a conflict was not applied: version_mismatch, deleted, not_found or id_taken
+    pub reason: Option<String>,
+    // Server copy of the entry after the operation, absent if the client may not see it
+    pub entry: Option<FuelEntry>,
+}
+
+// Stored outcome of the first request made with an Idempotency-Key
+#[derive(Debug, FromRow)]
+pub struct IdempotencyRecord {
+    pub request_hash: String,
+    // None while that request is still being processed
+    pub status_code: Option<i64>,
+    pub response_headers: Option<sqlx::types::Json<Vec<(String, String)>>>,
+    pub response_body: Option<Vec<u8>>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct DashboardStats {
+    // When the statistics were computed; older than now for cached responses
+    pub generated_at: DateTime<Utc>,
+    // Sections that were not requested are left out of the response
+    #[serde(flatten)]
+    pub summary: Option<DashboardSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub users_with_most_entries: Option<Vec<UserEntryCount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub most_expensive_entries: Option<Vec<FuelEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent_entries: Option<Vec<FuelEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly_stats: Option<Vec<MonthlyStats>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_registration_stats: Option<Vec<UserRegistrationStats>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fuel_efficiency_stats: Option<FuelEfficiencyStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consumption_patterns: Option<ConsumptionPatterns>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_analytics: Option<CostAnalytics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_behavior_stats: Option<UserBehaviorStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predictive_analytics: Option<PredictiveAnalytics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_trends: Option<PriceTrends>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DashboardSummary {
     pub total_users: i32,
     pub total_fuel_entries: i32,
     pub total_fuel_cost: f64,
     pub total_liters: f64,
     pub average_price_per_liter: f64,
-    pub users_with_most_entries: Vec<UserEntryCount>,
-    pub most_expensive_entries: Vec<FuelEntry>,
-    pub recent_entries: Vec<FuelEntry>,
-    pub monthly_stats: Vec<MonthlyStats>,
-    pub user_registration_stats: Vec<UserRegistrationStats>,
-    pub fuel_efficiency_stats: FuelEfficiencyStats,
-    pub consumption_patterns: ConsumptionPatterns,
-    pub cost_analytics: CostAnalytics,
-    pub user_behavior_stats: UserBehaviorStats,
-    pub predictive_analytics: PredictiveAnalytics,
-    pub price_trends: PriceTrends,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DashboardSection {
+    Summary,
+    UsersWithMostEntries,
+    MostExpensiveEntries,
+    RecentEntries,
+    MonthlyStats,
+    UserRegistrationStats,
+    FuelEfficiencyStats,
+    ConsumptionPatterns,
+    CostAnalytics,
+    UserBehaviorStats,
+    PredictiveAnalytics,
+    PriceTrends,
+}
+
+impl DashboardSection {
+    pub const ALL: [DashboardSection; 12] = [
+        DashboardSection::Summary,
+        DashboardSection::UsersWithMostEntries,
+        DashboardSection::MostExpensiveEntries,
+        DashboardSection::RecentEntries,
+        DashboardSection::MonthlyStats,
+        DashboardSection::UserRegistrationStats,
+        DashboardSection::FuelEfficiencyStats,
+        DashboardSection::ConsumptionPatterns,
+        DashboardSection::CostAnalytics,
+        DashboardSection::UserBehaviorStats,
+        DashboardSection::PredictiveAnalytics,
+        DashboardSection::PriceTrends,
+    ];
+
+    // Name used in the `sections` query parameter, same as the response field
+    pub fn name(&self) -> &'static str {
+        match self {
+            DashboardSection::Summary => "summary",
+            DashboardSection::UsersWithMostEntries => "users_with_most_entries",
+            DashboardSection::MostExpensiveEntries => "most_expensive_entries",
+            DashboardSection::RecentEntries => "recent_entries",
+            DashboardSection::MonthlyStats => "monthly_stats",
+            DashboardSection::UserRegistrationStats => "user_registration_stats",
+            DashboardSection::FuelEfficiencyStats => "fuel_efficiency_stats",
+            DashboardSection::ConsumptionPatterns => "consumption_patterns",
+            DashboardSection::CostAnalytics => "cost_analytics",
+            DashboardSection::UserBehaviorStats => "user_behavior_stats",
+            DashboardSection::PredictiveAnalytics => "predictive_analytics",
+            DashboardSection::PriceTrends => "price_trends",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DashboardSection> {
+        DashboardSection::ALL
+            .into_iter()
+            .find(|section| section.name() == name)
+    }
+}
+
+// Query string of the admin dashboard; lists are comma-separated
+#[derive(Debug, Deserialize)]
+pub struct DashboardQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub user_ids: Option<String>,
+    pub vehicle: Option<String>,
+    pub fuel_type: Option<String>,
+    pub sections: Option<String>,
+    // Leaves entries with unresolved anomalies out of every section
+    #[serde(default)]
+    pub exclude_flagged: bool,
+}
+
+// Entries and users the dashboard figures are computed over. The date range,
+// vehicle and fuel type apply to entries; registrations are limited to users
+// who signed up within the date range.
+#[derive(Debug, Default)]
+pub struct DashboardFilter {
+    pub entries: FuelEntryFilter,
+    // Empty means every user
+    pub user_ids: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -125,6 +985,50 @@ pub struct MonthlyStats {
     pub average_price: f64,
 }
 
+// Personal statistics of one user, see `get_user_stats`
+#[derive(Debug, Serialize)]
+pub struct UserStats {
+    pub user_id: String,
+    pub total_entries: i32,
+    pub total_cost: f64,
+    pub total_liters: f64,
+    pub average_price_paid: f64,
+    pub monthly_stats: Vec<MonthlyStats>,
+    pub economy_trend: Vec<EconomyTrendPoint>,
+    pub total_distance: Option<f64>,
+    pub cost_per_km: Option<f64>,
+    pub longest_gap_between_fills: Option<FillGap>,
+    pub year_over_year: Vec<YearComparison>,
+}
+
+// Fuel economy of the fill-ups in one month, measured between odometer readings
+#[derive(Debug, Serialize)]
+pub struct EconomyTrendPoint {
+    pub month: String,
+    pub distance: f64,
+    pub liters: f64,
+    pub liters_per_100km: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FillGap {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub days: i64,
+}
+
+// Yearly totals; the changes are relative to the previous calendar year
+#[derive(Debug, Serialize)]
+pub struct YearComparison {
+    pub year: i32,
+    pub total_entries: i32,
+    pub total_cost: f64,
+    pub total_liters: f64,
+    pub average_price: f64,
+    pub cost_change_percent: Option<f64>,
+    pub liters_change_percent: Option<f64>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserRegistrationStats {
     pub month: String,
@@ -209,9 +1113,9 @@ pub struct SeasonalPattern {
 
 #[derive(Debug, Serialize)]
 pub struct FillUpPatterns {
-    pub small_fillups: i32,    // < 10 liters
-    pub medium_fillups: i32,   // 10-30 liters
-    pub large_fillups: i32,    // > 30 liters
+    pub small_fillups: i32,  // < 10 liters
+    pub medium_fillups: i32, // 10-30 liters
+    pub large_fillups: i32,  // > 30 liters
     pub average_fillup_size: f64,
     pub most_common_fillup_range: String,
 }
@@ -226,19 +1130,37 @@ pub struct CostAnalytics {
 
 #[derive(Debug, Serialize)]
 pub struct CostDistribution {
-    pub low_cost_entries: i32,     // < $50
-    pub medium_cost_entries: i32,  // $50-$150
-    pub high_cost_entries: i32,    // > $150
+    pub low_cost_entries: i32,    // below the 25th percentile of entry cost
+    pub medium_cost_entries: i32, // 25th to 75th percentile
+    pub high_cost_entries: i32,   // above the 75th percentile
     pub cost_ranges: Vec<CostRange>,
+    pub entry_cost: DistributionSummary,
+    pub price_per_liter: DistributionSummary,
 }
 
 #[derive(Debug, Serialize)]
 pub struct CostRange {
     pub range: String,
+    pub lower_bound: f64,
+    pub upper_bound: f64,
     pub count: i32,
     pub percentage: f64,
 }
 
+// Percentiles interpolate linearly between the nearest ranks
+#[derive(Debug, Serialize)]
+pub struct DistributionSummary {
+    pub count: i32,
+    pub mean: f64,
+    pub min: f64,
+    pub p10: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SpendingTrend {
     pub month: String,
@@ -260,6 +1182,7 @@ pub struct BudgetAnalysis {
 pub struct CostPerUserStats {
     pub average_cost_per_user: f64,
     pub median_cost_per_user: f64,
+    pub per_user_spend: DistributionSummary,
     pub top_spenders: Vec<UserSpending>,
     pub cost_distribution_by_user: Vec<UserCostCategory>,
 }
@@ -349,6 +1272,18 @@ pub struct RetentionAnalysis {
     pub new_user_retention_30_day: f64,
     pub active_user_retention: f64,
     pub churn_rate: f64,
+    pub churned_users: i32,
+    pub churn_inactivity_days: i64,
+    pub signup_cohorts: Vec<SignupCohort>,
+}
+
+// `retention[k]` is the percentage of the cohort active in the k-th month after
+// the signup month `cohort` (YYYY-MM), starting with the signup month itself
+#[derive(Debug, Serialize)]
+pub struct SignupCohort {
+    pub cohort: String,
+    pub users: i32,
+    pub retention: Vec<f64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -357,13 +1292,19 @@ pub struct PredictiveAnalytics {
     pub consumption_forecast: Vec<ConsumptionForecast>,
     pub user_growth_forecast: Vec<UserGrowthForecast>,
     pub revenue_projections: RevenueProjections,
+    // Accuracy of the same models on the most recent months, None with too little history
+    pub price_backtest: Option<ForecastBacktest>,
+    pub consumption_backtest: Option<ForecastBacktest>,
 }
 
+// `confidence_level` is the level of the `lower_bound`..`upper_bound` prediction interval
 #[derive(Debug, Serialize)]
 pub struct PriceForecast {
     pub month: String,
     pub year: i32,
     pub predicted_price: f64,
+    pub lower_bound: f64,
+    pub upper_bound: f64,
     pub confidence_level: f64,
     pub trend_direction: String,
 }
@@ -373,9 +1314,19 @@ pub struct ConsumptionForecast {
     pub month: String,
     pub year: i32,
     pub predicted_consumption: f64,
+    pub lower_bound: f64,
+    pub upper_bound: f64,
     pub predicted_entries: i32,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ForecastBacktest {
+    pub holdout_months: i32,
+    pub mean_absolute_error: f64,
+    // None when nothing was recorded in the held-out months
+    pub mean_absolute_percentage_error: Option<f64>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserGrowthForecast {
     pub month: String,
@@ -416,12 +1367,28 @@ pub struct PriceVolatility {
     pub price_standard_deviation: f64,
     pub most_volatile_period: String,
     pub least_volatile_period: String,
+    pub window_months: i32,
+    pub monthly_volatility: Vec<PeriodVolatility>,
 }
 
+// Price spread over the `window_months` months ending with `month`/`year`
+#[derive(Debug, Serialize)]
+pub struct PeriodVolatility {
+    pub period: String,
+    pub month: String,
+    pub year: i32,
+    pub entry_count: i32,
+    pub price_standard_deviation: f64,
+    pub volatility_index: f64,
+}
+
+// Changes are percentages of the average price in `current_period`, the
+// latest month with entries; None when the earlier month has no entries
 #[derive(Debug, Serialize)]
 pub struct PriceComparisons {
-    pub current_vs_last_month: f64,
-    pub current_vs_last_year: f64,
+    pub current_period: String,
+    pub current_vs_last_month: Option<f64>,
+    pub current_vs_last_year: Option<f64>,
     pub lowest_recorded_price: f64,
     pub highest_recorded_price: f64,
     pub price_change_percentage: f64,
@@ -464,5 +1431,6 @@ pub struct AdminLoginRequest {
 pub struct AdminLoginResponse {
     pub success: bool,
     pub token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
     pub message: String,
-}
\ No newline at end of file
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::validation::ValidationError;
+
+// Currency used for rounding when a request does not name one
+const DEFAULT_CURRENCY: &str = "USD";
+
+// Liters are rounded to what pumps display, independent of currency
+const LITERS_DECIMALS: u32 = 3;
+
+// Minor-unit decimals for currencies whose precision differs from the default of 2.
+// Extra entries can be supplied at startup via `FUEL_CURRENCY_DECIMALS`, e.g. "JPY=0,KWD=3".
+const BUILTIN_CURRENCY_DECIMALS: &[(&str, u32)] = &[
+    ("BHD", 3),
+    ("CLP", 0),
+    ("IQD", 3),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("OMR", 3),
+    ("TND", 3),
+    ("VND", 0),
+];
+
+fn currency_decimals_table() -> &'static HashMap<String, u32> {
+    static TABLE: OnceLock<HashMap<String, u32>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table: HashMap<String, u32> = BUILTIN_CURRENCY_DECIMALS
+            .iter()
+            .map(|(code, decimals)| (code.to_string(), *decimals))
+            .collect();
+
+        if let Ok(overrides) = std::env::var("FUEL_CURRENCY_DECIMALS") {
+            for pair in overrides.split(',') {
+                let Some((code, decimals)) = pair.split_once('=') else {
+                    eprintln!("Ignoring malformed FUEL_CURRENCY_DECIMALS entry '{}'", pair);
+                    continue;
+                };
+                match decimals.trim().parse::<u32>() {
+                    Ok(decimals) => {
+                        table.insert(code.trim().to_uppercase(), decimals);
+                    }
+                    Err(_) => {
+                        eprintln!("Ignoring malformed FUEL_CURRENCY_DECIMALS entry '{}'", pair)
+                    }
+                }
+            }
+        }
+
+        table
+    })
+}
+
+pub fn currency_decimals(currency: Option<&str>) -> u32 {
+    let code = currency.unwrap_or(DEFAULT_CURRENCY).to_uppercase();
+    currency_decimals_table().get(&code).copied().unwrap_or(2)
+}
+
+pub fn round_to(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+// Fills in whichever of liters, price_per_liter and total_cost is missing.
+// Totals are rounded to the currency's minor unit and prices get one extra
+// decimal, matching how pumps display them. When all three are given they are
+// returned unchanged and the consistency check in validation rejects conflicts.
+pub fn resolve_amounts(
+    liters: Option<f64>,
+    price_per_liter: Option<f64>,
+    total_cost: Option<f64>,
+    currency: Option<&str>,
+) -> Result<(f64, f64, f64), ValidationError> {
+    let money_decimals = currency_decimals(currency);
+    let price_decimals = money_decimals + 1;
+
+    match (liters, price_per_liter, total_cost) {
+        (Some(liters), Some(price), Some(total)) => Ok((liters, price, total)),
+        (Some(liters), Some(price), None) => {
+            Ok((liters, price, round_to(liters * price, money_decimals)))
+        }
+        (Some(liters), None, Some(total)) if liters > 0.0 => {
+            Ok((liters, round_to(total / liters, price_decimals), total))
+        }
+        (None, Some(price), Some(total)) if price > 0.0 => {
+            Ok((round_to(total / price, LITERS_DECIMALS), price, total))
+        }
+        (Some(_), None, Some(_)) => {
+            let mut errors = ValidationError::default();
+            errors.add("liters", "must be greater than 0 to derive price_per_liter");
+            Err(errors)
+        }
+        (None, Some(_), Some(_)) => {
+            let mut errors = ValidationError::default();
+            errors.add("price_per_liter", "must be greater than 0 to derive liters");
+            Err(errors)
+        }
+        _ => {
+            let mut errors = ValidationError::default();
+            for (field, value) in [
+                ("liters", liters),
+                ("price_per_liter", price_per_liter),
+                ("total_cost", total_cost),
+            ] {
+                if value.is_none() {
+                    errors.add(
+                        field,
+                        "is required unless the other two of liters, price_per_liter and total_cost are given",
+                    );
+                }
+            }
+            Err(errors)
+        }
+    }
+}
+
+// Applies a partial update of the amounts on top of `existing` (liters, price, total).
+// Two or three given fields behave as on create. A single changed field keeps
+// price_per_liter fixed and recomputes the other amount, except when the price
+// itself changes, in which case liters stay fixed and the total is recomputed.
+pub fn resolve_updated_amounts(
+    existing: (f64, f64, f64),
+    liters: Option<f64>,
+    price_per_liter: Option<f64>,
+    total_cost: Option<f64>,
+    currency: Option<&str>,
+) -> Result<(f64, f64, f64), ValidationError> {
+    let (existing_liters, existing_price, _) = existing;
+
+    match (liters, price_per_liter, total_cost) {
+        (None, None, None) => Ok(existing),
+        (Some(liters), None, None) => {
+            resolve_amounts(Some(liters), Some(existing_price), None, currency)
+        }
+        (None, Some(price), None) => {
+            resolve_amounts(Some(existing_liters), Some(price), None, currency)
+        }
+        (None, None, Some(total)) => {
+            resolve_amounts(None, Some(existing_price), Some(total), currency)
+        }
+        _ => resolve_amounts(liters, price_per_liter, total_cost, currency),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FuelEntry;
+    use crate::validation::validate_fuel_entry;
+    use serde_json::json;
+
+    #[test]
+    fn derives_the_missing_amount() {
+        assert_eq!(
+            resolve_amounts(Some(40.0), Some(1.659), None, None).unwrap(),
+            (40.0, 1.659, 66.36)
+        );
+        assert_eq!(
+            resolve_amounts(Some(40.0), None, Some(66.36), None).unwrap(),
+            (40.0, 1.659, 66.36)
+        );
+        assert_eq!(
+            resolve_amounts(None, Some(1.659), Some(66.36), None).unwrap(),
+            (40.0, 1.659, 66.36)
+        );
+    }
+
+    #[test]
+    fn rounds_to_the_currency_minor_unit() {
+        assert_eq!(
+            resolve_amounts(Some(33.3), Some(172.4), None, Some("jpy")).unwrap(),
+            (33.3, 172.4, 5741.0)
+        );
+        assert_eq!(
+            resolve_amounts(Some(20.0), None, Some(7.5), Some("KWD")).unwrap(),
+            (20.0, 0.375, 7.5)
+        );
+        assert_eq!(currency_decimals(Some("EUR")), 2);
+    }
+
+    #[test]
+    fn keeps_three_given_amounts() {
+        assert_eq!(
+            resolve_amounts(Some(40.0), Some(1.5), Some(99.0), None).unwrap(),
+            (40.0, 1.5, 99.0)
+        );
+    }
+
+    #[test]
+    fn reports_missing_or_zero_amounts() {
+        let errors = resolve_amounts(Some(40.0), None, None, None).unwrap_err();
+        let fields: Vec<&str> = errors.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["price_per_liter", "total_cost"]);
+
+        let errors = resolve_amounts(Some(0.0), None, Some(10.0), None).unwrap_err();
+        assert_eq!(errors.errors[0].field, "liters");
+        let errors = resolve_amounts(None, Some(0.0), Some(10.0), None).unwrap_err();
+        assert_eq!(errors.errors[0].field, "price_per_liter");
+    }
+
+    #[test]
+    fn single_field_updates_keep_the_price() {
+        let existing = (40.0, 1.5, 60.0);
+        assert_eq!(
+            resolve_updated_amounts(existing, Some(30.0), None, None, None).unwrap(),
+            (30.0, 1.5, 45.0)
+        );
+        assert_eq!(
+            resolve_updated_amounts(existing, None, None, Some(75.0), None).unwrap(),
+            (50.0, 1.5, 75.0)
+        );
+        assert_eq!(
+            resolve_updated_amounts(existing, None, Some(2.0), None, None).unwrap(),
+            (40.0, 2.0, 80.0)
+        );
+        assert_eq!(
+            resolve_updated_amounts(existing, None, None, None, None).unwrap(),
+            existing
+        );
+    }
+
+    #[test]
+    fn derived_totals_pass_validation() {
+        // Rounded to whole yen: 5398.51 becomes 5399
+        let (liters, price_per_liter, total_cost) =
+            resolve_amounts(Some(31.7), Some(170.3), None, Some("JPY")).unwrap();
+        assert_eq!(total_cost, 5399.0);
+        let entry: FuelEntry = serde_json::from_value(json!({
+            "id": "e1",
+            "user_id": "u1",
+            "liters": liters,
+            "price_per_liter": price_per_liter,
+            "total_cost": total_cost,
+            "date_time": "2026-01-01T10:00:00Z",
+        }))
+        .unwrap();
+
+        assert!(validate_fuel_entry(&entry).is_empty());
+    }
+}
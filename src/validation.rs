@@ -0,0 +1,365 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Serialize;
+use std::fmt;
+
+use crate::models::{
+    CreateBudgetRequest, CreateWebhookRequest, FuelEntry, NotificationChannelKind,
+    SetVehicleRequest, UpdateUserSettingsRequest,
+};
+use crate::webhooks::is_public_url;
+
+// Generous upper bounds that still catch obvious typos (e.g. an extra zero)
+const MAX_LITERS: f64 = 1_000.0;
+const MAX_PRICE_PER_LITER: f64 = 10_000.0;
+const MAX_ODOMETER: f64 = 10_000_000.0;
+const MAX_BUDGET_AMOUNT: f64 = 100_000_000.0;
+
+// A total may differ from liters * price_per_liter by pump rounding; allow the
+// larger of an absolute and a relative difference before calling it inconsistent.
+const COST_TOLERANCE_ABSOLUTE: f64 = 0.05;
+const COST_TOLERANCE_RELATIVE: f64 = 0.01;
+
+// Device clocks drift, so entries a few minutes in the future are accepted
+const FUTURE_DATE_LEEWAY_MINUTES: i64 = 5;
+
+const MAX_VEHICLE_LENGTH: usize = 64;
+const MAX_FUEL_TYPE_LENGTH: usize = 32;
+const MAX_TAGS: usize = 20;
+const MAX_TAG_LENGTH: usize = 32;
+
+pub const DEFAULT_TIME_ZONE: &str = "UTC";
+pub const DEFAULT_HEMISPHERE: &str = "northern";
+const HEMISPHERES: [&str; 2] = ["northern", "southern"];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationError {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationError {
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn merge(&mut self, other: ValidationError) {
+        self.errors.extend(other.errors);
+    }
+
+    // Used by bulk operations so each error points at the offending item, e.g. `entries[3].liters`
+    pub fn extend_prefixed(&mut self, prefix: &str, other: ValidationError) {
+        for error in other.errors {
+            self.errors.push(FieldError {
+                field: format!("{}.{}", prefix, error.field),
+                message: error.message,
+            });
+        }
+    }
+
+    pub fn into_result(self) -> Result<(), ValidationError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self
+            .errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+// Checks user settings after trimming; the hemisphere is compared lowercase
+pub fn validate_user_settings(request: &UpdateUserSettingsRequest) -> ValidationError {
+    let mut errors = ValidationError::default();
+
+    if let Some(time_zone) = &request.time_zone {
+        if time_zone.trim().parse::<chrono_tz::Tz>().is_err() {
+            errors.add(
+                "time_zone",
+                "must be an IANA time zone name, e.g. Europe/Berlin",
+            );
+        }
+    }
+    if let Some(hemisphere) = &request.hemisphere {
+        if !HEMISPHERES.contains(&hemisphere.trim().to_lowercase().as_str()) {
+            errors.add(
+                "hemisphere",
+                format!("must be one of: {}", HEMISPHERES.join(", ")),
+            );
+        }
+    }
+
+    errors
+}
+
+// `name` is the vehicle name from the path, as given
+pub fn validate_vehicle(name: &str, request: &SetVehicleRequest) -> ValidationError {
+    let mut errors = ValidationError::default();
+
+    match normalize_vehicle(Some(name)) {
+        None => errors.add("name", "must not be blank"),
+        Some(name) if name.chars().count() > MAX_VEHICLE_LENGTH => errors.add(
+            "name",
+            format!("must not exceed {} characters", MAX_VEHICLE_LENGTH),
+        ),
+        Some(_) => {}
+    }
+
+    let capacity = request.tank_capacity_liters;
+    if !capacity.is_finite() || capacity <= 0.0 {
+        errors.add("tank_capacity_liters", "must be greater than 0");
+    } else if capacity > MAX_LITERS {
+        errors.add(
+            "tank_capacity_liters",
+            format!("must not exceed {}", MAX_LITERS),
+        );
+    }
+
+    errors
+}
+
+pub fn validate_budget_amount(amount: f64) -> ValidationError {
+    let mut errors = ValidationError::default();
+
+    if !amount.is_finite() || amount <= 0.0 {
+        errors.add("amount", "must be greater than 0");
+    } else if amount > MAX_BUDGET_AMOUNT {
+        errors.add("amount", format!("must not exceed {}", MAX_BUDGET_AMOUNT));
+    }
+
+    errors
+}
+
+pub fn validate_budget(request: &CreateBudgetRequest) -> ValidationError {
+    let mut errors = validate_budget_amount(request.amount);
+
+    if let Some(vehicle) = normalize_vehicle(request.vehicle.as_deref()) {
+        if vehicle.chars().count() > MAX_VEHICLE_LENGTH {
+            errors.add(
+                "vehicle",
+                format!("must not exceed {} characters", MAX_VEHICLE_LENGTH),
+            );
+        }
+    }
+
+    errors
+}
+
+// Email targets must be valid addresses
+pub fn validate_notification_target(
+    kind: NotificationChannelKind,
+    target: &str,
+) -> ValidationError {
+    let mut errors = ValidationError::default();
+
+    match kind {
+        NotificationChannelKind::Email => {
+            if target.parse::<lettre::Address>().is_err() {
+                errors.add("target", "must be a valid email address");
+            }
+        }
+    }
+
+    errors
+}
+
+pub fn validate_webhook(request: &CreateWebhookRequest) -> ValidationError {
+    let mut errors = ValidationError::default();
+
+    match reqwest::Url::parse(request.url.trim()) {
+        Ok(url) if is_public_url(&url) => {}
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            errors.add("url", "must not point to a local or private address");
+        }
+        _ => errors.add("url", "must be an http or https URL"),
+    }
+    if request.events.is_empty() {
+        errors.add("events", "must contain at least one event");
+    }
+
+    errors
+}
+
+// Trims the vehicle name; blank means no vehicle
+pub fn normalize_vehicle(vehicle: Option<&str>) -> Option<String> {
+    vehicle
+        .map(str::trim)
+        .filter(|vehicle| !vehicle.is_empty())
+        .map(str::to_string)
+}
+
+// Fuel types compare case-insensitively, so they are stored trimmed and lowercase
+pub fn normalize_fuel_type(fuel_type: Option<&str>) -> Option<String> {
+    normalize_vehicle(fuel_type).map(|fuel_type| fuel_type.to_lowercase())
+}
+
+// Trims tags and drops blanks and repeats, keeping the given order
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+fn earliest_allowed_date() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(1990, 1, 1, 0, 0, 0).unwrap()
+}
+
+pub fn validate_fuel_entry(entry: &FuelEntry) -> ValidationError {
+    let mut errors = ValidationError::default();
+
+    if !entry.liters.is_finite() || entry.liters <= 0.0 {
+        errors.add("liters", "must be greater than 0");
+    } else if entry.liters > MAX_LITERS {
+        errors.add("liters", format!("must not exceed {}", MAX_LITERS));
+    }
+
+    if !entry.price_per_liter.is_finite() || entry.price_per_liter <= 0.0 {
+        errors.add("price_per_liter", "must be greater than 0");
+    } else if entry.price_per_liter > MAX_PRICE_PER_LITER {
+        errors.add(
+            "price_per_liter",
+            format!("must not exceed {}", MAX_PRICE_PER_LITER),
+        );
+    }
+
+    if !entry.total_cost.is_finite() || entry.total_cost <= 0.0 {
+        errors.add("total_cost", "must be greater than 0");
+    }
+
+    // Only compare the total once its inputs are individually valid
+    if errors.is_empty() {
+        let expected = entry.liters * entry.price_per_liter;
+        let tolerance = COST_TOLERANCE_ABSOLUTE.max(expected * COST_TOLERANCE_RELATIVE);
+        if (entry.total_cost - expected).abs() > tolerance {
+            errors.add(
+                "total_cost",
+                format!(
+                    "does not match liters * price_per_liter (expected about {:.2}, got {:.2})",
+                    expected, entry.total_cost
+                ),
+            );
+        }
+    }
+
+    if entry.date_time > Utc::now() + Duration::minutes(FUTURE_DATE_LEEWAY_MINUTES) {
+        errors.add("date_time", "must not be in the future");
+    } else if entry.date_time < earliest_allowed_date() {
+        errors.add("date_time", "must not be before 1990-01-01");
+    }
+
+    if let Some(odometer) = entry.odometer_reading {
+        if !odometer.is_finite() || odometer < 0.0 {
+            errors.add("odometer_reading", "must not be negative");
+        } else if odometer > MAX_ODOMETER {
+            errors.add(
+                "odometer_reading",
+                format!("must not exceed {}", MAX_ODOMETER),
+            );
+        }
+    }
+
+    if let Some(vehicle) = &entry.vehicle {
+        if vehicle.chars().count() > MAX_VEHICLE_LENGTH {
+            errors.add(
+                "vehicle",
+                format!("must not exceed {} characters", MAX_VEHICLE_LENGTH),
+            );
+        }
+    }
+
+    if let Some(fuel_type) = &entry.fuel_type {
+        if fuel_type.chars().count() > MAX_FUEL_TYPE_LENGTH {
+            errors.add(
+                "fuel_type",
+                format!("must not exceed {} characters", MAX_FUEL_TYPE_LENGTH),
+            );
+        }
+    }
+
+    if entry.tags.len() > MAX_TAGS {
+        errors.add(
+            "tags",
+            format!("must not contain more than {} tags", MAX_TAGS),
+        );
+    }
+    if entry
+        .tags
+        .iter()
+        .any(|tag| tag.chars().count() > MAX_TAG_LENGTH)
+    {
+        errors.add(
+            "tags",
+            format!("each tag must not exceed {} characters", MAX_TAG_LENGTH),
+        );
+    }
+
+    errors
+}
+
+// Odometer readings must not decrease over time. `previous` and `next` are the
+// closest entries (by date) of the same user and vehicle that carry an odometer reading.
+pub fn validate_odometer_order(
+    entry: &FuelEntry,
+    previous: Option<&FuelEntry>,
+    next: Option<&FuelEntry>,
+) -> ValidationError {
+    let mut errors = ValidationError::default();
+
+    let Some(odometer) = entry.odometer_reading else {
+        return errors;
+    };
+
+    if let Some((prev, prev_reading)) = previous.and_then(|p| p.odometer_reading.map(|o| (p, o))) {
+        if odometer < prev_reading {
+            errors.add(
+                "odometer_reading",
+                format!(
+                    "must be at least {} (reading of the earlier entry on {})",
+                    prev_reading,
+                    prev.date_time.format("%Y-%m-%d")
+                ),
+            );
+        }
+    }
+
+    if let Some((next, next_reading)) = next.and_then(|n| n.odometer_reading.map(|o| (n, o))) {
+        if odometer > next_reading {
+            errors.add(
+                "odometer_reading",
+                format!(
+                    "must be at most {} (reading of the later entry on {})",
+                    next_reading,
+                    next.date_time.format("%Y-%m-%d")
+                ),
+            );
+        }
+    }
+
+    errors
+}
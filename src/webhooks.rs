@@ -0,0 +1,325 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::database::{get_due_webhook_deliveries, record_webhook_attempt};
+use crate::models::{Webhook, WebhookDelivery};
+
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+// Endpoints sent to at the same time by one run of the delivery job
+const MAX_CONCURRENT_WEBHOOKS: usize = 8;
+
+// A run stops sending to an endpoint once it has spent this long on it; the
+// rest of its deliveries stay due for the next run
+const WEBHOOK_TIME_BUDGET_SECS: u64 = 30;
+
+// A delivery is given up on after this many failed attempts
+pub const WEBHOOK_MAX_ATTEMPTS: i64 = 8;
+
+// Retries wait 30 seconds, then twice as long after every further failure, up
+// to 6 hours
+const RETRY_BASE_DELAY_SECS: i64 = 30;
+const RETRY_MAX_DELAY_SECS: i64 = 6 * 60 * 60;
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+pub fn new_webhook_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+// "t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<payload>" keyed with the
+// secret>". The timestamp is signed too so receivers can reject replays.
+pub fn signature_header(secret: &str, timestamp: i64, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    format!("t={},v1={:x}", timestamp, mac.finalize().into_bytes())
+}
+
+// Wait before the next attempt once `failed_attempts` attempts have failed
+pub fn retry_delay(failed_attempts: i64) -> Duration {
+    let exponent = (failed_attempts - 1).clamp(0, 20) as u32;
+    Duration::seconds((RETRY_BASE_DELAY_SECS << exponent).min(RETRY_MAX_DELAY_SECS))
+}
+
+// Whether an address is reachable on the public internet. Webhooks must not
+// reach loopback, private, link-local, shared (CGNAT) or other special-purpose
+// addresses, which would let users probe services behind the server.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+                    || ip.segments()[..2] == [0x2001, 0x0db8]
+                    || ip.segments()[..6] == [0x64, 0xff9b, 0, 0, 0, 0])
+            }
+        },
+    }
+}
+
+// Whether `url` may be used as a webhook target: http(s) with a host that is
+// not local. Names are checked again when they are resolved for a delivery.
+pub fn is_public_url(url: &Url) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    if let Some(domain) = url.domain() {
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        return domain != "localhost" && !domain.ends_with(".localhost");
+    }
+    // Otherwise the host is an IP literal, with brackets around IPv6 ones
+    url.host_str()
+        .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+        .and_then(|host| host.parse::<IpAddr>().ok())
+        .is_some_and(is_public_address)
+}
+
+// Resolves webhook hosts with the system resolver and drops every address that
+// is not public, so a name cannot point deliveries at internal services
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_address(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+// Redirects are not followed, since they could lead to an address the checks
+// above reject; proxies are ignored so the resolved address is the one dialled
+pub fn webhook_client() -> Result<reqwest::Client> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy()
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()?;
+    Ok(client)
+}
+
+// reqwest's own message leaves out why a request failed, e.g. that the host
+// has no public address
+fn error_with_sources(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+// Makes one attempt at `delivery` and records the outcome. Any 2xx response
+// counts as delivered. Returns whether it was.
+async fn attempt_delivery(
+    pool: &SqlitePool,
+    client: &reqwest::Client,
+    delivery: &WebhookDelivery,
+    webhook: &Webhook,
+) -> Result<bool> {
+    let started = std::time::Instant::now();
+    // IP literals never reach the resolver, and the URL may predate the checks
+    let response = match Url::parse(&webhook.url) {
+        Ok(url) if is_public_url(&url) => Ok(client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &delivery.event)
+            .header(DELIVERY_HEADER, &delivery.id)
+            .header(
+                SIGNATURE_HEADER,
+                signature_header(&webhook.secret, Utc::now().timestamp(), &delivery.payload),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await),
+        _ => Err("URL does not point to a public address".to_string()),
+    };
+    let duration_ms = started.elapsed().as_millis() as i64;
+
+    let (status_code, error) = match response {
+        Ok(Ok(response)) if response.status().is_success() => {
+            (Some(response.status().as_u16() as i64), None)
+        }
+        Ok(Ok(response)) => (
+            Some(response.status().as_u16() as i64),
+            Some(format!("HTTP {}", response.status())),
+        ),
+        Ok(Err(e)) => (None, Some(error_with_sources(&e))),
+        Err(e) => (None, Some(e)),
+    };
+    record_webhook_attempt(pool, delivery, status_code, error.as_deref(), duration_ms).await?;
+
+    Ok(error.is_none())
+}
+
+// Makes one attempt at every delivery that is due. Endpoints are sent to
+// concurrently, up to `MAX_CONCURRENT_WEBHOOKS` at a time, so a slow one does
+// not hold back the others. Deliveries to one endpoint go out in the order
+// they were queued; after a failure, or once `WEBHOOK_TIME_BUDGET_SECS` are
+// spent on it, the endpoint's remaining deliveries wait for the next run.
+// Returns the number of attempts made.
+pub async fn deliver_due_webhooks(pool: &SqlitePool, client: &reqwest::Client) -> Result<usize> {
+    let mut by_webhook: Vec<Vec<(WebhookDelivery, Webhook)>> = Vec::new();
+    for (delivery, webhook) in get_due_webhook_deliveries(pool, Utc::now()).await? {
+        match by_webhook
+            .iter_mut()
+            .find(|group| group[0].1.id == webhook.id)
+        {
+            Some(group) => group.push((delivery, webhook)),
+            None => by_webhook.push(vec![(delivery, webhook)]),
+        }
+    }
+
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_WEBHOOKS));
+    let mut tasks = JoinSet::new();
+    for group in by_webhook {
+        let (pool, client, semaphore) = (pool.clone(), client.clone(), semaphore.clone());
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await?;
+            let budget = std::time::Duration::from_secs(WEBHOOK_TIME_BUDGET_SECS);
+            let started = std::time::Instant::now();
+            let mut attempts = 0;
+            for (delivery, webhook) in &group {
+                attempts += 1;
+                let delivered = attempt_delivery(&pool, &client, delivery, webhook).await?;
+                if !delivered || started.elapsed() >= budget {
+                    break;
+                }
+            }
+            anyhow::Ok(attempts)
+        });
+    }
+
+    let mut attempts = 0;
+    let mut first_error = None;
+    while let Some(result) = tasks.join_next().await {
+        match result
+            .map_err(anyhow::Error::from)
+            .and_then(|result| result)
+        {
+            Ok(made) => attempts += made,
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(attempts),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_a_cap() {
+        assert_eq!(retry_delay(1), Duration::seconds(30));
+        assert_eq!(retry_delay(2), Duration::seconds(60));
+        assert_eq!(retry_delay(4), Duration::seconds(240));
+        assert_eq!(retry_delay(12), Duration::hours(6));
+        assert_eq!(retry_delay(WEBHOOK_MAX_ATTEMPTS * 100), Duration::hours(6));
+        // Before any failure the first delay applies
+        assert_eq!(retry_delay(0), Duration::seconds(30));
+    }
+
+    #[test]
+    fn signature_covers_the_timestamp_and_payload() {
+        let payload = r#"{"event":"user.created"}"#;
+        assert_eq!(
+            signature_header("whsec_test", 1767225600, payload),
+            "t=1767225600,v1=20682055bebcb2d03779c2bab909ef4c8cba7cac52a226af3fd7fc0f4f9ccb89"
+        );
+        assert_ne!(
+            signature_header("whsec_test", 1767225601, payload),
+            signature_header("whsec_test", 1767225600, payload)
+        );
+    }
+
+    #[test]
+    fn only_public_addresses_are_allowed() {
+        for ip in ["93.184.215.14", "8.8.8.8", "2606:4700::1111"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "224.0.0.1",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn local_urls_are_rejected() {
+        let allowed = |url: &str| is_public_url(&Url::parse(url).unwrap());
+
+        assert!(allowed("https://hooks.example.com/fuel"));
+        assert!(allowed("http://93.184.215.14:8080/"));
+        assert!(!allowed("http://localhost:3000/"));
+        assert!(!allowed("http://api.localhost/"));
+        assert!(!allowed("http://127.0.0.1/"));
+        assert!(!allowed("http://[::1]/"));
+        assert!(!allowed("http://169.254.169.254/latest/meta-data/"));
+        assert!(!allowed("ftp://hooks.example.com/"));
+    }
+}